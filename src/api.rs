@@ -0,0 +1,200 @@
+// yamc as a library: a document held in memory rendered the way `convert`
+// renders one, with what a program laying out a site needs besides the page.
+// nothing is read from files unless an option names one
+use crate::{
+    command_line, console, front_matter, parse_options, render_page, within_budget, Arguments,
+    Document, Failed, Options, STDIN,
+};
+use clap::FromArgMatches;
+use std::ffi::OsString;
+use std::fmt;
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Duration;
+
+// how `render_document` renders, the options `yamc convert` takes
+#[derive(Clone)]
+pub struct RenderOptions {
+    options: Options,
+}
+
+impl RenderOptions {
+    // the options of `yamc convert ARGUMENTS`, such as `["--toc", "--sanitize"]`.
+    // yamc.toml and the environment are not read, files an option names are
+    pub fn from_arguments<A: Into<OsString>>(
+        arguments: impl IntoIterator<Item = A>,
+    ) -> Result<RenderOptions, Error> {
+        let mut argv: Vec<OsString> = vec!["yamc".into(), "convert".into()];
+        argv.extend(arguments.into_iter().map(Into::into));
+        argv.push(STDIN.into());
+        let matches = command_line()
+            .try_get_matches_from(&argv)
+            .map_err(|err| Error::usage(err.to_string()))?;
+        let (name, submatches) = matches.subcommand().expect("convert was given");
+        let arguments =
+            Arguments::from_arg_matches(submatches).map_err(|err| Error::usage(err.to_string()))?;
+        let ((parsed, _), said) =
+            console::noting(|| console::hold(|| parse_options(name, arguments, submatches, &argv)));
+        match parsed {
+            Ok((_, options)) => Ok(RenderOptions { options }),
+            Err(failed) => Err(Error::new(failed, said)),
+        }
+    }
+}
+
+impl Default for RenderOptions {
+    fn default() -> RenderOptions {
+        RenderOptions::from_arguments(Vec::<OsString>::new()).expect("the defaults are valid")
+    }
+}
+
+// a heading of the document, with the id its anchor ended up with
+pub struct Heading {
+    pub level: usize,
+    pub id: String,
+    pub text: String,
+}
+
+// something worth knowing about the document that did not stop it
+pub struct Warning {
+    pub code: &'static str,
+    // 1-based, 0 when the warning is not about a line
+    pub line: usize,
+    pub message: String,
+}
+
+pub struct RenderedDocument {
+    // the rendered markdown, without the page around it
+    pub html_body: String,
+    // the whole page, as `convert` would write it
+    pub full_document: String,
+    pub title: String,
+    // the top level front matter keys, lists as arrays
+    pub metadata: serde_json::Map<String, serde_json::Value>,
+    pub outline: Vec<Heading>,
+    // the relative urls of the files the page refers to
+    pub assets: Vec<String>,
+    pub warnings: Vec<Warning>,
+    pub parse_time: Duration,
+    pub render_time: Duration,
+}
+
+// why a document was not rendered: `kind` is what the --json report calls
+// it, `message` what yamc would have said
+#[derive(Debug)]
+pub struct Error {
+    kind: &'static str,
+    message: String,
+}
+
+impl Error {
+    fn new(failed: Failed, said: Option<String>) -> Error {
+        Error {
+            kind: failed.kind(),
+            message: said.unwrap_or_else(|| format!("{} failure", failed.kind())),
+        }
+    }
+
+    fn usage(message: String) -> Error {
+        Error {
+            kind: Failed::Usage.kind(),
+            message,
+        }
+    }
+
+    pub fn kind(&self) -> &str {
+        self.kind
+    }
+
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.message.trim_end())
+    }
+}
+
+impl std::error::Error for Error {}
+
+// render `markdown` with `options`. includes are left as they are written,
+// there is no directory to find them in
+pub fn render_document(markdown: &str, options: RenderOptions) -> Result<RenderedDocument, Error> {
+    let mut options = options.options;
+    options.text = Some(Arc::from(markdown));
+    options.includes = false;
+    let ((document, _), said) = console::noting(|| {
+        console::hold(|| within_budget(Path::new(STDIN), &options, render_page))
+    });
+    let Document { rendered, page } = document.map_err(|failed| Error::new(failed, said))?;
+    Ok(RenderedDocument {
+        html_body: rendered.html,
+        full_document: page,
+        title: rendered.title,
+        metadata: rendered
+            .front_matter
+            .as_deref()
+            .map(front_matter::fields)
+            .unwrap_or_default(),
+        outline: rendered
+            .outline
+            .into_iter()
+            .map(|entry| Heading {
+                level: entry.level,
+                id: entry.id,
+                text: entry.text,
+            })
+            .collect(),
+        assets: rendered.references,
+        warnings: rendered
+            .warnings
+            .into_iter()
+            .map(|warning| Warning {
+                code: warning.code,
+                line: warning.line,
+                message: warning.message,
+            })
+            .collect(),
+        parse_time: rendered.timings.parse,
+        render_time: rendered.timings.render,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn a_string_is_rendered_with_its_outline_and_metadata() {
+        let markdown = "---\ntitle: Notes\ntags: [a, b]\n---\n# Start\n\ntext\n\n## Then\n";
+        let options = RenderOptions::from_arguments(["--toc"]).unwrap();
+        let document = render_document(markdown, options).unwrap();
+        assert!(document.html_body.contains("<p>text</p>"));
+        assert!(document.full_document.contains(&document.html_body));
+        assert_eq!(document.title, "Notes");
+        assert_eq!(document.metadata["tags"], serde_json::json!(["a", "b"]));
+        let outline: Vec<_> = document
+            .outline
+            .iter()
+            .map(|heading| (heading.level, heading.text.as_str()))
+            .collect();
+        assert_eq!(outline, [(1, "Start"), (2, "Then")]);
+        assert!(document
+            .html_body
+            .contains(&format!("href=\"#{}\"", document.outline[1].id)));
+    }
+
+    #[test]
+    fn bad_options_and_failed_documents_are_errors() {
+        let error = RenderOptions::from_arguments(["--no-such-option"])
+            .err()
+            .unwrap();
+        assert_eq!(error.kind(), "usage");
+        let options = RenderOptions::from_arguments(["--max-input-size", "4"]).unwrap();
+        let error = render_document("# too long", options).err().unwrap();
+        assert_eq!(error.kind(), "conversion");
+        assert!(error.message().contains("--max-input-size"));
+    }
+}
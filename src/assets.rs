@@ -0,0 +1,337 @@
+use crate::diagnostic::Diagnostic;
+#[cfg(not(feature = "offline-bundle"))]
+use crate::download;
+use crate::explain;
+use crate::paths;
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use comrak::nodes::{AstNode, NodeValue};
+#[cfg(not(feature = "offline-bundle"))]
+use std::collections::HashMap;
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, OnceLock};
+
+// point root-relative references such as `/assets/img/logo.png` into
+// `asset_root`. a relative root gives urls relative to the current directory,
+// an absolute one file urls. returns a warning for every reference that does
+// not exist under the root
+pub fn resolve_root<'a>(root: &'a AstNode<'a>, asset_root: &Path, path: &Path) -> Vec<Diagnostic> {
+    let mut warnings = Vec::new();
+    for node in root.descendants() {
+        let line = explain::line(node);
+        let mut data = node.data.borrow_mut();
+        let link = match data.value {
+            NodeValue::Link(ref mut link) | NodeValue::Image(ref mut link) => link,
+            _ => continue,
+        };
+        let url = String::from_utf8_lossy(&link.url).to_string();
+        let rest = match url.strip_prefix('/') {
+            Some(rest) if !rest.starts_with('/') => rest,
+            _ => continue,
+        };
+        let end = rest.find(['?', '#']).unwrap_or(rest.len());
+        let target = asset_root.join(&rest[..end]);
+        if !target.exists() {
+            warnings.push(
+                Diagnostic::warning(
+                    "missing-asset",
+                    path,
+                    line,
+                    format!("{} not found under {}", url, paths::display(asset_root)),
+                )
+                .pointing_at(&url),
+            );
+        }
+        let resolved = if asset_root.is_absolute() {
+            paths::to_file_url(&target)
+        } else {
+            paths::to_url(&target)
+        };
+        link.url = format!("{}{}", resolved, &rest[end..]).into_bytes();
+    }
+    warnings
+}
+
+fn media_type(path: &Path) -> Option<&'static str> {
+    let extension = path.extension()?.to_str()?.to_ascii_lowercase();
+    Some(match extension.as_str() {
+        "png" => "image/png",
+        "jpg" | "jpeg" => "image/jpeg",
+        "gif" => "image/gif",
+        "svg" => "image/svg+xml",
+        "webp" => "image/webp",
+        "bmp" => "image/bmp",
+        "ico" => "image/x-icon",
+        _ => return None,
+    })
+}
+
+// the page icon, linked or as a data url
+#[derive(Clone)]
+pub struct Icon {
+    pub href: String,
+    pub media_type: Option<&'static str>,
+}
+
+// the media type of an icon, browsers take .ico, .png and .svg ones
+fn icon_type(path: &Path) -> Option<&'static str> {
+    media_type(path)
+        .filter(|media_type| matches!(*media_type, "image/x-icon" | "image/png" | "image/svg+xml"))
+}
+
+// the icon `source` stands for: a url is linked, a local file is read into a
+// data url the way --embed-images does, so the page needs nothing next to it
+pub fn icon(source: &str) -> Result<Icon, String> {
+    if source.contains("://") || source.starts_with("//") || source.starts_with("data:") {
+        let path = source.split(['?', '#']).next().unwrap_or("");
+        return Ok(Icon {
+            href: source.to_string(),
+            media_type: icon_type(Path::new(path)),
+        });
+    }
+    let path = Path::new(source);
+    let media_type =
+        icon_type(path).ok_or_else(|| format!("{} is not an .ico, .png or .svg file", source))?;
+    let bytes = fs::read(path).map_err(|err| format!("cannot read {}: {}", source, err))?;
+    Ok(Icon {
+        href: format!("data:{};base64,{}", media_type, STANDARD.encode(bytes)),
+        media_type: Some(media_type),
+    })
+}
+
+pub const DEFAULT_MAX_EMBED_SIZE: u64 = 5 * 1024 * 1024;
+
+// replace local images with data urls so the html is self-contained. paths
+// are relative to the document, images larger than `max_size` bytes stay
+// links. returns a warning for every image left as is
+pub fn embed_images<'a>(root: &'a AstNode<'a>, path: &Path, max_size: u64) -> Vec<Diagnostic> {
+    let directory = path.parent().unwrap_or(Path::new(""));
+    let mut warnings = Vec::new();
+    for node in root.descendants() {
+        let line = explain::line(node);
+        let warning = |message: String, url: &str| {
+            Diagnostic::warning("embed-image", path, line, message).pointing_at(url)
+        };
+        let mut data = node.data.borrow_mut();
+        let image = match data.value {
+            NodeValue::Image(ref mut image) => image,
+            _ => continue,
+        };
+        let url = String::from_utf8_lossy(&image.url).to_string();
+        let local = url.split(['?', '#']).next().unwrap_or("");
+        // --asset-root makes file urls of the references it resolves
+        let target = match local.strip_prefix("file://") {
+            Some(absolute) => PathBuf::from(absolute),
+            None if url.contains("://") || url.starts_with("data:") || url.starts_with("//") => {
+                continue
+            }
+            None => directory.join(local),
+        };
+        let media_type = match media_type(&target) {
+            Some(media_type) => media_type,
+            None => {
+                warnings.push(warning(
+                    format!("{} is not an image type that can be embedded", url),
+                    &url,
+                ));
+                continue;
+            }
+        };
+        match fs::metadata(&target) {
+            Ok(metadata) if metadata.len() > max_size => {
+                warnings.push(warning(
+                    format!(
+                        "{} is {} bytes, larger than --max-embed-size {}, linking it instead",
+                        url,
+                        metadata.len(),
+                        max_size
+                    ),
+                    &url,
+                ));
+                continue;
+            }
+            _ => {}
+        }
+        match fs::read(&target) {
+            Ok(bytes) => {
+                image.url =
+                    format!("data:{};base64,{}", media_type, STANDARD.encode(bytes)).into_bytes();
+            }
+            Err(err) => warnings.push(warning(format!("cannot embed {}: {}", url, err), &url)),
+        }
+    }
+    warnings
+}
+
+// the data url of a remote image, or why there is none. every url is fetched
+// once per run, however many documents use it
+#[cfg(not(feature = "offline-bundle"))]
+fn fetched(url: &str, max_size: u64) -> Result<String, String> {
+    static FETCHED: OnceLock<Mutex<HashMap<String, Result<String, String>>>> = OnceLock::new();
+    let mut fetched = FETCHED
+        .get_or_init(Default::default)
+        .lock()
+        .expect("no fetch panics while holding the lock");
+    fetched
+        .entry(url.to_string())
+        .or_insert_with(|| {
+            let (bytes, content_type) = download::bytes(url, max_size)?;
+            let path = url.split(['?', '#']).next().unwrap_or(url);
+            let media_type = content_type
+                .filter(|content_type| content_type.starts_with("image/"))
+                .or_else(|| media_type(Path::new(path)).map(str::to_string))
+                .ok_or_else(|| "not an image".to_string())?;
+            Ok(format!(
+                "data:{};base64,{}",
+                media_type,
+                STANDARD.encode(bytes)
+            ))
+        })
+        .clone()
+}
+
+// replace http(s) images with data urls of their downloads. an image that
+// cannot be fetched or is larger than `max_size` bytes keeps its url, with
+// a warning
+#[cfg(not(feature = "offline-bundle"))]
+pub fn fetch_images<'a>(root: &'a AstNode<'a>, path: &Path, max_size: u64) -> Vec<Diagnostic> {
+    let mut warnings = Vec::new();
+    for node in root.descendants() {
+        let line = explain::line(node);
+        let mut data = node.data.borrow_mut();
+        let image = match data.value {
+            NodeValue::Image(ref mut image) => image,
+            _ => continue,
+        };
+        let url = String::from_utf8_lossy(&image.url).to_string();
+        if !(url.starts_with("http://") || url.starts_with("https://") || url.starts_with("//")) {
+            continue;
+        }
+        match fetched(&url, max_size) {
+            Ok(data_url) => image.url = data_url.into_bytes(),
+            Err(err) => warnings.push(
+                Diagnostic::warning(
+                    "fetch-image",
+                    path,
+                    line,
+                    format!("cannot fetch {}: {}, linking it instead", url, err),
+                )
+                .pointing_at(&url),
+            ),
+        }
+    }
+    warnings
+}
+
+// the relative urls of files a document links to or shows, other than
+// markdown documents, without their `?query` or `#fragment`
+pub fn local_references<'a>(root: &'a AstNode<'a>) -> Vec<String> {
+    let mut references = Vec::new();
+    for node in root.descendants() {
+        let url = match node.data.borrow().value {
+            NodeValue::Link(ref link) | NodeValue::Image(ref link) => {
+                String::from_utf8_lossy(&link.url).to_string()
+            }
+            _ => continue,
+        };
+        if url.contains(':') || url.starts_with('/') || url.starts_with('#') || url.is_empty() {
+            continue;
+        }
+        let file = url.split(['?', '#']).next().unwrap_or("").to_string();
+        if !file.is_empty() && !paths::is_markdown(Path::new(&file)) && !references.contains(&file)
+        {
+            references.push(file);
+        }
+    }
+    references
+}
+
+// the files copied next to pages in this run, so one used by several pages is
+// copied once
+fn copied() -> &'static Mutex<HashSet<PathBuf>> {
+    static COPIED: OnceLock<Mutex<HashSet<PathBuf>>> = OnceLock::new();
+    COPIED.get_or_init(Default::default)
+}
+
+pub fn copied_count() -> usize {
+    copied()
+        .lock()
+        .expect("no copy panics while holding the lock")
+        .len()
+}
+
+// copy the files `references` names, relative to the document at `path`,
+// to the same place relative to its page, keeping the layout below the
+// page's directory. a file outside `input_root` is not copied and gets a
+// warning, a file that does not exist is left to the page
+pub fn copy(path: &Path, page: &Path, input_root: &Path, references: &[String]) -> Vec<Diagnostic> {
+    let mut warnings = Vec::new();
+    let (directory, page_directory, input_root) = match (
+        fs::canonicalize(path),
+        page.parent().map(|parent| {
+            fs::canonicalize(if parent.as_os_str().is_empty() {
+                Path::new(".")
+            } else {
+                parent
+            })
+        }),
+        fs::canonicalize(input_root),
+    ) {
+        (Ok(document), Some(Ok(page_directory)), Ok(input_root)) => (
+            document.parent().map(Path::to_path_buf).unwrap_or_default(),
+            page_directory,
+            input_root,
+        ),
+        _ => return warnings,
+    };
+    for reference in references {
+        let source = paths::normal(&directory.join(reference));
+        if !source.is_file() {
+            continue;
+        }
+        if !source.starts_with(&input_root) {
+            warnings.push(
+                Diagnostic::warning(
+                    "copy-asset",
+                    path,
+                    0,
+                    format!(
+                        "{} is outside {}, not copied",
+                        reference,
+                        paths::display(&input_root)
+                    ),
+                )
+                .pointing_at(reference),
+            );
+            continue;
+        }
+        let destination = paths::normal(&page_directory.join(reference));
+        if destination == source
+            || !copied()
+                .lock()
+                .expect("no copy panics while holding the lock")
+                .insert(destination.clone())
+        {
+            continue;
+        }
+        let result = destination
+            .parent()
+            .map_or(Ok(()), fs::create_dir_all)
+            .and_then(|_| fs::copy(&source, &destination));
+        if let Err(err) = result {
+            copied()
+                .lock()
+                .expect("no copy panics while holding the lock")
+                .remove(&destination);
+            warnings.push(Diagnostic::warning(
+                "copy-asset",
+                path,
+                0,
+                format!("cannot copy {}: {}", reference, err),
+            ));
+        }
+    }
+    warnings
+}
@@ -0,0 +1,201 @@
+use comrak::nodes::{AstNode, NodeLink, NodeValue};
+use comrak::Arena;
+use regex::Regex;
+
+const TLDS: &str = "com|org|net|io|dev|app|edu|gov|info|co|uk|de|fr|nl|eu|ca|au|in|jp|me|ai";
+
+// text of a link if it is all the link contains
+fn link_text<'a>(node: &'a AstNode<'a>) -> Option<String> {
+    let child = node.first_child()?;
+    if child.next_sibling().is_some() {
+        return None;
+    }
+    match child.data.borrow().value {
+        NodeValue::Text(ref literal) => Some(String::from_utf8_lossy(literal).to_string()),
+        _ => None,
+    }
+}
+
+// links the autolink extension (or `<url>`) produced show their own url,
+// possibly without the scheme added for `www.`, bare domains and email addresses
+fn is_automatic<'a>(node: &'a AstNode<'a>) -> bool {
+    let url = match node.data.borrow().value {
+        NodeValue::Link(ref link) => String::from_utf8_lossy(&link.url).to_string(),
+        _ => return false,
+    };
+    match link_text(node) {
+        Some(text) => {
+            url == text
+                || url == format!("http://{}", text)
+                || url == format!("https://{}", text)
+                || url == format!("mailto:{}", text)
+        }
+        None => false,
+    }
+}
+
+fn in_link<'a>(node: &'a AstNode<'a>) -> bool {
+    node.ancestors().any(|ancestor| {
+        matches!(
+            ancestor.data.borrow().value,
+            NodeValue::Link(..) | NodeValue::Image(..)
+        )
+    })
+}
+
+fn text<'a>(arena: &'a Arena<AstNode<'a>>, text: &str) -> &'a AstNode<'a> {
+    arena.alloc(AstNode::from(NodeValue::Text(text.as_bytes().to_vec())))
+}
+
+// trailing punctuation belongs to the sentence, not the link. a closing paren
+// stays when the link itself opened one
+fn trim_trailing(candidate: &str) -> &str {
+    let mut end = candidate.len();
+    loop {
+        let trimmed = &candidate[..end];
+        match trimmed.chars().last() {
+            Some('.' | ',' | ':' | ';' | '!' | '?' | '\'' | '"') => end -= 1,
+            Some(')') if trimmed.matches('(').count() < trimmed.matches(')').count() => end -= 1,
+            _ => return trimmed,
+        }
+    }
+}
+
+// link bare domains such as `example.com/docs` in text. a leading backslash
+// (`\example.com`) keeps the domain as plain text and is removed
+pub fn link_domains<'a>(arena: &'a Arena<AstNode<'a>>, root: &'a AstNode<'a>) {
+    let pattern = Regex::new(&format!(
+        r"(?i)(\\)?\b((?:[a-z0-9](?:[a-z0-9-]*[a-z0-9])?\.)+(?:{})\b(?:/[^\s<]*)?)",
+        TLDS
+    ))
+    .expect("domain pattern should compile");
+    let nodes: Vec<_> = root
+        .descendants()
+        .filter(|node| matches!(node.data.borrow().value, NodeValue::Text(..)))
+        .filter(|node| !in_link(node))
+        .collect();
+
+    for node in nodes {
+        let content = match node.data.borrow().value {
+            NodeValue::Text(ref literal) => String::from_utf8_lossy(literal).to_string(),
+            _ => continue,
+        };
+        let mut pieces = Vec::new();
+        let mut last = 0;
+        for captures in pattern.captures_iter(&content) {
+            let whole = captures.get(0).unwrap();
+            let domain = captures.get(2).unwrap();
+            // part of an email address, a longer word or a url comrak skipped
+            let before = content[..whole.start()].chars().last();
+            if matches!(before, Some('@' | '/' | '.' | ':' | '-')) {
+                continue;
+            }
+            if captures.get(1).is_some() {
+                pieces.push(text(arena, &content[last..whole.start()]));
+                pieces.push(text(arena, domain.as_str()));
+                last = whole.end();
+                continue;
+            }
+            let linked = trim_trailing(domain.as_str());
+            pieces.push(text(arena, &content[last..domain.start()]));
+            let link = arena.alloc(AstNode::from(NodeValue::Link(NodeLink {
+                url: format!("https://{}", linked).into_bytes(),
+                title: vec![],
+            })));
+            link.append(text(arena, linked));
+            pieces.push(link);
+            last = domain.start() + linked.len();
+        }
+        if pieces.is_empty() {
+            continue;
+        }
+        pieces.push(text(arena, &content[last..]));
+        for piece in pieces {
+            node.insert_before(piece);
+        }
+        node.detach();
+    }
+}
+
+// turn automatic links inside headings or tables back into plain text
+pub fn unwrap_in<'a>(root: &'a AstNode<'a>, headings: bool, tables: bool) {
+    let links: Vec<_> = root
+        .descendants()
+        .filter(|node| is_automatic(node))
+        .filter(|node| {
+            node.ancestors()
+                .any(|ancestor| match ancestor.data.borrow().value {
+                    NodeValue::Heading(..) => headings,
+                    NodeValue::TableCell => tables,
+                    _ => false,
+                })
+        })
+        .collect();
+    for link in links {
+        while let Some(child) = link.first_child() {
+            link.insert_before(child);
+        }
+        link.detach();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // `markdown` rendered the way --autolink `mode` renders it
+    fn render(markdown: &str, mode: &str) -> String {
+        let mut options = comrak::ComrakOptions::default();
+        options.extension.autolink = mode != "off";
+        let arena = Arena::new();
+        let root = comrak::parse_document(&arena, markdown, &options);
+        if mode == "aggressive" {
+            link_domains(&arena, root);
+        }
+        let mut html = vec![];
+        comrak::format_html(root, &options, &mut html).unwrap();
+        String::from_utf8(html).unwrap()
+    }
+
+    #[test]
+    fn trailing_punctuation_is_not_linked() {
+        let markdown = "see example.com. or www.example.org, or https://example.net/a).\n";
+        assert_eq!(
+            render(markdown, "off"),
+            "<p>see example.com. or www.example.org, or https://example.net/a).</p>\n"
+        );
+        assert_eq!(
+            render(markdown, "standard"),
+            "<p>see example.com. or <a href=\"http://www.example.org\">www.example.org</a>, \
+             or <a href=\"https://example.net/a\">https://example.net/a</a>).</p>\n"
+        );
+        assert_eq!(
+            render(markdown, "aggressive"),
+            "<p>see <a href=\"https://example.com\">example.com</a>. \
+             or <a href=\"http://www.example.org\">www.example.org</a>, \
+             or <a href=\"https://example.net/a\">https://example.net/a</a>).</p>\n"
+        );
+    }
+
+    #[test]
+    fn balanced_parens_stay_in_the_link() {
+        assert_eq!(
+            trim_trailing("example.com/wiki/a_(b)."),
+            "example.com/wiki/a_(b)"
+        );
+        assert_eq!(trim_trailing("example.com/a)!?"), "example.com/a");
+        assert_eq!(trim_trailing("example.com/\"a\"'"), "example.com/\"a");
+    }
+
+    #[test]
+    fn escaped_and_linked_domains_are_left_alone() {
+        assert_eq!(
+            render(
+                "\\example.com, me@example.com and `example.com`\n",
+                "aggressive"
+            ),
+            "<p>example.com, <a href=\"mailto:me@example.com\">me@example.com</a> \
+             and <code>example.com</code></p>\n"
+        );
+    }
+}
@@ -0,0 +1,107 @@
+use crate::front_matter;
+use crate::html::escape;
+use crate::paths;
+use std::fs;
+use std::path::{Path, PathBuf};
+use time::format_description::well_known::Rfc3339;
+use time::OffsetDateTime;
+
+pub const DEFAULT_EXHIBIT_FORMAT: &str = "Exhibit %d";
+
+// one source document of the binder, already rendered to html
+pub struct Exhibit {
+    pub path: PathBuf,
+    pub html: String,
+    pub front_matter: Option<String>,
+    pub summary: Option<String>,
+}
+
+fn label(format: &str, number: usize) -> String {
+    format.replace("%d", &number.to_string())
+}
+
+fn modified(path: &Path) -> String {
+    fs::metadata(path)
+        .and_then(|metadata| metadata.modified())
+        .ok()
+        .and_then(|time| OffsetDateTime::from(time).replace_nanosecond(0).ok())
+        .and_then(|time| time.format(&Rfc3339).ok())
+        .unwrap_or_else(|| "unknown".to_string())
+}
+
+// css string literal for the footer of the exhibit's named page
+fn css_string(text: &str) -> String {
+    format!("\"{}\"", text.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+fn cover(label: &str, exhibit: &Exhibit) -> String {
+    let name = exhibit
+        .path
+        .file_name()
+        .map(|name| paths::display(Path::new(name)))
+        .unwrap_or_else(|| paths::display(&exhibit.path));
+    let full_path = fs::canonicalize(&exhibit.path)
+        .map(|path| paths::display(&path))
+        .unwrap_or_else(|_| paths::display(&exhibit.path));
+    let author = exhibit
+        .front_matter
+        .as_deref()
+        .and_then(|front_matter| front_matter::value(front_matter, "author"))
+        .unwrap_or_else(|| "unknown".to_string());
+    format!(
+        "<div class=\"yamc-cover\">\n<p class=\"yamc-cover-title\">{}</p>\n<dl>\n\
+         <dt>File</dt><dd>{}</dd>\n<dt>Path</dt><dd>{}</dd>\n\
+         <dt>Modified</dt><dd>{}</dd>\n<dt>Author</dt><dd>{}</dd>\n</dl>\n</div>\n",
+        escape(label),
+        escape(&name),
+        escape(&full_path),
+        modified(&exhibit.path),
+        escape(&author)
+    )
+}
+
+// combine the exhibits into one body: an index followed by every document
+// behind its own cover sheet. each exhibit prints on a named page so its
+// footer can carry the exhibit label
+pub fn assemble(exhibits: &[Exhibit], format: &str) -> String {
+    let mut index = String::from("<nav class=\"yamc-binder-index\">\n<h1>Index</h1>\n<ol>\n");
+    let mut sections = String::new();
+    let mut style = String::from(
+        "<style>\n\
+         .yamc-cover { break-after: page; padding-top: 30vh; }\n\
+         .yamc-cover-title { font-size: 2.5em; font-weight: 600; }\n\
+         .yamc-exhibit { break-before: page; }\n\
+         .yamc-binder-index ol { list-style: none; padding-left: 0; }\n",
+    );
+
+    for (position, exhibit) in exhibits.iter().enumerate() {
+        let number = position + 1;
+        let label = label(format, number);
+        index.push_str(&format!(
+            "<li><a href=\"#exhibit-{}\">{}</a> {}",
+            number,
+            escape(&label),
+            escape(&paths::display(&exhibit.path))
+        ));
+        if let Some(summary) = &exhibit.summary {
+            index.push_str(&format!("<p>{}</p>", escape(summary)));
+        }
+        index.push_str("</li>\n");
+        sections.push_str(&format!(
+            "<section class=\"yamc-exhibit\" id=\"exhibit-{}\" style=\"page: exhibit-{}\">\n",
+            number, number
+        ));
+        sections.push_str(&cover(&label, exhibit));
+        sections.push_str(&exhibit.html);
+        sections.push_str("</section>\n");
+        style.push_str(&format!(
+            "@page exhibit-{} {{ @bottom-center {{ content: {}; }} }}\n",
+            number,
+            css_string(&label)
+        ));
+    }
+
+    index.push_str("</ol>\n</nav>\n");
+    style.push_str("</style>\n");
+    format!("{}{}{}", index, sections, style)
+}
@@ -0,0 +1,97 @@
+use std::cmp::Ordering;
+use unicode_normalization::char::is_combining_mark;
+use unicode_normalization::UnicodeNormalization;
+
+#[derive(PartialEq, Eq, PartialOrd, Ord)]
+enum Chunk {
+    // digits without leading zeros, ordered by length first so 2 < 10
+    Number(usize, String),
+    Text(String),
+}
+
+// the text without case and accents, split into runs of digits and the rest
+fn chunks(text: &str) -> Vec<Chunk> {
+    let folded: String = text
+        .nfd()
+        .filter(|c| !is_combining_mark(*c))
+        .flat_map(char::to_lowercase)
+        .collect();
+    let mut chunks = Vec::new();
+    let mut rest = folded.as_str();
+    while let Some(first) = rest.chars().next() {
+        let digit = first.is_ascii_digit();
+        let end = rest
+            .find(|c: char| c.is_ascii_digit() != digit)
+            .unwrap_or(rest.len());
+        let (run, after) = rest.split_at(end);
+        chunks.push(if digit {
+            let number = run.trim_start_matches('0');
+            Chunk::Number(number.len(), number.to_string())
+        } else {
+            Chunk::Text(run.to_string())
+        });
+        rest = after;
+    }
+    chunks
+}
+
+// order for anything yamc lists: numbers by value ("Chapter 2" before
+// "Chapter 10"), case and accents ignored ("émile" next to "Emile"). strings
+// only equal under that fall back to plain comparison, so the order never
+// depends on the input order
+pub fn compare(a: &str, b: &str) -> Ordering {
+    chunks(a).cmp(&chunks(b)).then_with(|| a.cmp(b))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sorted(mut titles: Vec<&str>) -> Vec<&str> {
+        titles.sort_by(|a, b| compare(a, b));
+        titles
+    }
+
+    #[test]
+    fn numbers_sort_by_value() {
+        assert_eq!(
+            sorted(vec!["Chapter 10", "Chapter 2", "Chapter 1", "chapter 02b"]),
+            ["Chapter 1", "Chapter 2", "chapter 02b", "Chapter 10"]
+        );
+        assert_eq!(
+            sorted(vec!["v1.10.0", "v1.9.3", "v1.9"]),
+            ["v1.9", "v1.9.3", "v1.10.0"]
+        );
+    }
+
+    #[test]
+    fn case_and_accents_are_ignored() {
+        assert_eq!(
+            sorted(vec!["zebra", "Émile", "emu", "Eagle", "édith"]),
+            ["Eagle", "édith", "Émile", "emu", "zebra"]
+        );
+    }
+
+    #[test]
+    fn ties_break_the_same_way_every_time() {
+        assert_eq!(compare("Emile", "émile"), "Emile".cmp("émile"));
+        assert_eq!(compare("a01", "a1"), "a01".cmp("a1"));
+        assert_eq!(
+            sorted(vec!["émile", "Emile"]),
+            sorted(vec!["Emile", "émile"])
+        );
+    }
+
+    #[test]
+    fn mixed_scripts_keep_a_total_order() {
+        let titles = vec!["Привет 10", "hello", "Привет 9", "你好", "Ωmega", "ωmega 2"];
+        let once = sorted(titles.clone());
+        assert_eq!(
+            once,
+            ["hello", "Ωmega", "ωmega 2", "Привет 9", "Привет 10", "你好"]
+        );
+        let mut reversed = titles;
+        reversed.reverse();
+        assert_eq!(sorted(reversed), once);
+    }
+}
@@ -0,0 +1,176 @@
+use crate::paths;
+use clap::ArgAction;
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use toml::Value;
+
+pub const FILE: &str = "yamc.toml";
+
+// options that make no sense in a config file
+const EXCLUDED: &[&str] = &["config", "replay", "dump-effective-config", "help"];
+
+// environment variables for options, for containers and ci runs where the
+// command line is hard to change. they win over the config file
+pub const ENVIRONMENT: &[(&str, &str)] = &[
+    ("YAMC_CSS_URL", "css-url"),
+    ("YAMC_CSS_CLASS", "css-class"),
+    ("YAMC_OUTPUT_DIR", "output-dir"),
+];
+
+// an option given outside the command line, as arguments for it, and the
+// file or variable it came from
+pub struct Setting {
+    pub id: String,
+    pub arguments: Vec<String>,
+    pub source: String,
+}
+
+// the yamc.toml in the current directory or the nearest directory above it,
+// looking no further than the root of the repository
+pub fn find() -> Option<PathBuf> {
+    let current = env::current_dir().ok()?;
+    for directory in current.ancestors() {
+        let candidate = directory.join(FILE);
+        if candidate.is_file() {
+            return Some(candidate);
+        }
+        if directory.join(".git").exists() {
+            break;
+        }
+    }
+    None
+}
+
+// the line a top-level key is set on, for errors
+fn line_of(text: &str, key: &str) -> usize {
+    text.lines()
+        .position(|line| {
+            line.trim_start()
+                .strip_prefix(key)
+                .is_some_and(|rest| rest.trim_start().starts_with('='))
+        })
+        .map_or(0, |index| index + 1)
+}
+
+// a scalar as the text of a command line value
+fn scalar(value: &Value) -> Option<String> {
+    match value {
+        Value::String(text) => Some(text.clone()),
+        Value::Integer(number) => Some(number.to_string()),
+        Value::Float(number) => Some(number.to_string()),
+        _ => None,
+    }
+}
+
+// the options a config file sets, as arguments for `command`. keys are the
+// command's long option names or their aliases, with `_` or `-` between
+// words. a table gives a repeatable option a `key=value` per entry
+pub fn arguments(path: &Path, command: &clap::Command) -> Result<Vec<Setting>, String> {
+    let text = fs::read_to_string(path)
+        .map_err(|err| format!("cannot read {}: {}", paths::display(path), err))?;
+    settings(&text, path, command)
+}
+
+// `arguments` for the `text` of a config file at `path`
+pub fn settings(text: &str, path: &Path, command: &clap::Command) -> Result<Vec<Setting>, String> {
+    let table: toml::Table = text
+        .parse()
+        .map_err(|err: toml::de::Error| format!("{}: {}", paths::display(path), err))?;
+    let mut settings = Vec::new();
+    for (key, value) in &table {
+        let fail = |message: String| {
+            format!(
+                "{}:{}: {} {}",
+                paths::display(path),
+                line_of(text, key),
+                key,
+                message
+            )
+        };
+        let name = key.replace('_', "-");
+        let argument = command
+            .get_arguments()
+            .find(|argument| {
+                argument.get_long() == Some(name.as_str())
+                    || argument
+                        .get_all_aliases()
+                        .is_some_and(|aliases| aliases.contains(&name.as_str()))
+            })
+            .filter(|_| !EXCLUDED.contains(&name.as_str()))
+            .ok_or_else(|| fail("is not an option of this command".to_string()))?;
+        let flag = format!("--{}", name);
+        let mut arguments = Vec::new();
+        match (argument.get_action(), value) {
+            (ArgAction::SetTrue, Value::Boolean(enabled)) => {
+                if *enabled {
+                    arguments.push(flag);
+                }
+            }
+            (ArgAction::SetTrue, _) => return Err(fail("must be true or false".to_string())),
+            (ArgAction::Append, Value::Array(values)) => {
+                for value in values {
+                    let value = scalar(value)
+                        .ok_or_else(|| fail("must be a list of strings or numbers".to_string()))?;
+                    arguments.push(flag.clone());
+                    arguments.push(value);
+                }
+            }
+            (ArgAction::Append, Value::Table(entries)) => {
+                for (entry, value) in entries {
+                    let value = scalar(value)
+                        .ok_or_else(|| fail("must be a table of strings or numbers".to_string()))?;
+                    arguments.push(flag.clone());
+                    arguments.push(format!("{}={}", entry, value));
+                }
+            }
+            (_, value) => {
+                let value = scalar(value)
+                    .ok_or_else(|| fail("must be a string or a number".to_string()))?;
+                arguments.push(flag);
+                arguments.push(value);
+            }
+        }
+        settings.push(Setting {
+            id: argument.get_id().to_string(),
+            arguments,
+            source: paths::display(path),
+        });
+    }
+    Ok(settings)
+}
+
+// the options set by `ENVIRONMENT` variables that `command` has. an empty
+// variable counts as unset, a value the option refuses is an error naming
+// the variable
+pub fn environment(command: &clap::Command) -> Result<Vec<Setting>, String> {
+    let mut settings = Vec::new();
+    for (variable, long) in ENVIRONMENT {
+        let value = match env::var_os(variable) {
+            Some(value) if !value.is_empty() => value,
+            _ => continue,
+        };
+        let argument = match command
+            .get_arguments()
+            .find(|argument| argument.get_long() == Some(*long))
+        {
+            Some(argument) => argument,
+            None => continue,
+        };
+        let value = value
+            .into_string()
+            .map_err(|_| format!("{} is not valid unicode", variable))?;
+        let arguments = vec![format!("--{}", long), value];
+        command
+            .clone()
+            .no_binary_name(true)
+            .try_get_matches_from(&arguments)
+            .map_err(|_| format!("{}: {:?} is not a valid --{}", variable, arguments[1], long))?;
+        settings.push(Setting {
+            id: argument.get_id().to_string(),
+            arguments,
+            source: variable.to_string(),
+        });
+    }
+    Ok(settings)
+}
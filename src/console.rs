@@ -0,0 +1,89 @@
+use std::cell::RefCell;
+use std::io::{self, Write};
+use std::sync::{Arc, Mutex};
+
+type Held = Arc<Mutex<String>>;
+type Last = Arc<Mutex<Option<String>>>;
+
+thread_local! {
+    // where the messages of the document this thread works on are kept
+    static HELD: RefCell<Option<Held>> = const { RefCell::new(None) };
+    // the last message about it, see `noting`
+    static LAST: RefCell<Option<Last>> = const { RefCell::new(None) };
+}
+
+fn remember(message: &str) {
+    LAST.with(|last| {
+        if let Some(last) = last.borrow().as_ref() {
+            *last.lock().expect("the message lock is never poisoned") = Some(message.to_string());
+        }
+    });
+}
+
+// print `text` on standard error in one piece, or keep it with the other
+// messages of the document being converted
+pub fn write(text: String) {
+    remember(text.lines().next().unwrap_or_default());
+    let held = HELD.with(|held| held.borrow().clone());
+    match held {
+        Some(held) => held
+            .lock()
+            .expect("the message lock is never poisoned")
+            .push_str(&text),
+        None => {
+            let _ = io::stderr().lock().write_all(text.as_bytes());
+        }
+    }
+}
+
+// run `work`, keeping back what it prints, and return that with its result
+pub fn hold<T>(work: impl FnOnce() -> T) -> (T, String) {
+    let held: Held = Arc::default();
+    let outer = HELD.with(|current| current.replace(Some(Arc::clone(&held))));
+    let result = work();
+    HELD.with(|current| current.replace(outer));
+    let text = held
+        .lock()
+        .expect("the message lock is never poisoned")
+        .clone();
+    (result, text)
+}
+
+// keep `message` as the last one said, for one printed as json
+pub fn note(message: &str) {
+    remember(message);
+}
+
+// run `work` and return its result with the last message it said, the
+// reason a failed document gets in the --json report
+pub fn noting<T>(work: impl FnOnce() -> T) -> (T, Option<String>) {
+    let last: Last = Arc::default();
+    let outer = LAST.with(|current| current.replace(Some(Arc::clone(&last))));
+    let result = work();
+    LAST.with(|current| current.replace(outer));
+    let said = last
+        .lock()
+        .expect("the message lock is never poisoned")
+        .take();
+    (result, said)
+}
+
+// `work`, for another thread, keeping its messages where this thread would
+pub fn carry<T>(work: impl FnOnce() -> T + Send) -> impl FnOnce() -> T + Send {
+    let held = HELD.with(|held| held.borrow().clone());
+    let last = LAST.with(|last| last.borrow().clone());
+    move || {
+        HELD.with(|current| current.replace(held));
+        LAST.with(|current| current.replace(last));
+        work()
+    }
+}
+
+// print what this thread keeps back now, before the process ends
+pub fn flush() {
+    let held = HELD.with(|held| held.borrow_mut().take());
+    if let Some(held) = held {
+        let text = held.lock().expect("the message lock is never poisoned");
+        let _ = io::stderr().lock().write_all(text.as_bytes());
+    }
+}
@@ -0,0 +1,124 @@
+use crate::diagnostic::Diagnostic;
+use crate::explain;
+use comrak::nodes::{AstNode, NodeValue};
+use std::env;
+use std::path::Path;
+use std::process::Command;
+
+// build metadata documents can refer to as `{{ git.branch }}` or
+// `{{ env.NAME }}`. environment variables are only visible when exposed
+// explicitly with --expose-env, everything else stays private
+pub struct Context<'e> {
+    exposed: &'e [String],
+    git: Option<Option<Git>>,
+}
+
+struct Git {
+    branch: String,
+    tag: String,
+    sha: String,
+    dirty: bool,
+}
+
+fn git(directory: &Path, args: &[&str]) -> Option<String> {
+    let output = Command::new("git")
+        .args(args)
+        .current_dir(directory)
+        .output()
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    Some(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+impl Git {
+    // none outside a repository or when git is not installed
+    fn read(directory: &Path) -> Option<Git> {
+        let sha = git(directory, &["rev-parse", "HEAD"])?;
+        Some(Git {
+            branch: git(directory, &["rev-parse", "--abbrev-ref", "HEAD"]).unwrap_or_default(),
+            tag: git(directory, &["describe", "--tags", "--exact-match"]).unwrap_or_default(),
+            sha,
+            dirty: git(directory, &["status", "--porcelain"]).is_some_and(|s| !s.is_empty()),
+        })
+    }
+}
+
+impl<'e> Context<'e> {
+    pub fn new(exposed: &'e [String]) -> Context<'e> {
+        Context { exposed, git: None }
+    }
+
+    // Ok(None) for names outside the git and env namespaces, which are left
+    // untouched in the document
+    pub fn lookup(&mut self, name: &str, directory: &Path) -> Result<Option<String>, String> {
+        if let Some(variable) = name.strip_prefix("env.") {
+            if !self.exposed.iter().any(|exposed| exposed == variable) {
+                return Err(format!(
+                    "env.{} is not exposed, pass --expose-env {} to allow documents to read it",
+                    variable, variable
+                ));
+            }
+            return Ok(Some(env::var(variable).unwrap_or_default()));
+        }
+        let field = match name.strip_prefix("git.") {
+            Some(field) => field,
+            None => return Ok(None),
+        };
+        let git = self.git.get_or_insert_with(|| Git::read(directory));
+        let value = match (field, git) {
+            ("branch", Some(git)) => git.branch.clone(),
+            ("tag", Some(git)) => git.tag.clone(),
+            ("sha", Some(git)) => git.sha.clone(),
+            ("dirty", Some(git)) => git.dirty.to_string(),
+            ("branch" | "tag" | "sha" | "dirty", None) => String::new(),
+            _ => return Err(format!("unknown value git.{}", field)),
+        };
+        Ok(Some(value))
+    }
+}
+
+// replace `{{ name }}` in the document text, code is left alone
+pub fn substitute<'a>(
+    root: &'a AstNode<'a>,
+    context: &mut Context,
+    path: &Path,
+) -> Result<(), Diagnostic> {
+    let directory = match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    };
+    for node in root.descendants() {
+        let line = explain::line(node);
+        let mut data = node.data.borrow_mut();
+        let literal = match data.value {
+            NodeValue::Text(ref mut literal) => literal,
+            _ => continue,
+        };
+        let text = String::from_utf8_lossy(literal).to_string();
+        if !text.contains("{{") {
+            continue;
+        }
+        let mut output = String::new();
+        let mut rest = text.as_str();
+        while let Some(start) = rest.find("{{") {
+            let end = match rest[start..].find("}}") {
+                Some(end) => start + end,
+                None => break,
+            };
+            let name = rest[start + 2..end].trim();
+            output.push_str(&rest[..start]);
+            match context.lookup(name, directory).map_err(|err| {
+                Diagnostic::error("context", path, line, err).pointing_at(&rest[start..end + 2])
+            })? {
+                Some(value) => output.push_str(&value),
+                None => output.push_str(&rest[start..end + 2]),
+            }
+            rest = &rest[end + 2..];
+        }
+        output.push_str(rest);
+        *literal = output.into_bytes();
+    }
+    Ok(())
+}
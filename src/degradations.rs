@@ -0,0 +1,57 @@
+use std::fmt;
+
+// features of a document that did not make it into the output as written
+pub struct Degradation {
+    pub feature: &'static str,
+    pub count: usize,
+    pub first_line: u32,
+}
+
+#[derive(Default)]
+pub struct Degradations {
+    list: Vec<Degradation>,
+}
+
+impl Degradations {
+    pub fn record(&mut self, feature: &'static str, line: u32) {
+        match self.list.iter_mut().find(|d| d.feature == feature) {
+            Some(degradation) => degradation.count += 1,
+            None => self.list.push(Degradation {
+                feature,
+                count: 1,
+                first_line: line,
+            }),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.list.is_empty()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Degradation> {
+        self.list.iter()
+    }
+}
+
+// `2 features degraded: raw-html x2 (line 3), ...`
+impl fmt::Display for Degradations {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} feature{} degraded: ",
+            self.list.len(),
+            if self.list.len() == 1 { "" } else { "s" }
+        )?;
+        for (index, degradation) in self.list.iter().enumerate() {
+            if index > 0 {
+                write!(f, ", ")?;
+            }
+            write!(
+                f,
+                "{} x{} (first on line {})",
+                degradation.feature, degradation.count, degradation.first_line
+            )?;
+        }
+        Ok(())
+    }
+}
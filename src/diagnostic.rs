@@ -0,0 +1,264 @@
+use crate::paths;
+use serde_json::json;
+use std::env;
+use std::fmt;
+use std::fs;
+use std::io::{self, IsTerminal};
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum Severity {
+    Warning,
+    Error,
+}
+
+impl Severity {
+    fn name(self) -> &'static str {
+        match self {
+            Severity::Warning => "warning",
+            Severity::Error => "error",
+        }
+    }
+
+    // ansi style of the label and the carets
+    fn style(self) -> &'static str {
+        match self {
+            Severity::Warning => "\x1b[1;33m",
+            Severity::Error => "\x1b[1;31m",
+        }
+    }
+}
+
+const BOLD: &str = "\x1b[1m";
+const BLUE: &str = "\x1b[1;34m";
+const RESET: &str = "\x1b[0m";
+
+// a problem found in a source file, printed rustc style with the offending
+// line and a caret under the part it is about
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub code: &'static str,
+    pub path: PathBuf,
+    // 1-based, 0 when the problem is not tied to a line
+    pub line: usize,
+    // byte range in the file, the line is taken from it when present
+    pub span: Option<Range<usize>>,
+    pub message: String,
+}
+
+// where a diagnostic points inside the source, columns are byte offsets into
+// `text`
+struct Location<'s> {
+    line: usize,
+    text: &'s str,
+    start: usize,
+    end: usize,
+}
+
+// whether diagnostics should be colored: only when stderr is a terminal, and
+// never when NO_COLOR is set
+pub fn color() -> bool {
+    io::stderr().is_terminal() && env::var_os("NO_COLOR").is_none()
+}
+
+fn paint(text: &str, style: &str, color: bool) -> String {
+    if color {
+        format!("{}{}{}", style, text, RESET)
+    } else {
+        text.to_string()
+    }
+}
+
+impl Diagnostic {
+    pub fn new(
+        severity: Severity,
+        code: &'static str,
+        path: &Path,
+        line: usize,
+        message: impl Into<String>,
+    ) -> Diagnostic {
+        Diagnostic {
+            severity,
+            code,
+            path: path.to_path_buf(),
+            line,
+            span: None,
+            message: message.into(),
+        }
+    }
+
+    pub fn warning(
+        code: &'static str,
+        path: &Path,
+        line: usize,
+        message: impl Into<String>,
+    ) -> Diagnostic {
+        Diagnostic::new(Severity::Warning, code, path, line, message)
+    }
+
+    pub fn error(
+        code: &'static str,
+        path: &Path,
+        line: usize,
+        message: impl Into<String>,
+    ) -> Diagnostic {
+        Diagnostic::new(Severity::Error, code, path, line, message)
+    }
+
+    pub fn with_span(mut self, span: Range<usize>) -> Diagnostic {
+        self.span = Some(span);
+        self
+    }
+
+    // point at the first `text` from the diagnostic's line to the end of its
+    // paragraph. inline nodes have no position of their own, only their block
+    pub fn pointing_at(self, text: &str) -> Diagnostic {
+        if self.line == 0 || text.is_empty() {
+            return self;
+        }
+        let source = match fs::read_to_string(&self.path) {
+            Ok(source) => source,
+            Err(_) => return self,
+        };
+        let start = match source.split_inclusive('\n').nth(self.line - 1) {
+            Some(line) => line.as_ptr() as usize - source.as_ptr() as usize,
+            None => return self,
+        };
+        let end = source[start..]
+            .find("\n\n")
+            .map_or(source.len(), |end| start + end);
+        match source[start..end].find(text) {
+            Some(found) => self.with_span(start + found..start + found + text.len()),
+            None => self,
+        }
+    }
+
+    fn locate<'s>(&self, source: &'s str) -> Option<Location<'s>> {
+        let (line, start, end) = match &self.span {
+            Some(span) if span.start <= source.len() && source.is_char_boundary(span.start) => {
+                let line_start = source[..span.start].rfind('\n').map_or(0, |i| i + 1);
+                let line = source[..span.start].matches('\n').count() + 1;
+                (line, span.start - line_start, Some(span.end - line_start))
+            }
+            _ if self.line > 0 => (self.line, 0, None),
+            _ => return None,
+        };
+        let text = source.lines().nth(line - 1)?;
+        let (start, end) = match end {
+            Some(end) => (start.min(text.len()), end.min(text.len())),
+            // the whole line without its indentation
+            None => (text.len() - text.trim_start().len(), text.trim_end().len()),
+        };
+        if !text.is_char_boundary(start) || !text.is_char_boundary(end) {
+            return None;
+        }
+        Some(Location {
+            line,
+            text,
+            start,
+            end: end.max(start),
+        })
+    }
+
+    pub fn render(&self, color: bool) -> String {
+        let style = self.severity.style();
+        let mut output = format!(
+            "{}{}\n",
+            paint(
+                &format!("{}[{}]", self.severity.name(), self.code),
+                style,
+                color
+            ),
+            paint(&format!(": {}", self.message), BOLD, color)
+        );
+        let path = paths::display(&self.path);
+        let source = fs::read_to_string(&self.path).ok();
+        let location = match source.as_deref().and_then(|source| self.locate(source)) {
+            Some(location) => location,
+            None if self.line > 0 => {
+                output.push_str(&format!(
+                    " {} {}:{}\n",
+                    paint("-->", BLUE, color),
+                    path,
+                    self.line
+                ));
+                return output;
+            }
+            None => {
+                output.push_str(&format!(" {} {}\n", paint("-->", BLUE, color), path));
+                return output;
+            }
+        };
+        let number = location.line.to_string();
+        let gutter = " ".repeat(number.len());
+        let column = location.text[..location.start].chars().count() + 1;
+        // tabs stay tabs so the carets line up with the source
+        let indent: String = location.text[..location.start]
+            .chars()
+            .map(|c| if c == '\t' { '\t' } else { ' ' })
+            .collect();
+        let width = location.text[location.start..location.end]
+            .chars()
+            .count()
+            .max(1);
+        output.push_str(&format!(
+            "{}{} {}:{}:{}\n",
+            gutter,
+            paint("-->", BLUE, color),
+            path,
+            location.line,
+            column
+        ));
+        output.push_str(&format!("{} {}\n", gutter, paint("|", BLUE, color)));
+        output.push_str(&format!(
+            "{} {}\n",
+            paint(&format!("{} |", number), BLUE, color),
+            location.text
+        ));
+        output.push_str(&format!(
+            "{} {} {}{}\n",
+            gutter,
+            paint("|", BLUE, color),
+            indent,
+            paint(&"^".repeat(width), style, color)
+        ));
+        output
+    }
+
+    // one line of json for editors, with the position resolved to line and
+    // column (both 1-based) as well as the byte span when there is one. a
+    // path that is not valid unicode also comes as `path_base64`
+    pub fn to_json(&self) -> String {
+        let source = fs::read_to_string(&self.path).ok();
+        let location = source.as_deref().and_then(|source| self.locate(source));
+        let (line, column, end_column) = match &location {
+            Some(location) => (
+                location.line,
+                Some(location.text[..location.start].chars().count() + 1),
+                Some(location.text[..location.end].chars().count() + 1),
+            ),
+            None => (self.line, None, None),
+        };
+        let mut report = json!({
+            "severity": self.severity.name(),
+            "code": self.code,
+            "message": self.message,
+            "path": paths::display(&self.path),
+            "line": line,
+            "column": column,
+            "end_column": end_column,
+            "span": self.span.as_ref().map(|span| json!({ "start": span.start, "end": span.end })),
+        });
+        if let Some(raw) = paths::base64(&self.path) {
+            report["path_base64"] = json!(raw);
+        }
+        report.to_string()
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.render(false).trim_end())
+    }
+}
@@ -0,0 +1,222 @@
+use crate::footnotes;
+use crate::inline;
+use crate::math;
+
+// input dialects select a preset of syntax features, individual features can
+// then be switched with --enable/--disable
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Dialect {
+    CommonMark,
+    Gfm,
+    Obsidian,
+}
+
+impl Dialect {
+    pub fn parse(name: &str) -> Option<Dialect> {
+        match name {
+            "commonmark" => Some(Dialect::CommonMark),
+            "gfm" => Some(Dialect::Gfm),
+            "obsidian" => Some(Dialect::Obsidian),
+            _ => None,
+        }
+    }
+}
+
+pub const FEATURES: &[&str] = &[
+    "strikethrough",
+    "tagfilter",
+    "table",
+    "autolink",
+    "autolink-domains",
+    "tasklist",
+    "superscript",
+    "footnotes",
+    "description-lists",
+    "hardbreaks",
+    "wikilinks",
+    "highlight",
+    "inline-footnotes",
+    "subscript",
+    "math",
+    "smart",
+];
+
+// the features the front matter of a document can switch for itself, with
+// `feature: true` or `feature: false`
+pub const DOCUMENT_FEATURES: &[&str] = &["smart", "hardbreaks"];
+
+#[derive(Clone)]
+pub struct Syntax {
+    pub strikethrough: bool,
+    pub tagfilter: bool,
+    pub table: bool,
+    pub autolink: bool,
+    // also link bare domains like `example.com`
+    pub autolink_domains: bool,
+    pub tasklist: bool,
+    pub superscript: bool,
+    pub footnotes: bool,
+    pub description_lists: bool,
+    pub hardbreaks: bool,
+    // `[[Page]]`, `[[Page|label]]` and `[[Page#heading]]` links to Page.html
+    pub wikilinks: bool,
+    // `==text==` renders as <mark>
+    pub highlight: bool,
+    // pandoc style `^[inline notes]`, implies footnotes
+    pub inline_footnotes: bool,
+    // `H~2~O` renders as H<sub>2</sub>O, `~~text~~` stays strikethrough
+    pub subscript: bool,
+    // `$tex$` and `$$tex$$` render as math, typeset by KaTeX in the browser
+    pub math: bool,
+    // curly quotes, en and em dashes for `--` and `---`, an ellipsis for `...`
+    pub smart: bool,
+}
+
+impl Syntax {
+    pub fn preset(dialect: Dialect) -> Syntax {
+        let gfm = dialect != Dialect::CommonMark;
+        let obsidian = dialect == Dialect::Obsidian;
+        Syntax {
+            strikethrough: gfm,
+            tagfilter: gfm,
+            table: gfm,
+            autolink: gfm,
+            autolink_domains: false,
+            tasklist: gfm,
+            // yamc has always rendered ^superscript^ for gfm input
+            superscript: gfm && !obsidian,
+            footnotes: obsidian,
+            description_lists: false,
+            hardbreaks: obsidian,
+            wikilinks: obsidian,
+            highlight: obsidian,
+            inline_footnotes: obsidian,
+            subscript: false,
+            math: false,
+            smart: false,
+        }
+    }
+
+    pub fn set(&mut self, feature: &str, enabled: bool) -> Result<(), String> {
+        let flag = match feature {
+            "strikethrough" => &mut self.strikethrough,
+            "tagfilter" => &mut self.tagfilter,
+            "table" => &mut self.table,
+            "autolink" => &mut self.autolink,
+            "autolink-domains" => &mut self.autolink_domains,
+            "tasklist" => &mut self.tasklist,
+            "superscript" => &mut self.superscript,
+            "footnotes" => &mut self.footnotes,
+            "description-lists" => &mut self.description_lists,
+            "hardbreaks" => &mut self.hardbreaks,
+            "wikilinks" => &mut self.wikilinks,
+            "highlight" => &mut self.highlight,
+            "inline-footnotes" => &mut self.inline_footnotes,
+            "subscript" => &mut self.subscript,
+            "math" => &mut self.math,
+            "smart" => &mut self.smart,
+            _ => {
+                return Err(format!(
+                    "Unknown feature {:?}, expected one of {}",
+                    feature,
+                    FEATURES.join(", ")
+                ))
+            }
+        };
+        *flag = enabled;
+        Ok(())
+    }
+
+    // names of the features that are switched on, in `FEATURES` order
+    pub fn enabled(&self) -> Vec<&'static str> {
+        let flags = [
+            self.strikethrough,
+            self.tagfilter,
+            self.table,
+            self.autolink,
+            self.autolink_domains,
+            self.tasklist,
+            self.superscript,
+            self.footnotes,
+            self.description_lists,
+            self.hardbreaks,
+            self.wikilinks,
+            self.highlight,
+            self.inline_footnotes,
+            self.subscript,
+            self.math,
+            self.smart,
+        ];
+        FEATURES
+            .iter()
+            .zip(flags)
+            .filter(|(_, enabled)| *enabled)
+            .map(|(feature, _)| *feature)
+            .collect()
+    }
+}
+
+// convert markdown to html using comrak with the features of the dialect.
+// raw html is rendered as is; authored html is replaced before rendering by
+// `html::omit_raw` so only markup generated by yamc reaches the output
+pub fn create_comrak_options(syntax: &Syntax) -> comrak::ComrakOptions {
+    let footnotes = syntax.footnotes || syntax.inline_footnotes;
+    comrak::ComrakOptions {
+        extension: comrak::ComrakExtensionOptions {
+            strikethrough: syntax.strikethrough,
+            tagfilter: syntax.tagfilter,
+            table: syntax.table,
+            autolink: syntax.autolink,
+            tasklist: syntax.tasklist,
+            // comrak's superscript swallows `[^label]` footnote references,
+            // so footnotes win when both are asked for
+            superscript: syntax.superscript && !footnotes,
+            header_ids: Some("".to_string()),
+            footnotes,
+            description_lists: syntax.description_lists,
+            front_matter_delimiter: Some("---".to_string()),
+        },
+        parse: comrak::ComrakParseOptions {
+            smart: syntax.smart,
+            ..Default::default()
+        },
+        render: comrak::ComrakRenderOptions {
+            hardbreaks: syntax.hardbreaks,
+            unsafe_: true,
+            ..Default::default()
+        },
+    }
+}
+
+// plain commonmark as the spec describes it, for comparing against other
+// implementations: no extensions, no header ids. raw html is rendered when
+// --unsafe leaves it in the document
+pub fn strict_comrak_options() -> comrak::ComrakOptions {
+    comrak::ComrakOptions {
+        render: comrak::ComrakRenderOptions {
+            unsafe_: true,
+            ..Default::default()
+        },
+        ..Default::default()
+    }
+}
+
+// source level rewrites that have to happen before comrak parses the text
+pub fn preprocess(markdown: String, syntax: &Syntax) -> String {
+    // first, so nothing below sees into a formula
+    let markdown = if syntax.math {
+        math::protect(&markdown)
+    } else {
+        markdown
+    };
+    let markdown = if syntax.inline_footnotes {
+        footnotes::expand(&markdown)
+    } else {
+        markdown
+    };
+    if syntax.subscript {
+        inline::mark_subscripts(&markdown)
+    } else {
+        markdown
+    }
+}
@@ -0,0 +1,134 @@
+use crate::{offline, paths, resources};
+use std::env;
+use std::fs;
+use std::net::{TcpListener, TcpStream, ToSocketAddrs};
+use std::path::{Path, PathBuf, MAIN_SEPARATOR};
+use std::process;
+use std::time::Duration;
+
+// how long the stylesheet's server gets to answer
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(5);
+
+// one thing yamc needs from the machine it runs on, and what became of
+// looking for it. a check that is not `required` only limits some commands
+pub struct Check {
+    pub name: &'static str,
+    pub required: bool,
+    pub outcome: Result<String, String>,
+    // what to do when it failed
+    pub hint: &'static str,
+}
+
+impl Check {
+    pub fn new(
+        name: &'static str,
+        required: bool,
+        outcome: Result<String, String>,
+        hint: &'static str,
+    ) -> Check {
+        Check {
+            name,
+            required,
+            outcome,
+            hint,
+        }
+    }
+}
+
+// print every check, true when none that is required failed
+pub fn report(checks: &[Check]) -> bool {
+    let width = checks
+        .iter()
+        .map(|check| check.name.len())
+        .max()
+        .unwrap_or(0);
+    let mut usable = true;
+    for check in checks {
+        match &check.outcome {
+            Ok(found) => println!("pass  {:width$}  {}", check.name, found, width = width),
+            Err(problem) => {
+                let status = if check.required { "FAIL" } else { "warn" };
+                println!(
+                    "{}  {:width$}  {}",
+                    status,
+                    check.name,
+                    problem,
+                    width = width
+                );
+                println!("      {:width$}  {}", "", check.hint, width = width);
+                usable &= !check.required;
+            }
+        }
+    }
+    usable
+}
+
+// whether the server of the stylesheet the page links to can be reached
+pub fn stylesheet(url: &str, bundled: bool) -> Result<String, String> {
+    if bundled {
+        return Ok("yamc's own stylesheet is put into the page".to_string());
+    }
+    if !offline::is_remote(url) {
+        return Ok(format!("{} is not loaded over the network", url));
+    }
+    let host = resources::domain(url)
+        .or_else(|| resources::domain(&format!("https:{}", url)))
+        .ok_or_else(|| format!("cannot tell the server of {}", url))?;
+    let port = if url.starts_with("http://") { 80 } else { 443 };
+    let addresses = (host.as_str(), port)
+        .to_socket_addrs()
+        .map_err(|err| format!("cannot look up {}: {}", host, err))?;
+    let mut last = None;
+    for address in addresses {
+        match TcpStream::connect_timeout(&address, CONNECT_TIMEOUT) {
+            Ok(_) => return Ok(format!("{}:{} answers", host, port)),
+            Err(err) => last = Some(err),
+        }
+    }
+    Err(match last {
+        Some(err) => format!("cannot connect to {}:{}: {}", host, port, err),
+        None => format!("{} has no address", host),
+    })
+}
+
+// the first `program` on PATH, or the program itself when it is a path
+fn find_program(program: &str) -> Option<PathBuf> {
+    if program.contains(MAIN_SEPARATOR) {
+        return Some(PathBuf::from(program)).filter(|path| path.is_file());
+    }
+    let path = env::var_os("PATH")?;
+    env::split_paths(&path)
+        .flat_map(|directory| {
+            let plain = directory.join(program);
+            let windows = directory.join(format!("{}.exe", program));
+            [plain, windows]
+        })
+        .find(|candidate| candidate.is_file())
+}
+
+pub fn program(program: &str) -> Result<String, String> {
+    find_program(program)
+        .map(|path| paths::display(&path))
+        .ok_or_else(|| format!("{} is not on PATH", program))
+}
+
+// whether `serve` can listen on `port`
+pub fn port(port: u16) -> Result<String, String> {
+    TcpListener::bind(("127.0.0.1", port))
+        .map(|_| format!("127.0.0.1:{} is free", port))
+        .map_err(|err| format!("cannot listen on 127.0.0.1:{}: {}", port, err))
+}
+
+// whether pages can be written into `directory`, or into the nearest
+// directory above it that is there to make it in
+pub fn writable(directory: &Path) -> Result<String, String> {
+    let directory = directory
+        .ancestors()
+        .find(|ancestor| ancestor.is_dir())
+        .unwrap_or(Path::new("."));
+    let probe = directory.join(format!(".yamc-doctor-{}", process::id()));
+    fs::write(&probe, b"")
+        .map_err(|err| format!("cannot write in {}: {}", paths::display(directory), err))?;
+    let _ = fs::remove_file(&probe);
+    Ok(format!("{} is writable", paths::display(directory)))
+}
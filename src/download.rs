@@ -0,0 +1,66 @@
+use reqwest::blocking::Response;
+use reqwest::header::CONTENT_TYPE;
+use std::io::Read;
+use std::time::Duration;
+
+// how long a download may take before yamc goes on without it
+const TIMEOUT: Duration = Duration::from_secs(15);
+
+// the response for `url`, which may leave out the scheme as in `//host/path`.
+// every request yamc makes goes through here. test builds have no network:
+// a test that asks for a url fails, so one that passes made no request
+fn get(url: &str) -> Result<Response, String> {
+    if cfg!(test) {
+        panic!("{} was requested, tests have no network", url);
+    }
+    let url = match url.strip_prefix("//") {
+        Some(rest) => format!("https://{}", rest),
+        None => url.to_string(),
+    };
+    let client = reqwest::blocking::Client::builder()
+        .timeout(TIMEOUT)
+        .user_agent(concat!("yamc/", env!("CARGO_PKG_VERSION")))
+        .build()
+        .map_err(|err| err.to_string())?;
+    client
+        .get(&url)
+        .send()
+        .and_then(|response| response.error_for_status())
+        .map_err(|err| err.to_string())
+}
+
+// the text at `url`
+pub fn text(url: &str) -> Result<String, String> {
+    get(url)?.text().map_err(|err| err.to_string())
+}
+
+// the bytes at `url` and the content type the server gave them, refused
+// once they pass `limit` bytes
+pub fn bytes(url: &str, limit: u64) -> Result<(Vec<u8>, Option<String>), String> {
+    let response = get(url)?;
+    let content_type = response
+        .headers()
+        .get(CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .map(|value| value.split(';').next().unwrap_or(value).trim().to_string());
+    let mut bytes = Vec::new();
+    response
+        .take(limit + 1)
+        .read_to_end(&mut bytes)
+        .map_err(|err| err.to_string())?;
+    if bytes.len() as u64 > limit {
+        return Err(format!("larger than {} bytes", limit));
+    }
+    Ok((bytes, content_type))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    #[should_panic(expected = "tests have no network")]
+    fn requests_fail_in_tests() {
+        let _ = text("https://example.com/style.css");
+    }
+}
@@ -0,0 +1,432 @@
+use comrak::nodes::{AstNode, NodeValue};
+
+// github's shortcodes for the emoji most used in writing, sorted for
+// binary search
+const SHORTCODES: &[(&str, &str)] = &[
+    ("+1", "👍"),
+    ("-1", "👎"),
+    ("100", "💯"),
+    ("1st_place_medal", "🥇"),
+    ("abc", "🔤"),
+    ("airplane", "✈\u{fe0f}"),
+    ("alarm_clock", "⏰"),
+    ("alien", "👽"),
+    ("anger", "💢"),
+    ("angry", "😠"),
+    ("ant", "🐜"),
+    ("apple", "🍎"),
+    ("arrow_down", "⬇\u{fe0f}"),
+    ("arrow_forward", "▶\u{fe0f}"),
+    ("arrow_left", "⬅\u{fe0f}"),
+    ("arrow_right", "➡\u{fe0f}"),
+    ("arrow_up", "⬆\u{fe0f}"),
+    ("arrows_counterclockwise", "🔄"),
+    ("art", "🎨"),
+    ("astonished", "😲"),
+    ("atom_symbol", "⚛\u{fe0f}"),
+    ("balloon", "🎈"),
+    ("ballot_box_with_check", "☑\u{fe0f}"),
+    ("banana", "🍌"),
+    ("bar_chart", "📊"),
+    ("basketball", "🏀"),
+    ("battery", "🔋"),
+    ("bear", "🐻"),
+    ("bee", "🐝"),
+    ("beer", "🍺"),
+    ("beers", "🍻"),
+    ("beetle", "🐞"),
+    ("bell", "🔔"),
+    ("bike", "🚲"),
+    ("birthday", "🎂"),
+    ("black_circle", "⚫"),
+    ("black_large_square", "⬛"),
+    ("blue_heart", "💙"),
+    ("blush", "😊"),
+    ("bomb", "💣"),
+    ("book", "📖"),
+    ("bookmark", "🔖"),
+    ("books", "📚"),
+    ("boom", "💥"),
+    ("brain", "🧠"),
+    ("broken_heart", "💔"),
+    ("bug", "🐛"),
+    ("bulb", "💡"),
+    ("bus", "🚌"),
+    ("butterfly", "🦋"),
+    ("cactus", "🌵"),
+    ("cake", "🍰"),
+    ("calendar", "📆"),
+    ("camera", "📷"),
+    ("candle", "🕯"),
+    ("car", "🚗"),
+    ("card_index", "📇"),
+    ("cat", "🐱"),
+    ("cd", "💿"),
+    ("chart", "💹"),
+    ("chart_with_downwards_trend", "📉"),
+    ("chart_with_upwards_trend", "📈"),
+    ("checkered_flag", "🏁"),
+    ("cherry_blossom", "🌸"),
+    ("christmas_tree", "🎄"),
+    ("clap", "👏"),
+    ("clipboard", "📋"),
+    ("closed_lock_with_key", "🔐"),
+    ("cloud", "☁\u{fe0f}"),
+    ("coffee", "☕"),
+    ("computer", "💻"),
+    ("confetti_ball", "🎊"),
+    ("confused", "😕"),
+    ("construction", "🚧"),
+    ("cookie", "🍪"),
+    ("cool", "🆒"),
+    ("copyright", "©\u{fe0f}"),
+    ("crab", "🦀"),
+    ("crescent_moon", "🌙"),
+    ("crown", "👑"),
+    ("cry", "😢"),
+    ("dancer", "💃"),
+    ("dart", "🎯"),
+    ("dash", "💨"),
+    ("date", "📅"),
+    ("deciduous_tree", "🌳"),
+    ("disappointed", "😞"),
+    ("dizzy", "💫"),
+    ("dizzy_face", "😵"),
+    ("dna", "🧬"),
+    ("dog", "🐶"),
+    ("dollar", "💵"),
+    ("droplet", "💧"),
+    ("earth_africa", "🌍"),
+    ("earth_americas", "🌎"),
+    ("eight", "8\u{fe0f}\u{20e3}"),
+    ("electric_plug", "🔌"),
+    ("email", "✉\u{fe0f}"),
+    ("envelope", "✉\u{fe0f}"),
+    ("evergreen_tree", "🌲"),
+    ("exclamation", "❗"),
+    ("expressionless", "😑"),
+    ("eyeglasses", "👓"),
+    ("eyes", "👀"),
+    ("facepalm", "🤦"),
+    ("fallen_leaf", "🍂"),
+    ("fast_forward", "⏩"),
+    ("fearful", "😨"),
+    ("file_folder", "📁"),
+    ("fire", "🔥"),
+    ("fireworks", "🎆"),
+    ("fist", "✊"),
+    ("five", "5\u{fe0f}\u{20e3}"),
+    ("flashlight", "🔦"),
+    ("floppy_disk", "💾"),
+    ("flushed", "😳"),
+    ("four", "4\u{fe0f}\u{20e3}"),
+    ("four_leaf_clover", "🍀"),
+    ("fox_face", "🦊"),
+    ("free", "🆓"),
+    ("frog", "🐸"),
+    ("full_moon", "🌕"),
+    ("game_die", "🎲"),
+    ("gear", "⚙\u{fe0f}"),
+    ("gem", "💎"),
+    ("ghost", "👻"),
+    ("gift", "🎁"),
+    ("globe_with_meridians", "🌐"),
+    ("green_circle", "🟢"),
+    ("green_heart", "💚"),
+    ("grey_exclamation", "❕"),
+    ("grey_question", "❔"),
+    ("grin", "😁"),
+    ("grinning", "😀"),
+    ("guitar", "🎸"),
+    ("hamburger", "🍔"),
+    ("hammer", "🔨"),
+    ("hammer_and_wrench", "🛠\u{fe0f}"),
+    ("handshake", "🤝"),
+    ("hankey", "💩"),
+    ("hash", "#\u{fe0f}\u{20e3}"),
+    ("hatching_chick", "🐣"),
+    ("headphones", "🎧"),
+    ("hear_no_evil", "🙉"),
+    ("heart", "❤\u{fe0f}"),
+    ("heart_eyes", "😍"),
+    ("heavy_check_mark", "✔\u{fe0f}"),
+    ("heavy_dollar_sign", "💲"),
+    ("heavy_exclamation_mark", "❗"),
+    ("heavy_minus_sign", "➖"),
+    ("heavy_multiplication_x", "✖\u{fe0f}"),
+    ("heavy_plus_sign", "➕"),
+    ("herb", "🌿"),
+    ("hole", "🕳\u{fe0f}"),
+    ("honeybee", "🐝"),
+    ("hourglass", "⌛"),
+    ("hourglass_flowing_sand", "⏳"),
+    ("house", "🏠"),
+    ("hugs", "🤗"),
+    ("inbox_tray", "📥"),
+    ("infinity", "♾\u{fe0f}"),
+    ("information_source", "ℹ\u{fe0f}"),
+    ("innocent", "😇"),
+    ("iphone", "📱"),
+    ("jack_o_lantern", "🎃"),
+    ("joy", "😂"),
+    ("key", "🔑"),
+    ("keyboard", "⌨\u{fe0f}"),
+    ("keycap_ten", "🔟"),
+    ("kissing_heart", "😘"),
+    ("label", "🏷\u{fe0f}"),
+    ("large_blue_circle", "🔵"),
+    ("laughing", "😆"),
+    ("link", "🔗"),
+    ("lipstick", "💄"),
+    ("lock", "🔒"),
+    ("lock_with_ink_pen", "🔏"),
+    ("loudspeaker", "📢"),
+    ("mag", "🔍"),
+    ("man_technologist", "👨\u{200d}💻"),
+    ("mask", "😷"),
+    ("medal_sports", "🏅"),
+    ("mega", "📣"),
+    ("memo", "📝"),
+    ("microscope", "🔬"),
+    ("moneybag", "💰"),
+    ("monkey_face", "🐵"),
+    ("mountain", "⛰\u{fe0f}"),
+    ("movie_camera", "🎥"),
+    ("moyai", "🗿"),
+    ("muscle", "💪"),
+    ("musical_note", "🎵"),
+    ("necktie", "👔"),
+    ("negative_squared_cross_mark", "❎"),
+    ("nerd_face", "🤓"),
+    ("neutral_face", "😐"),
+    ("new", "🆕"),
+    ("new_moon", "🌑"),
+    ("newspaper", "📰"),
+    ("nine", "9\u{fe0f}\u{20e3}"),
+    ("no_entry", "⛔"),
+    ("no_entry_sign", "🚫"),
+    ("no_mouth", "😶"),
+    ("notes", "🎶"),
+    ("nut_and_bolt", "🔩"),
+    ("ocean", "🌊"),
+    ("octopus", "🐙"),
+    ("office", "🏢"),
+    ("ok", "🆗"),
+    ("ok_hand", "👌"),
+    ("ok_person", "🙆"),
+    ("one", "1\u{fe0f}\u{20e3}"),
+    ("open_file_folder", "📂"),
+    ("open_mouth", "😮"),
+    ("outbox_tray", "📤"),
+    ("owl", "🦉"),
+    ("package", "📦"),
+    ("page_facing_up", "📄"),
+    ("panda_face", "🐼"),
+    ("paperclip", "📎"),
+    ("pause_button", "⏸\u{fe0f}"),
+    ("pencil", "📝"),
+    ("pencil2", "✏\u{fe0f}"),
+    ("penguin", "🐧"),
+    ("pensive", "😔"),
+    ("pill", "💊"),
+    ("pizza", "🍕"),
+    ("point_down", "👇"),
+    ("point_left", "👈"),
+    ("point_right", "👉"),
+    ("point_up", "☝\u{fe0f}"),
+    ("poop", "💩"),
+    ("pray", "🙏"),
+    ("punch", "👊"),
+    ("purple_heart", "💜"),
+    ("pushpin", "📌"),
+    ("question", "❓"),
+    ("rabbit", "🐰"),
+    ("rage", "😡"),
+    ("rainbow", "🌈"),
+    ("raised_hands", "🙌"),
+    ("raising_hand", "🙋"),
+    ("recycle", "♻\u{fe0f}"),
+    ("red_car", "🚗"),
+    ("red_circle", "🔴"),
+    ("registered", "®\u{fe0f}"),
+    ("relieved", "😌"),
+    ("repeat", "🔁"),
+    ("robot", "🤖"),
+    ("rocket", "🚀"),
+    ("rofl", "🤣"),
+    ("rose", "🌹"),
+    ("rotating_light", "🚨"),
+    ("running", "🏃"),
+    ("santa", "🎅"),
+    ("satellite", "📡"),
+    ("scissors", "✂\u{fe0f}"),
+    ("scream", "😱"),
+    ("scroll", "📜"),
+    ("see_no_evil", "🙈"),
+    ("seedling", "🌱"),
+    ("seven", "7\u{fe0f}\u{20e3}"),
+    ("shield", "🛡\u{fe0f}"),
+    ("ship", "🚢"),
+    ("shirt", "👕"),
+    ("shrug", "🤷"),
+    ("six", "6\u{fe0f}\u{20e3}"),
+    ("skull", "💀"),
+    ("sleeping", "😴"),
+    ("sleepy", "😪"),
+    ("slightly_frowning_face", "🙁"),
+    ("slightly_smiling_face", "🙂"),
+    ("smile", "😄"),
+    ("smiley", "😃"),
+    ("smirk", "😏"),
+    ("snail", "🐌"),
+    ("snake", "🐍"),
+    ("snowflake", "❄\u{fe0f}"),
+    ("snowman", "⛄"),
+    ("sob", "😭"),
+    ("soccer", "⚽"),
+    ("sos", "🆘"),
+    ("sparkler", "🎇"),
+    ("sparkles", "✨"),
+    ("sparkling_heart", "💖"),
+    ("speak_no_evil", "🙊"),
+    ("speech_balloon", "💬"),
+    ("star", "⭐"),
+    ("star2", "🌟"),
+    ("stop_sign", "🛑"),
+    ("stopwatch", "⏱\u{fe0f}"),
+    ("straight_ruler", "📏"),
+    ("stuck_out_tongue", "😛"),
+    ("stuck_out_tongue_winking_eye", "😜"),
+    ("sun_with_face", "🌞"),
+    ("sunflower", "🌻"),
+    ("sunglasses", "😎"),
+    ("sunny", "☀\u{fe0f}"),
+    ("sweat", "😓"),
+    ("sweat_drops", "💦"),
+    ("sweat_smile", "😅"),
+    ("syringe", "💉"),
+    ("tada", "🎉"),
+    ("tea", "🍵"),
+    ("technologist", "🧑\u{200d}💻"),
+    ("telescope", "🔭"),
+    ("test_tube", "🧪"),
+    ("thinking", "🤔"),
+    ("thought_balloon", "💭"),
+    ("three", "3\u{fe0f}\u{20e3}"),
+    ("thumbsdown", "👎"),
+    ("thumbsup", "👍"),
+    ("tiger", "🐯"),
+    ("tm", "™\u{fe0f}"),
+    ("toolbox", "🧰"),
+    ("tophat", "🎩"),
+    ("train", "🚋"),
+    ("triangular_flag_on_post", "🚩"),
+    ("triangular_ruler", "📐"),
+    ("triumph", "😤"),
+    ("trophy", "🏆"),
+    ("tulip", "🌷"),
+    ("turtle", "🐢"),
+    ("tv", "📺"),
+    ("twisted_rightwards_arrows", "🔀"),
+    ("two", "2\u{fe0f}\u{20e3}"),
+    ("umbrella", "☔"),
+    ("unamused", "😒"),
+    ("unicorn", "🦄"),
+    ("unlock", "🔓"),
+    ("up", "🆙"),
+    ("upside_down_face", "🙃"),
+    ("v", "✌\u{fe0f}"),
+    ("video_game", "🎮"),
+    ("volcano", "🌋"),
+    ("walking", "🚶"),
+    ("warning", "⚠\u{fe0f}"),
+    ("wastebasket", "🗑\u{fe0f}"),
+    ("watch", "⌚"),
+    ("wave", "👋"),
+    ("whale", "🐳"),
+    ("white_check_mark", "✅"),
+    ("white_circle", "⚪"),
+    ("white_flag", "🏳\u{fe0f}"),
+    ("white_large_square", "⬜"),
+    ("wink", "😉"),
+    ("woman_technologist", "👩\u{200d}💻"),
+    ("world_map", "🗺\u{fe0f}"),
+    ("worried", "😟"),
+    ("wrench", "🔧"),
+    ("x", "❌"),
+    ("yellow_circle", "🟡"),
+    ("yellow_heart", "💛"),
+    ("yum", "😋"),
+    ("zap", "⚡"),
+    ("zero", "0\u{fe0f}\u{20e3}"),
+    ("zipper_mouth_face", "🤐"),
+    ("zzz", "💤"),
+];
+
+fn emoji(name: &str) -> Option<&'static str> {
+    SHORTCODES
+        .binary_search_by(|(shortcode, _)| (*shortcode).cmp(name))
+        .ok()
+        .map(|index| SHORTCODES[index].1)
+}
+
+fn is_name(c: char) -> bool {
+    c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_' || c == '+' || c == '-'
+}
+
+// `text` with its known shortcodes replaced, none when it has none
+fn replaced(text: &str) -> Option<String> {
+    let mut output = String::with_capacity(text.len());
+    let mut rest = text;
+    let mut changed = false;
+    while let Some(start) = rest.find(':') {
+        output.push_str(&rest[..start]);
+        let after = &rest[start + 1..];
+        let length = after.find(|c| !is_name(c)).unwrap_or(after.len());
+        match emoji(&after[..length]).filter(|_| after[length..].starts_with(':')) {
+            Some(emoji) => {
+                output.push_str(emoji);
+                rest = &after[length + 1..];
+                changed = true;
+            }
+            // the closing colon may open the next shortcode
+            None => {
+                output.push(':');
+                rest = after;
+            }
+        }
+    }
+    output.push_str(rest);
+    changed.then_some(output)
+}
+
+// `text` with its known shortcodes replaced
+pub fn text(text: &str) -> String {
+    replaced(text).unwrap_or_else(|| text.to_string())
+}
+
+// whether `node` is the text of a bare url, which shows its url as it is
+fn in_url<'a>(node: &'a AstNode<'a>, text: &str) -> bool {
+    node.ancestors()
+        .any(|ancestor| match ancestor.data.borrow().value {
+            NodeValue::Link(ref link) => String::from_utf8_lossy(&link.url).ends_with(text),
+            _ => false,
+        })
+}
+
+// replace `:shortcode:` with its emoji in the text of the document. code,
+// urls and raw html are not text, unknown shortcodes stay as they are
+pub fn replace<'a>(root: &'a AstNode<'a>) {
+    for node in root.descendants() {
+        let text = match node.data.borrow().value {
+            NodeValue::Text(ref literal) => String::from_utf8_lossy(literal).into_owned(),
+            _ => continue,
+        };
+        if in_url(node, &text) {
+            continue;
+        }
+        if let Some(text) = replaced(&text) {
+            node.data.borrow_mut().value = NodeValue::Text(text.into_bytes());
+        }
+    }
+}
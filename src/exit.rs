@@ -0,0 +1,33 @@
+use crate::console;
+use std::process;
+
+// the exit statuses of yamc, for scripts that need to tell failures apart.
+// the numbers stay as they are once released
+#[derive(Clone, Copy, PartialEq)]
+pub enum Status {
+    // a document could not be converted, or did not pass a check it was
+    // asked to pass
+    Failed = 1,
+    // the options or the files naming options do not make sense; clap exits
+    // with the same status for the errors it finds
+    Usage = 2,
+    // an input could not be read or an output could not be written
+    Io = 3,
+    // a conversion took longer than --time-budget
+    Timeout = 4,
+}
+
+// for --help
+pub const HELP: &str = "Exit status:
+  0  success
+  1  a document could not be converted or failed a check
+  2  usage error: options, config file, replay file or template
+  3  an input could not be read or an output not written
+  4  --time-budget ran out";
+
+impl Status {
+    pub fn exit(self) -> ! {
+        console::flush();
+        process::exit(self as i32)
+    }
+}
@@ -0,0 +1,135 @@
+use comrak::nodes::{AstNode, NodeValue};
+use std::fmt::Write;
+
+// a short description of a node: its kind and the detail that matters when
+// comparing parses, e.g. heading level or link url
+fn describe(value: &NodeValue) -> String {
+    let lossy = |bytes: &[u8]| String::from_utf8_lossy(bytes).to_string();
+    match value {
+        NodeValue::Document => "document".to_string(),
+        NodeValue::FrontMatter(..) => "front_matter".to_string(),
+        NodeValue::BlockQuote => "block_quote".to_string(),
+        NodeValue::List(list) => format!("list {:?} tight={}", list.list_type, list.tight),
+        NodeValue::Item(..) => "item".to_string(),
+        NodeValue::DescriptionList => "description_list".to_string(),
+        NodeValue::DescriptionItem(..) => "description_item".to_string(),
+        NodeValue::DescriptionTerm => "description_term".to_string(),
+        NodeValue::DescriptionDetails => "description_details".to_string(),
+        NodeValue::CodeBlock(block) => format!(
+            "code_block fenced={} {:?}",
+            block.fenced,
+            lossy(&block.info)
+        ),
+        NodeValue::HtmlBlock(block) => format!("html_block {:?}", lossy(&block.literal)),
+        NodeValue::Paragraph => "paragraph".to_string(),
+        NodeValue::Heading(heading) => {
+            format!("heading level={} setext={}", heading.level, heading.setext)
+        }
+        NodeValue::ThematicBreak => "thematic_break".to_string(),
+        NodeValue::FootnoteDefinition(label) => format!("footnote_definition {:?}", lossy(label)),
+        NodeValue::Table(..) => "table".to_string(),
+        NodeValue::TableRow(header) => format!("table_row header={}", header),
+        NodeValue::TableCell => "table_cell".to_string(),
+        NodeValue::Text(literal) => format!("text {:?}", lossy(literal)),
+        NodeValue::TaskItem(checked) => format!("task_item checked={}", checked),
+        NodeValue::SoftBreak => "softbreak".to_string(),
+        NodeValue::LineBreak => "linebreak".to_string(),
+        NodeValue::Code(code) => format!("code {:?}", lossy(&code.literal)),
+        NodeValue::HtmlInline(literal) => format!("html_inline {:?}", lossy(literal)),
+        NodeValue::Emph => "emph".to_string(),
+        NodeValue::Strong => "strong".to_string(),
+        NodeValue::Strikethrough => "strikethrough".to_string(),
+        NodeValue::Superscript => "superscript".to_string(),
+        NodeValue::Link(link) => format!("link {:?}", lossy(&link.url)),
+        NodeValue::Image(link) => format!("image {:?}", lossy(&link.url)),
+        NodeValue::FootnoteReference(label) => format!("footnote_reference {:?}", lossy(label)),
+    }
+}
+
+// comrak counts lines from the end of the front matter, report lines of the
+// file instead
+pub fn start_line<'a>(node: &'a AstNode<'a>) -> u32 {
+    let start = node.data.borrow().start_line;
+    if start == 0 {
+        return 0;
+    }
+    let root = node.ancestors().last().unwrap_or(node);
+    let front_matter = root
+        .first_child()
+        .map_or(0, |first| match first.data.borrow().value {
+            NodeValue::FrontMatter(ref literal) => literal.iter().filter(|c| **c == b'\n').count(),
+            _ => 0,
+        });
+    start + front_matter as u32
+}
+
+// the file line of any node, inline nodes have no position of their own and
+// take their block's
+pub fn line<'a>(node: &'a AstNode<'a>) -> usize {
+    node.ancestors()
+        .map(start_line)
+        .find(|line| *line > 0)
+        .unwrap_or(0) as usize
+}
+
+// comrak only records where a block starts. a block ends where the next one
+// starts, or where its parent ends
+fn end_line<'a>(node: &'a AstNode<'a>) -> Option<u32> {
+    let mut current = node;
+    loop {
+        if let Some(next) = current.next_sibling() {
+            let start = start_line(next);
+            if start > 0 {
+                return Some(start.saturating_sub(1).max(start_line(node)));
+            }
+        }
+        current = current.parent()?;
+    }
+}
+
+fn position<'a>(node: &'a AstNode<'a>) -> String {
+    let start = start_line(node);
+    if start == 0 {
+        return String::new();
+    }
+    match end_line(node) {
+        Some(end) if end != start => format!(" [{}-{}]", start, end),
+        Some(_) => format!(" [{}]", start),
+        None => format!(" [{}-]", start),
+    }
+}
+
+// every node of the tree, indented by depth, with the source lines of blocks
+pub fn events<'a>(root: &'a AstNode<'a>) -> String {
+    let mut output = String::new();
+    for node in root.descendants() {
+        let depth = node.ancestors().count() - 1;
+        writeln!(
+            output,
+            "{}{}{}",
+            "  ".repeat(depth),
+            describe(&node.data.borrow().value),
+            position(node)
+        )
+        .expect("Writing to a string cannot fail");
+    }
+    output
+}
+
+// the blocks covering a source line, outermost first
+pub fn covering<'a>(root: &'a AstNode<'a>, line: u32) -> Vec<&'a AstNode<'a>> {
+    let mut found = Vec::new();
+    let mut parent = root;
+    while let Some(node) = parent.children().find(|child| {
+        let start = start_line(child);
+        start > 0 && start <= line && end_line(child).is_none_or(|end| line <= end)
+    }) {
+        found.push(node);
+        parent = node;
+    }
+    found
+}
+
+pub fn describe_node<'a>(node: &'a AstNode<'a>) -> String {
+    format!("{}{}", describe(&node.data.borrow().value), position(node))
+}
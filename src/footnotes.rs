@@ -0,0 +1,238 @@
+// rewrites pandoc style inline footnotes `^[like this]` into labeled reference
+// footnotes appended to the document, so comrak's footnote support renders
+// them. code blocks, code spans and escaped carets are left alone
+
+struct Note {
+    depth: usize,
+    text: String,
+}
+
+// an opening code fence: the fence character and its length
+pub fn fence(line: &str) -> Option<(char, usize)> {
+    let trimmed = line.trim_start_matches(' ');
+    if line.len() - trimmed.len() > 3 {
+        return None;
+    }
+    let c = trimmed.chars().next().filter(|c| *c == '`' || *c == '~')?;
+    let length = trimmed.chars().take_while(|x| *x == c).count();
+    if length < 3 || (c == '`' && trimmed[length..].contains('`')) {
+        return None;
+    }
+    Some((c, length))
+}
+
+pub fn closes(line: &str, (c, length): (char, usize)) -> bool {
+    let trimmed = line.trim();
+    trimmed.len() >= length && trimmed.chars().all(|x| x == c)
+}
+
+fn indent(line: &str) -> usize {
+    line.chars()
+        .take_while(|c| *c == ' ' || *c == '\t')
+        .map(|c| if c == '\t' { 4 } else { 1 })
+        .sum()
+}
+
+fn scan(line: &str, output: &mut String, note: &mut Option<Note>, notes: &mut Vec<String>) {
+    let chars: Vec<char> = line.chars().collect();
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        let target = match note {
+            Some(ref mut note) => &mut note.text,
+            None => &mut *output,
+        };
+        if c == '\\' && i + 1 < chars.len() {
+            target.push(c);
+            target.push(chars[i + 1]);
+            i += 2;
+            continue;
+        }
+        if c == '`' {
+            let run = chars[i..].iter().take_while(|x| **x == '`').count();
+            let mut end = None;
+            let mut j = i + run;
+            while j < chars.len() {
+                let closing = chars[j..].iter().take_while(|x| **x == '`').count();
+                if closing == run {
+                    end = Some(j + closing);
+                    break;
+                }
+                j += closing.max(1);
+            }
+            let end = end.unwrap_or(i + run);
+            target.extend(&chars[i..end]);
+            i = end;
+            continue;
+        }
+        match note {
+            None if c == '^' && chars.get(i + 1) == Some(&'[') => {
+                *note = Some(Note {
+                    depth: 1,
+                    text: String::new(),
+                });
+                i += 2;
+                continue;
+            }
+            None => output.push(c),
+            Some(ref mut open) if c == ']' && open.depth == 1 => {
+                let text = open.text.split_whitespace().collect::<Vec<_>>().join(" ");
+                if text.is_empty() {
+                    output.push_str("^[");
+                    output.push_str(&open.text);
+                    output.push(']');
+                } else {
+                    notes.push(text);
+                    output.push_str(&format!("\u{0}{}\u{0}", notes.len() - 1));
+                }
+                *note = None;
+            }
+            Some(ref mut open) => {
+                match c {
+                    '[' => open.depth += 1,
+                    ']' => open.depth -= 1,
+                    _ => {}
+                }
+                open.text.push(c);
+            }
+        }
+        i += 1;
+    }
+}
+
+// an unterminated note goes back into the text as it was written
+fn abandon(output: &mut String, note: &mut Option<Note>) {
+    if let Some(note) = note.take() {
+        output.push_str("^[");
+        output.push_str(&note.text);
+    }
+}
+
+pub fn expand(markdown: &str) -> String {
+    if !markdown.contains("^[") {
+        return markdown.to_string();
+    }
+    let mut output = String::with_capacity(markdown.len());
+    let mut notes = Vec::new();
+    let mut note = None;
+    let mut open_fence = None;
+    let mut indented = false;
+    let mut previous_blank = true;
+
+    for line in markdown.split_inclusive('\n') {
+        let content = line.trim_end_matches(['\n', '\r']);
+        let blank = content.trim().is_empty();
+        if let Some(open) = open_fence {
+            output.push_str(line);
+            if closes(content, open) {
+                open_fence = None;
+            }
+            previous_blank = blank;
+            continue;
+        }
+        if note.is_none() {
+            if let Some(opened) = fence(content) {
+                open_fence = Some(opened);
+                output.push_str(line);
+                previous_blank = false;
+                continue;
+            }
+            if !blank && indent(content) >= 4 && (previous_blank || indented) {
+                indented = true;
+                output.push_str(line);
+                previous_blank = false;
+                continue;
+            }
+        }
+        if blank {
+            abandon(&mut output, &mut note);
+        } else {
+            indented = false;
+        }
+        scan(line, &mut output, &mut note, &mut notes);
+        previous_blank = blank;
+    }
+    abandon(&mut output, &mut note);
+    if notes.is_empty() {
+        return output;
+    }
+
+    // labels must not clash with footnotes the author already wrote
+    let mut labels = Vec::new();
+    let mut counter = 0;
+    while labels.len() < notes.len() {
+        counter += 1;
+        let label = format!("inline-{}", counter);
+        if !markdown.contains(&format!("[^{}]", label)) {
+            labels.push(label);
+        }
+    }
+    for (index, label) in labels.iter().enumerate() {
+        output = output.replacen(&format!("\u{0}{}\u{0}", index), &format!("[^{}]", label), 1);
+    }
+    if !output.ends_with('\n') {
+        output.push('\n');
+    }
+    for (label, text) in labels.iter().zip(&notes) {
+        output.push_str(&format!("\n[^{}]: {}\n", label, text));
+    }
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn notes_become_reference_footnotes() {
+        assert_eq!(
+            expand("Text^[a note] here.\n"),
+            "Text[^inline-1] here.\n\n[^inline-1]: a note\n"
+        );
+    }
+
+    #[test]
+    fn nested_brackets_stay_in_the_note() {
+        assert_eq!(
+            expand("See^[the [manual](https://example.com) and [1]].\n"),
+            "See[^inline-1].\n\n[^inline-1]: the [manual](https://example.com) and [1]\n"
+        );
+    }
+
+    #[test]
+    fn notes_in_one_paragraph_are_numbered_in_order() {
+        assert_eq!(
+            expand("One^[first] and two^[second\nline].\n"),
+            "One[^inline-1] and two[^inline-2].\n\n\
+             [^inline-1]: first\n\n[^inline-2]: second line\n"
+        );
+    }
+
+    #[test]
+    fn labels_skip_the_authors_own() {
+        assert_eq!(
+            expand("A^[new] and B[^inline-1].\n\n[^inline-1]: old\n"),
+            "A[^inline-2] and B[^inline-1].\n\n[^inline-1]: old\n\n[^inline-2]: new\n"
+        );
+    }
+
+    #[test]
+    fn code_and_escapes_are_left_alone() {
+        for markdown in [
+            "`x^[y]` and \\^[z]\n",
+            "```\n^[in a fence]\n```\n",
+            "text\n\n    ^[indented code]\n",
+        ] {
+            assert_eq!(expand(markdown), markdown);
+        }
+    }
+
+    #[test]
+    fn unterminated_notes_stay_text() {
+        assert_eq!(
+            expand("an ^[open note\n\nnext\n"),
+            "an ^[open note\n\nnext\n"
+        );
+        assert_eq!(expand("empty ^[ ] one\n"), "empty ^[ ] one\n");
+    }
+}
@@ -0,0 +1,221 @@
+use crate::diagnostic::Diagnostic;
+use comrak::nodes::{AstNode, NodeValue};
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+pub const DEFAULTS_FILE: &str = "_defaults.md";
+
+// the raw front matter block of a parsed document, delimiters included
+pub fn raw<'a>(root: &'a AstNode<'a>) -> Option<String> {
+    root.children()
+        .find_map(|node| match node.data.borrow().value {
+            NodeValue::FrontMatter(ref literal) => {
+                Some(String::from_utf8_lossy(literal).to_string())
+            }
+            _ => None,
+        })
+}
+
+// looks up a top level `key: value` pair, stripping surrounding quotes
+pub fn value(front_matter: &str, key: &str) -> Option<String> {
+    front_matter.lines().find_map(|line| {
+        let (name, value) = line.split_once(':')?;
+        if name.trim() != key || line.starts_with(char::is_whitespace) {
+            return None;
+        }
+        let value = value.trim();
+        let value = value
+            .strip_prefix('"')
+            .and_then(|v| v.strip_suffix('"'))
+            .or_else(|| value.strip_prefix('\'').and_then(|v| v.strip_suffix('\'')))
+            .unwrap_or(value);
+        if value.is_empty() {
+            None
+        } else {
+            Some(value.to_string())
+        }
+    })
+}
+
+// a top level key of a front matter block with its lines, nested ones
+// included
+struct Entry {
+    key: String,
+    lines: Vec<String>,
+}
+
+impl Entry {
+    // the items of a `[a, b]` or `- a` list value, lists merge instead of
+    // replacing each other
+    fn items(&self) -> Option<Vec<String>> {
+        let value = self.lines[0].split_once(':')?.1.trim();
+        if let Some(inner) = value.strip_prefix('[').and_then(|v| v.strip_suffix(']')) {
+            return Some(
+                inner
+                    .split(',')
+                    .map(|item| item.trim().to_string())
+                    .filter(|item| !item.is_empty())
+                    .collect(),
+            );
+        }
+        if !value.is_empty() || self.lines.len() == 1 {
+            return None;
+        }
+        self.lines[1..]
+            .iter()
+            .map(|line| {
+                line.trim_start()
+                    .strip_prefix("- ")
+                    .map(|item| item.trim().to_string())
+            })
+            .collect()
+    }
+}
+
+// top level keys in order of appearance, delimiters and comments skipped
+fn entries(front_matter: &str) -> Vec<Entry> {
+    let mut entries: Vec<Entry> = Vec::new();
+    for line in front_matter.lines() {
+        if line == "---" || line == "..." || line.trim().is_empty() {
+            continue;
+        }
+        if line.starts_with(char::is_whitespace) {
+            if let Some(entry) = entries.last_mut() {
+                entry.lines.push(line.to_string());
+            }
+            continue;
+        }
+        match line.split_once(':') {
+            Some((key, _)) if !line.starts_with('#') => entries.push(Entry {
+                key: key.trim().to_string(),
+                lines: vec![line.to_string()],
+            }),
+            _ => {}
+        }
+    }
+    entries
+}
+
+// the top level keys of a front matter block with their values, lists as
+// arrays and everything else as text. nested maps are left out
+pub fn fields(front_matter: &str) -> serde_json::Map<String, serde_json::Value> {
+    let mut fields = serde_json::Map::new();
+    for entry in entries(front_matter) {
+        let value = match entry.items() {
+            Some(items) => serde_json::Value::from(items),
+            None => match value(front_matter, &entry.key) {
+                Some(value) => serde_json::Value::from(value),
+                None => continue,
+            },
+        };
+        fields.insert(entry.key, value);
+    }
+    fields
+}
+
+// merge front matter blocks, later ones win. a list value is added to the
+// list before it, without repeating items, anything else replaces the value
+// before it. the result is a front matter block of its own
+pub fn merge(layers: &[String]) -> Option<String> {
+    let mut merged: Vec<Entry> = Vec::new();
+    for layer in layers {
+        for entry in entries(layer) {
+            let earlier = match merged.iter_mut().find(|earlier| earlier.key == entry.key) {
+                Some(earlier) => earlier,
+                None => {
+                    merged.push(entry);
+                    continue;
+                }
+            };
+            match (earlier.items(), entry.items()) {
+                (Some(mut items), Some(more)) => {
+                    for item in more {
+                        if !items.contains(&item) {
+                            items.push(item);
+                        }
+                    }
+                    earlier.lines = vec![format!("{}:", entry.key)];
+                    earlier
+                        .lines
+                        .extend(items.iter().map(|item| format!("  - {}", item)));
+                }
+                _ => earlier.lines = entry.lines,
+            }
+        }
+    }
+    if merged.is_empty() {
+        return None;
+    }
+    let mut output = String::from("---\n");
+    for entry in merged {
+        for line in entry.lines {
+            output.push_str(&line);
+            output.push('\n');
+        }
+    }
+    output.push_str("---\n");
+    Some(output)
+}
+
+// the front matter at the top of a markdown text, without its opening
+// delimiter, and the rest of the text
+pub fn leading(text: &str) -> Option<(&str, &str)> {
+    let rest = text
+        .strip_prefix("---\n")
+        .or_else(|| text.strip_prefix("---\r\n"))?;
+    let end = rest
+        .split_inclusive('\n')
+        .position(|line| line.trim_end() == "---")?;
+    let front_matter: usize = rest.split_inclusive('\n').take(end + 1).map(str::len).sum();
+    Some((&rest[..front_matter], &rest[front_matter..]))
+}
+
+// the front matter of every `_defaults.md` from the current directory down to
+// the document's, outermost first. a document outside the current directory
+// only gets the defaults next to it
+pub fn defaults(path: &Path, warnings: &mut Vec<Diagnostic>) -> Vec<String> {
+    let directory = match fs::canonicalize(path) {
+        Ok(absolute) => absolute.parent().map(Path::to_path_buf).unwrap_or_default(),
+        Err(_) => return Vec::new(),
+    };
+    let mut directories: Vec<PathBuf> = directory.ancestors().map(Path::to_path_buf).collect();
+    directories.reverse();
+    let top = env::current_dir()
+        .and_then(fs::canonicalize)
+        .ok()
+        .filter(|current| directory.starts_with(current));
+    directories.retain(|ancestor| match &top {
+        Some(top) => ancestor.starts_with(top),
+        None => *ancestor == directory,
+    });
+
+    let mut layers = Vec::new();
+    for ancestor in directories {
+        let file = ancestor.join(DEFAULTS_FILE);
+        let text = match fs::read_to_string(&file) {
+            Ok(text) => text,
+            Err(_) => continue,
+        };
+        match leading(&text) {
+            Some((front_matter, body)) => {
+                if !body.trim().is_empty() {
+                    warnings.push(Diagnostic::warning(
+                        "defaults-body",
+                        &file,
+                        text[..text.len() - body.len()].lines().count() + 1,
+                        "only the front matter of a defaults file is used, the rest is ignored",
+                    ));
+                }
+                layers.push(front_matter.to_string());
+            }
+            None => warnings.push(Diagnostic::warning(
+                "defaults-body",
+                &file,
+                1,
+                "a defaults file should hold only front matter between --- lines",
+            )),
+        }
+    }
+    layers
+}
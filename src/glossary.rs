@@ -0,0 +1,265 @@
+use crate::collate;
+use crate::degradations::Degradations;
+use crate::dialect::{self, Syntax};
+use crate::html::{self, escape};
+use crate::include::heading_text;
+use crate::paths;
+use comrak::nodes::{AstNode, NodeHeading, NodeHtmlBlock, NodeValue};
+use comrak::Arena;
+use regex::{Regex, RegexBuilder};
+use std::fs;
+use std::path::Path;
+
+struct Entry {
+    term: String,
+    slug: String,
+    // rendered definition, none for terms only marked in the text
+    definition: Option<String>,
+}
+
+// terms from a glossary file, where every heading is a term and the blocks
+// up to the next heading its definition
+pub struct Glossary {
+    entries: Vec<Entry>,
+    case_sensitive: bool,
+}
+
+fn slug(term: &str) -> String {
+    format!(
+        "glossary-{}",
+        comrak::Anchorizer::new().anchorize(term.to_string())
+    )
+}
+
+impl Glossary {
+    pub fn load(path: &Path, syntax: &Syntax, case_sensitive: bool) -> Result<Glossary, String> {
+        let markdown = fs::read_to_string(path)
+            .map_err(|err| format!("cannot read glossary {}: {}", paths::display(path), err))?;
+        let options = dialect::create_comrak_options(syntax);
+        let arena = Arena::new();
+        let root = comrak::parse_document(&arena, &markdown, &options);
+        html::omit_raw(root, &mut Degradations::default());
+        let mut entries: Vec<Entry> = Vec::new();
+        for node in root.children() {
+            if matches!(node.data.borrow().value, NodeValue::Heading(..)) {
+                let term = heading_text(node).trim().to_string();
+                entries.push(Entry {
+                    slug: slug(&term),
+                    term,
+                    definition: Some(String::new()),
+                });
+                continue;
+            }
+            if let Some(Some(definition)) = entries.last_mut().map(|entry| &mut entry.definition) {
+                let mut rendered = vec![];
+                comrak::format_html(node, &options, &mut rendered).map_err(|err| {
+                    format!("cannot render glossary {}: {}", paths::display(path), err)
+                })?;
+                definition.push_str(&String::from_utf8_lossy(&rendered));
+            }
+        }
+        Ok(Glossary {
+            entries,
+            case_sensitive,
+        })
+    }
+
+    fn find(&self, term: &str) -> Option<usize> {
+        self.entries.iter().position(|entry| {
+            if self.case_sensitive {
+                entry.term == term
+            } else {
+                entry.term.to_lowercase() == term.to_lowercase()
+            }
+        })
+    }
+
+    fn pattern(&self) -> Option<Regex> {
+        let mut terms: Vec<&str> = self.entries.iter().map(|e| e.term.as_str()).collect();
+        if terms.is_empty() {
+            return None;
+        }
+        // longer terms first so `API key` wins over `API`
+        terms.sort_by_key(|term| std::cmp::Reverse(term.len()));
+        let alternation: Vec<String> = terms.iter().map(|term| regex::escape(term)).collect();
+        RegexBuilder::new(&alternation.join("|"))
+            .case_insensitive(!self.case_sensitive)
+            .build()
+            .ok()
+    }
+}
+
+fn is_word(c: Option<char>) -> bool {
+    c.is_some_and(|c| c.is_alphanumeric() || c == '_')
+}
+
+// the next match that is a whole word, as a byte range
+fn find_word(pattern: &Regex, content: &str, from: usize) -> Option<(usize, usize)> {
+    let mut position = from;
+    while let Some(found) = pattern.find_at(content, position) {
+        if !is_word(content[..found.start()].chars().last())
+            && !is_word(content[found.end()..].chars().next())
+        {
+            return Some((found.start(), found.end()));
+        }
+        position = found.start()
+            + content[found.start()..]
+                .chars()
+                .next()
+                .map_or(1, char::len_utf8);
+    }
+    None
+}
+
+fn text<'a>(arena: &'a Arena<AstNode<'a>>, text: &str) -> &'a AstNode<'a> {
+    arena.alloc(AstNode::from(NodeValue::Text(text.as_bytes().to_vec())))
+}
+
+fn inline_html<'a>(arena: &'a Arena<AstNode<'a>>, html: String) -> &'a AstNode<'a> {
+    arena.alloc(AstNode::from(NodeValue::HtmlInline(html.into_bytes())))
+}
+
+// text where a term may be linked: not in headings, links or images
+fn candidates<'a>(root: &'a AstNode<'a>) -> Vec<&'a AstNode<'a>> {
+    root.descendants()
+        .filter(|node| matches!(node.data.borrow().value, NodeValue::Text(..)))
+        .filter(|node| {
+            !node.ancestors().any(|ancestor| {
+                matches!(
+                    ancestor.data.borrow().value,
+                    NodeValue::Heading(..) | NodeValue::Link(..) | NodeValue::Image(..)
+                )
+            })
+        })
+        .collect()
+}
+
+// link the first use of every term to its entry and append a glossary of the
+// terms used. `*[Term]{glossary}` marks a use explicitly and adds terms the
+// glossary file does not define. generates inline html, so it has to run
+// after `html::omit_raw`
+pub fn link<'a>(arena: &'a Arena<AstNode<'a>>, root: &'a AstNode<'a>, mut glossary: Glossary) {
+    let marker = Regex::new(r"\*\[([^\]]+)\]\{glossary\}").expect("marker pattern should compile");
+    // terms only known from markers join the glossary before any linking
+    for node in candidates(root) {
+        if let NodeValue::Text(ref literal) = node.data.borrow().value {
+            for captures in marker.captures_iter(&String::from_utf8_lossy(literal)) {
+                let term = captures[1].trim().to_string();
+                if glossary.find(&term).is_none() {
+                    glossary.entries.push(Entry {
+                        slug: slug(&term),
+                        term,
+                        definition: None,
+                    });
+                }
+            }
+        }
+    }
+    let pattern = match glossary.pattern() {
+        Some(pattern) => pattern,
+        None => return,
+    };
+    let mut used = vec![false; glossary.entries.len()];
+
+    for node in candidates(root) {
+        let content = match node.data.borrow().value {
+            NodeValue::Text(ref literal) => String::from_utf8_lossy(literal).to_string(),
+            _ => continue,
+        };
+        let mut pieces = Vec::new();
+        let mut last = 0;
+        let mut position = 0;
+        loop {
+            // `*[Term]{glossary}` as (start, end, term)
+            let explicit = marker.captures(&content[position..]).map(|captures| {
+                let whole = captures.get(0).unwrap();
+                let term = captures.get(1).unwrap();
+                (
+                    position + whole.start(),
+                    position + whole.end(),
+                    &content[position + term.start()..position + term.end()],
+                )
+            });
+            let implicit = find_word(&pattern, &content, position);
+            // a marker at or before the next plain use goes first
+            let (start, end, term) = match (explicit, implicit) {
+                (Some(marked), found) if found.is_none_or(|found| marked.0 <= found.0) => marked,
+                (_, Some((start, end))) => (start, end, &content[start..end]),
+                (_, None) => break,
+            };
+            let index = match glossary.find(term.trim()) {
+                Some(index) => index,
+                None => {
+                    position = end;
+                    continue;
+                }
+            };
+            let explicit = explicit.is_some_and(|marked| marked.0 == start);
+            if used[index] && !explicit {
+                position = end;
+                continue;
+            }
+            pieces.push(text(arena, &content[last..start]));
+            if used[index] {
+                pieces.push(text(arena, term));
+            } else {
+                used[index] = true;
+                let slug = &glossary.entries[index].slug;
+                pieces.push(inline_html(
+                    arena,
+                    format!(
+                        "<a href=\"#{}\" id=\"{}-use\" class=\"yamc-glossary-term\">",
+                        slug, slug
+                    ),
+                ));
+                pieces.push(text(arena, term));
+                pieces.push(inline_html(arena, "</a>".to_string()));
+            }
+            last = end;
+            position = end;
+        }
+        if pieces.is_empty() {
+            continue;
+        }
+        pieces.push(text(arena, &content[last..]));
+        for piece in pieces {
+            node.insert_before(piece);
+        }
+        node.detach();
+    }
+
+    let mut entries: Vec<&Entry> = glossary
+        .entries
+        .iter()
+        .zip(&used)
+        .filter(|(_, used)| **used)
+        .map(|(entry, _)| entry)
+        .collect();
+    if entries.is_empty() {
+        return;
+    }
+    entries.sort_by(|a, b| collate::compare(&a.term, &b.term));
+    let heading = arena.alloc(AstNode::from(NodeValue::Heading(NodeHeading {
+        level: 2,
+        setext: false,
+    })));
+    heading.append(text(arena, "Glossary"));
+    root.append(heading);
+    let mut list = String::from("<dl class=\"yamc-glossary\">\n");
+    for entry in entries {
+        list.push_str(&format!(
+            "<dt id=\"{}\">{}</dt>\n<dd>{}<a href=\"#{}-use\" class=\"yamc-glossary-backlink\">↩</a></dd>\n",
+            entry.slug,
+            escape(&entry.term),
+            entry.definition.as_deref().unwrap_or(""),
+            entry.slug
+        ));
+    }
+    list.push_str("</dl>\n");
+    root.append(
+        arena.alloc(AstNode::from(NodeValue::HtmlBlock(NodeHtmlBlock {
+            block_type: 0,
+            literal: list.into_bytes(),
+        }))),
+    );
+}
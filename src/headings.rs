@@ -0,0 +1,361 @@
+use crate::explain;
+use crate::html::escape;
+use crate::summary;
+use comrak::nodes::{AstNode, NodeValue};
+
+// move every heading by `offset` levels, keeping them within h1..h6.
+// returns the lines of the headings that had to be clamped
+pub fn shift<'a>(root: &'a AstNode<'a>, offset: i32) -> Vec<usize> {
+    let mut clamped = Vec::new();
+    for node in root.descendants() {
+        let line = explain::line(node);
+        if let NodeValue::Heading(ref mut heading) = node.data.borrow_mut().value {
+            let level = heading.level as i32 + offset;
+            if !(1..=6).contains(&level) {
+                clamped.push(line);
+            }
+            heading.level = level.clamp(1, 6) as u32;
+        }
+    }
+    clamped
+}
+
+fn first_h1<'a>(root: &'a AstNode<'a>) -> Option<&'a AstNode<'a>> {
+    root.descendants()
+        .find(|node| matches!(node.data.borrow().value, NodeValue::Heading(ref heading) if heading.level == 1))
+}
+
+// the text of the first h1, the title the document gives itself
+pub fn title<'a>(root: &'a AstNode<'a>) -> Option<String> {
+    Some(summary::plain_text(first_h1(root)?)).filter(|text| !text.is_empty())
+}
+
+// remove the first h1 from the document, it usually repeats the page title
+pub fn strip_title<'a>(root: &'a AstNode<'a>) {
+    if let Some(title) = first_h1(root) {
+        title.detach();
+    }
+}
+
+// a trailing `{#id .class key="value"}` block on a heading line. `.no-toc`
+// and `toc-title` are read by yamc itself and never written out
+#[derive(Default)]
+pub struct Attributes {
+    pub id: Option<String>,
+    pub classes: Vec<String>,
+    pub pairs: Vec<(String, String)>,
+}
+
+const OWN_CLASSES: &[&str] = &["no-toc"];
+const OWN_KEYS: &[&str] = &["toc-title"];
+
+fn is_name(name: &str) -> bool {
+    let mut chars = name.chars();
+    chars
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || "-_:.".contains(c))
+}
+
+fn parse_attributes(block: &str) -> Option<Attributes> {
+    let mut attributes = Attributes::default();
+    let mut rest = block.trim();
+    while !rest.is_empty() {
+        let (token, remaining) = match rest.find(|c: char| c.is_whitespace() || c == '"') {
+            Some(end) if rest[end..].starts_with('"') => {
+                let closing = rest[end + 1..].find('"')? + end + 1;
+                (&rest[..closing + 1], &rest[closing + 1..])
+            }
+            Some(end) => (&rest[..end], &rest[end..]),
+            None => (rest, ""),
+        };
+        if let Some(id) = token.strip_prefix('#') {
+            if id.is_empty() || id.contains('"') {
+                return None;
+            }
+            attributes.id = Some(id.to_string());
+        } else if let Some(class) = token.strip_prefix('.') {
+            if !is_name(class) {
+                return None;
+            }
+            attributes.classes.push(class.to_string());
+        } else {
+            let (key, value) = token.split_once('=')?;
+            let value = match value.strip_prefix('"') {
+                Some(quoted) => quoted.strip_suffix('"')?,
+                None if !value.contains('"') => value,
+                None => return None,
+            };
+            if !is_name(key) {
+                return None;
+            }
+            attributes.pairs.push((key.to_string(), value.to_string()));
+        }
+        rest = remaining.trim_start();
+    }
+    if attributes.id.is_none() && attributes.classes.is_empty() && attributes.pairs.is_empty() {
+        return None;
+    }
+    Some(attributes)
+}
+
+// the heading text without its attribute block, if it has one
+pub fn split_attributes(text: &str) -> Option<(&str, Attributes)> {
+    let body = text.trim_end().strip_suffix('}')?;
+    let start = body.rfind('{')?;
+    if !text[..start].ends_with(char::is_whitespace) {
+        return None;
+    }
+    let attributes = parse_attributes(&body[start + 1..])?;
+    Some((text[..start].trim_end(), attributes))
+}
+
+// remove attribute blocks from every heading, returning them in document order
+// for `apply_attributes`
+pub fn take_attributes<'a>(root: &'a AstNode<'a>) -> Vec<Attributes> {
+    let mut found = Vec::new();
+    for node in root.descendants() {
+        if !matches!(node.data.borrow().value, NodeValue::Heading(..)) {
+            continue;
+        }
+        let attributes = node.last_child().and_then(|last| {
+            let mut data = last.data.borrow_mut();
+            let literal = match data.value {
+                NodeValue::Text(ref mut literal) => literal,
+                _ => return None,
+            };
+            let text = String::from_utf8_lossy(literal).to_string();
+            let (stripped, attributes) = split_attributes(&text)?;
+            *literal = stripped.as_bytes().to_vec();
+            Some(attributes)
+        });
+        found.push(attributes.unwrap_or_default());
+    }
+    found
+}
+
+fn heading_tag(html: &str) -> Option<usize> {
+    let mut position = 0;
+    while let Some(found) = html[position..].find("<h") {
+        let start = position + found;
+        let bytes = html.as_bytes();
+        if matches!(bytes.get(start + 2), Some(b'1'..=b'6')) && bytes.get(start + 3) == Some(&b'>')
+        {
+            return Some(start);
+        }
+        position = start + 2;
+    }
+    None
+}
+
+// the empty anchor comrak starts a rendered heading with, found in the
+// final html: where it ends, the heading's level and its id
+pub struct Anchor {
+    pub end: usize,
+    pub level: usize,
+    pub id: String,
+}
+
+// the anchors of every heading in rendered html, in document order. text in
+// code blocks is escaped and never looks like one
+pub fn anchors(html: &str) -> Vec<Anchor> {
+    let mut found = Vec::new();
+    let mut position = 0;
+    while let Some(offset) = html[position..].find("<a href=\"#") {
+        let start = position + offset;
+        position = start + 1;
+        let rest = &html[start..];
+        let id = match rest["<a href=\"#".len()..].split_once('"') {
+            Some((id, _)) => id,
+            None => continue,
+        };
+        let anchor = format!(
+            "<a href=\"#{}\" aria-hidden=\"true\" class=\"anchor\" id=\"{}\"></a>",
+            id, id
+        );
+        if !rest.starts_with(&anchor) {
+            continue;
+        }
+        // right after `<hN>` or `<hN class="...">`
+        let before = &html[..start];
+        let tag = &before[before.rfind('<').unwrap_or(0)..];
+        let level = match tag.as_bytes() {
+            [b'<', b'h', digit @ b'1'..=b'6', b'>' | b' ', ..] if tag.ends_with('>') => {
+                usize::from(digit - b'0')
+            }
+            _ => continue,
+        };
+        found.push(Anchor {
+            end: start + anchor.len(),
+            level,
+            id: id.to_string(),
+        });
+    }
+    found
+}
+
+const PERMALINK: &str = "<span class=\"yamc-permalink\">¶</span>";
+
+const PERMALINK_STYLE: &str = "<style>
+.anchor .yamc-permalink { visibility: hidden; font-weight: normal; text-decoration: none; }
+h1:hover .yamc-permalink, h2:hover .yamc-permalink, h3:hover .yamc-permalink,
+h4:hover .yamc-permalink, h5:hover .yamc-permalink, h6:hover .yamc-permalink,
+.anchor:focus .yamc-permalink { visibility: visible; }
+@media print {
+  .anchor .yamc-permalink { display: none; }
+}
+</style>
+";
+
+// give the anchor of every heading a mark that shows on hover, so a link to
+// the heading can be copied
+pub fn permalinks(html: String) -> String {
+    let anchors = anchors(&html);
+    if anchors.is_empty() {
+        return html;
+    }
+    let mut output = String::with_capacity(html.len() + anchors.len() * PERMALINK.len());
+    output.push_str(PERMALINK_STYLE);
+    let mut copied = 0;
+    for anchor in anchors {
+        let closing = anchor.end - "</a>".len();
+        output.push_str(&html[copied..closing]);
+        output.push_str(PERMALINK);
+        copied = closing;
+    }
+    output.push_str(&html[copied..]);
+    output
+}
+
+pub const DEFAULT_ID_PREFIX: &str = "section-";
+
+// whether comrak found nothing to make an id of: empty, punctuation or emoji
+// only headings get `""`, `"-"` or such, then `"-1"` and so on
+fn is_blank(id: &str) -> bool {
+    let base = id
+        .rsplit_once('-')
+        .filter(|(_, number)| !number.is_empty() && number.chars().all(|c| c.is_ascii_digit()))
+        .map_or(id, |(base, _)| base);
+    !base.chars().any(char::is_alphanumeric)
+}
+
+// a generated id that works as a css selector: `prefix` before one starting
+// with a digit, `prefix` and the heading's position for a blank one
+pub fn usable_id(generated: &str, position: usize, prefix: &str) -> String {
+    if is_blank(generated) {
+        format!("{}{}", prefix, position)
+    } else if generated.starts_with(|c: char| c.is_ascii_digit()) {
+        format!("{}{}", prefix, generated)
+    } else {
+        generated.to_string()
+    }
+}
+
+// with `extra`, give the rendered headings the ids, classes and other
+// attributes of their attribute blocks. headings are matched to `attributes` in document order.
+// generated ids are made usable with `prefix` (see `usable_id`), links to
+// them follow. fails when two headings end up with the same id
+pub fn apply_attributes(
+    html: String,
+    attributes: Vec<Attributes>,
+    extra: bool,
+    prefix: &str,
+) -> Result<String, String> {
+    let mut output = String::with_capacity(html.len());
+    let mut ids: Vec<String> = Vec::new();
+    let mut renamed: Vec<(String, String)> = Vec::new();
+    let mut rest = html.as_str();
+    let mut attributes = attributes.into_iter();
+    let mut position = 0;
+    while let Some(start) = heading_tag(rest) {
+        let attributes = attributes.next().unwrap_or_default();
+        position += 1;
+        // `<hN>`, then comrak's anchor `<a href="#id" aria-hidden="true" class="anchor" id="id"></a>`
+        output.push_str(&rest[..start + 3]);
+        if extra {
+            let classes: Vec<&str> = attributes
+                .classes
+                .iter()
+                .map(String::as_str)
+                .filter(|class| !OWN_CLASSES.contains(class))
+                .collect();
+            if !classes.is_empty() {
+                output.push_str(&format!(" class=\"{}\"", escape(&classes.join(" "))));
+            }
+            for (key, value) in &attributes.pairs {
+                if !OWN_KEYS.contains(&key.as_str()) && key != "class" && key != "id" {
+                    output.push_str(&format!(" {}=\"{}\"", key, escape(value)));
+                }
+            }
+        }
+        output.push('>');
+        rest = &rest[start + 4..];
+        let generated = match rest
+            .strip_prefix("<a href=\"#")
+            .and_then(|after| after.split_once('"'))
+        {
+            Some((generated, _)) => generated.to_string(),
+            None => continue,
+        };
+        let anchor = format!(
+            "<a href=\"#{}\" aria-hidden=\"true\" class=\"anchor\" id=\"{}\">",
+            generated, generated
+        );
+        if !rest.starts_with(&anchor) {
+            continue;
+        }
+        let id = match attributes.id {
+            Some(id) if extra => escape(&id),
+            _ => {
+                let mut id = usable_id(&generated, position, prefix);
+                // a prefixed id may meet one comrak generated as it is
+                let base = id.clone();
+                let mut number = 0;
+                while ids.contains(&id) {
+                    number += 1;
+                    id = format!("{}-{}", base, number);
+                }
+                if id != generated && !is_blank(&generated) {
+                    renamed.push((generated, id.clone()));
+                }
+                id
+            }
+        };
+        if ids.contains(&id) {
+            return Err(format!("more than one heading has the id {:?}", id));
+        }
+        output.push_str(&format!(
+            "<a href=\"#{}\" aria-hidden=\"true\" class=\"anchor\" id=\"{}\">",
+            id, id
+        ));
+        ids.push(id);
+        rest = &rest[anchor.len()..];
+    }
+    output.push_str(rest);
+    Ok(follow(output, &renamed))
+}
+
+// point links at the ids their headings ended up with, leaving the headings'
+// own anchors alone
+fn follow(html: String, renamed: &[(String, String)]) -> String {
+    if renamed.is_empty() {
+        return html;
+    }
+    let mut output = String::with_capacity(html.len());
+    let mut rest = html.as_str();
+    while let Some(start) = rest.find("href=\"#") {
+        let after = start + "href=\"#".len();
+        output.push_str(&rest[..after]);
+        rest = &rest[after..];
+        let end = rest.find('"').unwrap_or(rest.len());
+        let target = &rest[..end];
+        let anchor = rest[end..].starts_with("\" aria-hidden=\"true\" class=\"anchor\"");
+        match renamed.iter().find(|(generated, _)| generated == target) {
+            Some((_, id)) if !anchor => output.push_str(id),
+            _ => output.push_str(target),
+        }
+        rest = &rest[end..];
+    }
+    output.push_str(rest);
+    output
+}
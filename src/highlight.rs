@@ -0,0 +1,90 @@
+use crate::html::escape;
+use comrak::nodes::{AstNode, NodeHtmlBlock, NodeValue};
+use std::sync::OnceLock;
+use syntect::easy::HighlightLines;
+use syntect::highlighting::{Color, ThemeSet};
+use syntect::html::{styled_line_to_highlighted_html, IncludeBackground};
+use syntect::parsing::SyntaxSet;
+use syntect::util::LinesWithEndings;
+
+pub const DEFAULT_THEME: &str = "InspiredGitHub";
+
+// the themes syntect comes with
+pub const THEMES: &[&str] = &[
+    "InspiredGitHub",
+    "Solarized (dark)",
+    "Solarized (light)",
+    "base16-eighties.dark",
+    "base16-mocha.dark",
+    "base16-ocean.dark",
+    "base16-ocean.light",
+];
+
+struct Definitions {
+    syntaxes: SyntaxSet,
+    themes: ThemeSet,
+}
+
+// loading the definitions takes a moment, so it happens once and only for
+// documents that have code to colour
+fn definitions() -> &'static Definitions {
+    static DEFINITIONS: OnceLock<Definitions> = OnceLock::new();
+    DEFINITIONS.get_or_init(|| Definitions {
+        syntaxes: SyntaxSet::load_defaults_newlines(),
+        themes: ThemeSet::load_defaults(),
+    })
+}
+
+fn css_color(color: Color) -> String {
+    format!("#{:02x}{:02x}{:02x}", color.r, color.g, color.b)
+}
+
+// the code block as html with inline colours, none when the language is not
+// one syntect knows
+fn highlighted(language: &str, code: &str, theme: &str) -> Option<String> {
+    let definitions = definitions();
+    let syntax = definitions.syntaxes.find_syntax_by_token(language)?;
+    let theme = definitions.themes.themes.get(theme)?;
+    let mut lines = HighlightLines::new(syntax, theme);
+    let mut html = String::new();
+    for line in LinesWithEndings::from(code) {
+        let regions = lines.highlight_line(line, &definitions.syntaxes).ok()?;
+        html.push_str(&styled_line_to_highlighted_html(&regions, IncludeBackground::No).ok()?);
+    }
+    // on the pre itself, so the page stylesheet's colours for pre do not
+    // show through
+    let background = theme.settings.background.unwrap_or(Color::WHITE);
+    let foreground = theme.settings.foreground.unwrap_or(Color::BLACK);
+    Some(format!(
+        "<pre class=\"yamc-highlight\" style=\"background-color: {}; color: {};\"><code class=\"language-{}\">{}</code></pre>\n",
+        css_color(background),
+        css_color(foreground),
+        escape(language),
+        html
+    ))
+}
+
+// colour every fenced code block whose language is known, in `theme`. other
+// code blocks stay as they are
+pub fn code_blocks<'a>(root: &'a AstNode<'a>, theme: &str) {
+    for node in root.descendants() {
+        let html = match node.data.borrow().value {
+            NodeValue::CodeBlock(ref block) if block.fenced => {
+                let info = String::from_utf8_lossy(&block.info);
+                match info.split_whitespace().next() {
+                    Some(language) => {
+                        highlighted(language, &String::from_utf8_lossy(&block.literal), theme)
+                    }
+                    None => None,
+                }
+            }
+            _ => None,
+        };
+        if let Some(html) = html {
+            node.data.borrow_mut().value = NodeValue::HtmlBlock(NodeHtmlBlock {
+                block_type: 0,
+                literal: html.into_bytes(),
+            });
+        }
+    }
+}
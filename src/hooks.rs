@@ -0,0 +1,111 @@
+use std::io::Read;
+use std::path::Path;
+use std::process::{Command, Stdio};
+use std::thread;
+use std::time::{Duration, Instant};
+
+pub const DEFAULT_TIMEOUT: u64 = 60;
+
+// what a hook is told about the document it runs for
+pub struct Job<'a> {
+    pub input: &'a Path,
+    pub output: &'a Path,
+    pub status: &'a str,
+}
+
+fn shell(command: &str) -> Command {
+    if cfg!(windows) {
+        let mut shell = Command::new("cmd");
+        shell.args(["/C", command]);
+        shell
+    } else {
+        let mut shell = Command::new("sh");
+        shell.args(["-c", command]);
+        shell
+    }
+}
+
+// read a pipe on its own thread so a chatty hook cannot block on a full pipe
+fn capture<R: Read + Send + 'static>(pipe: Option<R>) -> thread::JoinHandle<String> {
+    thread::spawn(move || {
+        let mut output = String::new();
+        if let Some(mut pipe) = pipe {
+            let mut bytes = Vec::new();
+            let _ = pipe.read_to_end(&mut bytes);
+            output = String::from_utf8_lossy(&bytes).to_string();
+        }
+        output
+    })
+}
+
+// run a hook command through the shell and wait for it at most `timeout`.
+// both a success and a failure carry what the hook printed
+pub fn run(command: &str, job: &Job, timeout: Duration) -> Result<String, String> {
+    let mut child = shell(command)
+        .env("YAMC_INPUT", job.input)
+        .env("YAMC_OUTPUT", job.output)
+        .env("YAMC_FORMAT", "html")
+        .env("YAMC_STATUS", job.status)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|err| format!("cannot run {:?}: {}", command, err))?;
+    let stdout = capture(child.stdout.take());
+    let stderr = capture(child.stderr.take());
+    let started = Instant::now();
+    loop {
+        match child.try_wait() {
+            Ok(Some(status)) => {
+                let output = format!(
+                    "{}{}",
+                    stdout.join().unwrap_or_default(),
+                    stderr.join().unwrap_or_default()
+                );
+                let output = output.trim_end();
+                return match (status.success(), output.is_empty()) {
+                    (true, _) => Ok(output.to_string()),
+                    (false, true) => Err(format!("{:?} failed with {}", command, status)),
+                    (false, false) => {
+                        Err(format!("{:?} failed with {}\n{}", command, status, output))
+                    }
+                };
+            }
+            // processes the hook started may still hold its output open, so
+            // the output of a hook that timed out is not waited for
+            Ok(None) if started.elapsed() >= timeout => {
+                let _ = child.kill();
+                let _ = child.wait();
+                return Err(format!(
+                    "{:?} did not finish within {}s",
+                    command,
+                    timeout.as_secs()
+                ));
+            }
+            Ok(None) => thread::sleep(Duration::from_millis(20)),
+            Err(err) => return Err(format!("cannot wait for {:?}: {}", command, err)),
+        }
+    }
+}
+
+// the commands are for sh
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn output_comes_back_either_way() {
+        let job = Job {
+            input: Path::new("page.md"),
+            output: Path::new("page.html"),
+            status: "success",
+        };
+        let timeout = Duration::from_secs(DEFAULT_TIMEOUT);
+        assert_eq!(
+            run("echo $YAMC_OUTPUT; echo warned >&2", &job, timeout),
+            Ok("page.html\nwarned".to_string())
+        );
+        let failed = run("echo broken; exit 3", &job, timeout).unwrap_err();
+        assert!(failed.ends_with("\nbroken"), "{}", failed);
+    }
+}
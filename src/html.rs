@@ -0,0 +1,456 @@
+use crate::degradations::Degradations;
+use comrak::nodes::{AstNode, NodeValue};
+use std::fmt::Write;
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum Flavor {
+    Html5,
+    // void elements self-closed and the document well-formed xml
+    Xhtml,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum Encoding {
+    Utf8,
+    // everything outside ascii written as character references
+    Ascii,
+}
+
+impl Encoding {
+    pub fn charset(self) -> &'static str {
+        match self {
+            Encoding::Utf8 => "utf-8",
+            Encoding::Ascii => "us-ascii",
+        }
+    }
+}
+
+// how code blocks handle lines wider than the page. browsers can scroll,
+// printed pages cut the line off
+#[derive(Clone, Copy, PartialEq)]
+pub enum CodeWrap {
+    Scroll,
+    Wrap,
+    WrapPrintOnly,
+}
+
+// `{class}` is the class of the page body
+const WRAP_STYLE: &str = ".{class} pre, .{class} pre code { white-space: pre-wrap; word-break: break-all; overflow-wrap: anywhere; }
+.{class} pre { border-left: 3px solid var(--yamc-code-border, #d0d7de); }";
+
+// yamc's styles and the usual markdown ones indent and rule lists, quotes
+// and code on the left, right to left text has them on the right
+const RTL_STYLE: &str = ".{class} ul, .{class} ol { padding-left: 0; padding-right: 2em; }
+.{class} blockquote { border-left: 0; border-right: 0.25em solid #d0d7de; }
+[dir=\"rtl\"] nav.toc ul { padding-left: 0; padding-right: 1.2em; }
+[dir=\"rtl\"] nav.toc > ul { padding-right: 0; }";
+
+const RTL_WRAP_STYLE: &str =
+    ".{class} pre { border-left: 0; border-right: 3px solid var(--yamc-code-border, #d0d7de); }";
+
+// the style element mirroring the built-in styles for right to left pages,
+// wrapped code blocks included
+pub fn rtl_style(class: &str, code_wrap: CodeWrap) -> String {
+    let mut css = RTL_STYLE.replace("{class}", class);
+    let wrap = RTL_WRAP_STYLE.replace("{class}", class);
+    match code_wrap {
+        CodeWrap::Scroll => {}
+        CodeWrap::Wrap => css.push_str(&format!("\n{}", wrap)),
+        CodeWrap::WrapPrintOnly => css.push_str(&format!("\n@media print {{\n{}\n}}", wrap)),
+    }
+    style_element(&css)
+}
+
+// the css variables a page can be themed with, as `--yamc-<name>`, with the
+// rules that apply them. yamc's own styles read the ones without rules and
+// fall back to their usual values. `{class}` is the class of the page body
+pub const CSS_VARIABLES: &[(&str, &str)] = &[
+    (
+        "max-width",
+        ".{class} { max-width: var(--yamc-max-width); margin: 0 auto; }",
+    ),
+    (
+        "font-body",
+        ".{class} { font-family: var(--yamc-font-body); }",
+    ),
+    (
+        "font-code",
+        ".{class} code, .{class} pre { font-family: var(--yamc-font-code); }",
+    ),
+    (
+        "code-bg",
+        ".{class} code, .{class} pre { background-color: var(--yamc-code-bg); }",
+    ),
+    ("accent", ".{class} a { color: var(--yamc-accent); }"),
+    ("code-border", ""),
+    ("table-header-bg", ""),
+];
+
+// a `name=value` of --css-var. the value ends up inside a <style> element,
+// so it cannot close it or the declaration
+pub fn parse_css_variable(setting: &str) -> Result<(String, String), String> {
+    let (name, value) = setting
+        .split_once('=')
+        .ok_or_else(|| "expected name=value".to_string())?;
+    let name = name.trim().trim_start_matches("--yamc-").replace('_', "-");
+    if !CSS_VARIABLES.iter().any(|(known, _)| *known == name) {
+        let names: Vec<&str> = CSS_VARIABLES.iter().map(|(name, _)| *name).collect();
+        return Err(format!(
+            "unknown variable {:?}, expected one of {}",
+            name,
+            names.join(", ")
+        ));
+    }
+    let value = value.trim();
+    if value.is_empty() || value.contains(|c| "<>{};".contains(c)) {
+        return Err(format!("{:?} is not a usable value", value));
+    }
+    Ok((name, value.to_string()))
+}
+
+// the style element setting the given variables for every medium, a later
+// setting of a variable replaces an earlier one
+pub fn variables_style(variables: &[(String, String)], class: &str) -> Option<String> {
+    let mut names: Vec<&str> = Vec::new();
+    let mut values: Vec<&str> = Vec::new();
+    for (name, value) in variables {
+        match names.iter().position(|known| known == name) {
+            Some(index) => values[index] = value,
+            None => {
+                names.push(name);
+                values.push(value);
+            }
+        }
+    }
+    if names.is_empty() {
+        return None;
+    }
+    let mut style = String::from("<style>\n:root {");
+    for (name, value) in names.iter().zip(&values) {
+        style.push_str(&format!(" --yamc-{}: {};", name, value));
+    }
+    style.push_str(" }\n");
+    for (name, rule) in CSS_VARIABLES {
+        if names.contains(name) && !rule.is_empty() {
+            style.push_str(&rule.replace("{class}", class));
+            style.push('\n');
+        }
+    }
+    style.push_str("</style>");
+    Some(style)
+}
+
+// a <style> element holding `css`, which cannot end it early
+pub fn style_element(css: &str) -> String {
+    format!(
+        "<style>\n{}\n</style>",
+        css.trim_end().replace("</", "<\\/")
+    )
+}
+
+impl CodeWrap {
+    pub fn parse(name: &str) -> Option<CodeWrap> {
+        match name {
+            "scroll" => Some(CodeWrap::Scroll),
+            "wrap" => Some(CodeWrap::Wrap),
+            "wrap-pdf-only" => Some(CodeWrap::WrapPrintOnly),
+            _ => None,
+        }
+    }
+
+    // the style element that makes code blocks wrap, if they should
+    pub fn style(self, class: &str) -> Option<String> {
+        let rules = WRAP_STYLE.replace("{class}", class);
+        match self {
+            CodeWrap::Scroll => None,
+            CodeWrap::Wrap => Some(format!("<style>\n{}\n</style>", rules)),
+            CodeWrap::WrapPrintOnly => {
+                Some(format!("<style>\n@media print {{\n{}\n}}\n</style>", rules))
+            }
+        }
+    }
+}
+
+// escape text for use in html element content and quoted attribute values
+pub fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+// what comrak writes in place of raw html it is not allowed to render
+pub const OMITTED: &str = "<!-- raw HTML omitted -->";
+
+// replace the raw html written by the author so that only html generated by
+// yamc's own passes is rendered. html blocks built by yamc use block_type 0,
+// which comrak never produces; inline html has no such marker, so passes that
+// generate inline html must run after this one. markup other than comments is
+// recorded as degraded
+pub fn omit_raw<'a>(root: &'a AstNode<'a>, degradations: &mut Degradations) {
+    for node in root.descendants() {
+        let line = node
+            .ancestors()
+            .map(|ancestor| ancestor.data.borrow().start_line)
+            .find(|line| *line > 0)
+            .unwrap_or(0);
+        let mut data = node.data.borrow_mut();
+        let literal = match data.value {
+            NodeValue::HtmlBlock(ref mut block) if block.block_type != 0 => &mut block.literal,
+            NodeValue::HtmlInline(ref mut literal) => literal,
+            _ => continue,
+        };
+        let raw = String::from_utf8_lossy(literal);
+        let raw = raw.trim();
+        if !(raw.starts_with("<!--") && raw.ends_with("-->")) {
+            degradations.record("raw-html", line);
+        }
+        *literal = OMITTED.as_bytes().to_vec();
+    }
+}
+
+// replace non-ascii characters with numeric character references. those are
+// not understood inside <style> and <script>, which get css and javascript
+// escapes instead
+pub fn to_ascii(html: &str) -> String {
+    let mut ascii = String::with_capacity(html.len());
+    // the element the text is in, by its end tag
+    let mut inside: Option<&str> = None;
+    let mut rest = html;
+    loop {
+        let next = match inside {
+            Some(close) => rest.find(close).map(|end| (end, close)),
+            None => ["<style", "<script"]
+                .into_iter()
+                .filter_map(|open| rest.find(open).map(|start| (start, open)))
+                .min(),
+        };
+        let end = next.map(|(end, _)| end).unwrap_or(rest.len());
+        for c in rest[..end].chars() {
+            if c.is_ascii() {
+                ascii.push(c);
+                continue;
+            }
+            match inside {
+                Some("</style>") => write!(ascii, "\\{:06X}", c as u32),
+                Some(_) => c
+                    .encode_utf16(&mut [0; 2])
+                    .iter()
+                    .try_for_each(|unit| write!(ascii, "\\u{:04X}", unit)),
+                None => write!(ascii, "&#x{:X};", c as u32),
+            }
+            .expect("Writing to a string cannot fail");
+        }
+        match next {
+            Some((end, marker)) => {
+                ascii.push_str(marker);
+                rest = &rest[end + marker.len()..];
+                inside = match marker {
+                    "<style" => Some("</style>"),
+                    "<script" => Some("</script>"),
+                    _ => None,
+                };
+            }
+            None => return ascii,
+        }
+    }
+}
+
+// xhtml consumers parse the document as xml, so refuse to write one they
+// would reject
+pub fn check_well_formed(document: &str) -> Result<(), String> {
+    roxmltree::Document::parse(document)
+        .map(|_| ())
+        .map_err(|err| err.to_string())
+}
+
+// the part of a hand-written page between <body> and </body>. pages without a
+// body tag lose their doctype, <html> and <head> instead
+pub fn body(document: &str) -> &str {
+    let lower = document.to_ascii_lowercase();
+    let start = match lower.find("<body") {
+        Some(start) => match lower[start..].find('>') {
+            Some(end) => start + end + 1,
+            None => return "",
+        },
+        None => match lower.find("</head>") {
+            Some(end) => end + "</head>".len(),
+            None => skip_preamble(&lower),
+        },
+    };
+    let end = lower[start..]
+        .find("</body>")
+        .or_else(|| lower[start..].find("</html>"))
+        .map_or(document.len(), |end| start + end);
+    &document[start..end]
+}
+
+// leading doctype, <html> tag and comments of a page without head or body
+fn skip_preamble(lower: &str) -> usize {
+    let mut position = 0;
+    loop {
+        let rest = lower[position..].trim_start();
+        let offset = lower.len() - rest.len();
+        let close = if rest.starts_with("<!--") {
+            "-->"
+        } else if rest.starts_with("<!doctype") || rest.starts_with("<html") {
+            ">"
+        } else {
+            return offset;
+        };
+        match rest.find(close) {
+            Some(end) => position = offset + end + close.len(),
+            None => return offset,
+        }
+    }
+}
+
+// the <style>, <script> and stylesheet <link> elements of a page's head
+pub fn head_assets(document: &str) -> String {
+    let lower = document.to_ascii_lowercase();
+    let head = match (lower.find("<head"), lower.find("</head>")) {
+        (Some(start), Some(end)) if start < end => start..end,
+        _ => return String::new(),
+    };
+    let mut assets = String::new();
+    let mut position = head.start;
+    while let Some(found) = lower[position..head.end].find('<') {
+        let start = position + found;
+        let tag = &lower[start..head.end];
+        let end = if tag.starts_with("<style") {
+            tag.find("</style>").map(|end| end + "</style>".len())
+        } else if tag.starts_with("<script") {
+            tag.find("</script>").map(|end| end + "</script>".len())
+        } else if tag.starts_with("<link")
+            && tag[..tag.find('>').unwrap_or(0)].contains("stylesheet")
+        {
+            tag.find('>').map(|end| end + 1)
+        } else {
+            None
+        };
+        match end {
+            Some(end) => {
+                assets.push_str(&document[start..start + end]);
+                assets.push('\n');
+                position = start + end;
+            }
+            None => position = start + 1,
+        }
+    }
+    assets
+}
+
+// drop every comment, including the placeholders left for omitted raw html
+pub fn strip_comments(html: &str) -> String {
+    let mut stripped = String::with_capacity(html.len());
+    let mut rest = html;
+    while let Some(start) = rest.find("<!--") {
+        stripped.push_str(&rest[..start]);
+        rest = match rest[start..].find("-->") {
+            Some(end) => &rest[start + end + "-->".len()..],
+            None => "",
+        };
+    }
+    stripped.push_str(rest);
+    stripped
+}
+
+// styles for pasting into editors that drop stylesheets but keep style
+// attributes
+const INLINE_STYLES: &[(&str, &str)] = &[
+    ("pre", "background: #f6f8fa; padding: 16px; overflow: auto; border-radius: 6px;"),
+    ("code", "font-family: SFMono-Regular, Consolas, monospace; background: #f6f8fa; padding: 0.2em 0.4em; border-radius: 6px;"),
+    ("blockquote", "margin: 0; padding: 0 1em; color: #57606a; border-left: 0.25em solid #d0d7de;"),
+    ("table", "border-collapse: collapse;"),
+    ("th", "border: 1px solid #d0d7de; padding: 6px 13px; font-weight: 600;"),
+    ("td", "border: 1px solid #d0d7de; padding: 6px 13px;"),
+    ("img", "max-width: 100%;"),
+];
+
+// a fragment that keeps its look without the page stylesheet
+pub fn inline_styles(html: &str) -> String {
+    let mut styled = html.to_string();
+    for (tag, style) in INLINE_STYLES {
+        for end in [" ", ">"] {
+            styled = styled.replace(
+                &format!("<{}{}", tag, end),
+                &format!("<{} style=\"{}\"{}", tag, style, end),
+            );
+        }
+    }
+    format!(
+        "<div style=\"font-family: -apple-system, 'Segoe UI', Helvetica, Arial, sans-serif; line-height: 1.5;\">{}</div>",
+        styled
+    )
+}
+
+// the text of rendered html, for places that cannot take markup
+pub fn to_text(html: &str) -> String {
+    // blocks are separated by a blank line
+    let mut html = html.to_string();
+    for block in [
+        "p",
+        "h1",
+        "h2",
+        "h3",
+        "h4",
+        "h5",
+        "h6",
+        "pre",
+        "blockquote",
+        "ul",
+        "ol",
+        "table",
+    ] {
+        let closing = format!("</{}>", block);
+        html = html.replace(&closing, &format!("{}\n", closing));
+    }
+    let mut text = String::with_capacity(html.len());
+    let mut rest = html.as_str();
+    while let Some(start) = rest.find('<') {
+        text.push_str(&rest[..start]);
+        rest = match rest[start..].find('>') {
+            Some(end) => &rest[start + end + 1..],
+            None => "",
+        };
+    }
+    text.push_str(rest);
+    let text = text
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&#39;", "'")
+        .replace("&amp;", "&");
+    let mut collapsed = String::with_capacity(text.len());
+    for line in text.lines().map(str::trim_end) {
+        if !(line.is_empty() && (collapsed.is_empty() || collapsed.ends_with("\n\n"))) {
+            collapsed.push_str(line);
+            collapsed.push('\n');
+        }
+    }
+    collapsed
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ascii_output_escapes_each_element_its_own_way() {
+        let html = "<p>café</p><style>p::before { content: \"é\" }</style>\
+                    <script>alert(\"é😀\")</script><p>😀</p>";
+        assert_eq!(
+            to_ascii(html),
+            "<p>caf&#xE9;</p><style>p::before { content: \"\\0000E9\" }</style>\
+             <script>alert(\"\\u00E9\\uD83D\\uDE00\")</script><p>&#x1F600;</p>"
+        );
+    }
+}
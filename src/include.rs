@@ -0,0 +1,328 @@
+use crate::diagnostic::Diagnostic;
+use crate::dialect::{self, Syntax};
+use crate::explain;
+use crate::headings;
+use crate::paths;
+use crate::private;
+use comrak::nodes::{AstNode, NodeValue};
+use comrak::{Arena, ComrakOptions};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+// an include directive is an html block holding a single comment such as
+// `<!-- include: ../api.md -->` or `<!-- include: ../api.md#authentication offset=1 -->`
+struct Directive {
+    file: String,
+    section: Option<String>,
+    offset: Option<i32>,
+}
+
+fn parse_directive(literal: &[u8]) -> Option<Directive> {
+    let literal = String::from_utf8_lossy(literal);
+    let comment = literal.trim().strip_prefix("<!--")?.strip_suffix("-->")?;
+    let mut words = comment.trim().strip_prefix("include:")?.split_whitespace();
+    let target = words.next()?;
+    let mut offset = None;
+    for word in words {
+        offset = Some(word.strip_prefix("offset=")?.parse().ok()?);
+    }
+    let (file, section) = match target.split_once('#') {
+        Some((file, section)) => (file.to_string(), Some(section.to_string())),
+        None => (target.to_string(), None),
+    };
+    Some(Directive {
+        file,
+        section,
+        offset,
+    })
+}
+
+fn heading_level(node: &AstNode) -> Option<u32> {
+    match node.data.borrow().value {
+        NodeValue::Heading(ref heading) => Some(heading.level),
+        _ => None,
+    }
+}
+
+pub fn heading_text<'a>(node: &'a AstNode<'a>) -> String {
+    let mut text = String::new();
+    for descendant in node.descendants() {
+        match descendant.data.borrow().value {
+            NodeValue::Text(ref literal) => text.push_str(&String::from_utf8_lossy(literal)),
+            NodeValue::Code(ref code) => text.push_str(&String::from_utf8_lossy(&code.literal)),
+            _ => {}
+        }
+    }
+    // an attribute block is not part of the heading's name
+    match headings::split_attributes(&text) {
+        Some((name, _)) => name.to_string(),
+        None => text,
+    }
+}
+
+// the level of the closest heading before the directive, walking back through
+// previous siblings and then up through the parents
+fn level_at<'a>(node: &'a AstNode<'a>) -> Option<u32> {
+    let mut current = node;
+    loop {
+        current = match current.previous_sibling() {
+            Some(previous) => {
+                if let Some(level) = heading_level(previous) {
+                    return Some(level);
+                }
+                previous
+            }
+            None => current.parent()?,
+        };
+    }
+}
+
+// the top level nodes of the included document, or only the section under
+// the heading whose slug matches, as comrak makes it or as the page has it
+// with `prefix`
+fn select<'a>(
+    root: &'a AstNode<'a>,
+    section: Option<&str>,
+    prefix: &str,
+) -> Option<Vec<&'a AstNode<'a>>> {
+    let nodes = root
+        .children()
+        .filter(|node| !matches!(node.data.borrow().value, NodeValue::FrontMatter(..)));
+    let slug = match section {
+        Some(slug) => slug,
+        None => return Some(nodes.collect()),
+    };
+
+    let mut anchorizer = comrak::Anchorizer::new();
+    let mut selected = Vec::new();
+    let mut level = None;
+    let mut position = 0;
+    for node in nodes {
+        if let Some(node_level) = heading_level(node) {
+            position += 1;
+            let id = anchorizer.anchorize(heading_text(node));
+            let matches = id == slug || headings::usable_id(&id, position, prefix) == slug;
+            match level {
+                Some(level) if node_level <= level => break,
+                None if matches => level = Some(node_level),
+                _ => {}
+            }
+        }
+        if level.is_some() {
+            selected.push(node);
+        }
+    }
+    level.map(|_| selected)
+}
+
+// whether `url` starts with a scheme such as `https:`, `data:` or `tel:`,
+// a letter followed by letters, digits, `+`, `-` or `.` up to the colon
+fn has_scheme(url: &str) -> bool {
+    match url.split_once(':') {
+        Some((scheme, _)) => {
+            scheme.starts_with(|c: char| c.is_ascii_alphabetic())
+                && scheme
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || matches!(c, '+' | '-' | '.'))
+        }
+        None => false,
+    }
+}
+
+// links inside the fragment are relative to the included file, so prefix them
+// with the directory of the directive's path
+fn rebase<'a>(node: &'a AstNode<'a>, base: &Path) {
+    let base = paths::to_url(base);
+    if base.is_empty() {
+        return;
+    }
+    for descendant in node.descendants() {
+        let mut data = descendant.data.borrow_mut();
+        if let NodeValue::Link(ref mut link) | NodeValue::Image(ref mut link) = data.value {
+            let url = String::from_utf8_lossy(&link.url).to_string();
+            let external =
+                has_scheme(&url) || url.starts_with('#') || url.starts_with('/') || url.is_empty();
+            if !external {
+                link.url = format!("{}/{}", base, url).into_bytes();
+            }
+        }
+    }
+}
+
+fn shift<'a>(nodes: &[&'a AstNode<'a>], offset: i32) {
+    for node in nodes {
+        for descendant in node.descendants() {
+            if let NodeValue::Heading(ref mut heading) = descendant.data.borrow_mut().value {
+                heading.level = (heading.level as i32 + offset).clamp(1, 6) as u32;
+            }
+        }
+    }
+}
+
+// where include directives may reach and how included sections are named
+pub struct Bounds<'b> {
+    // the directory every included file has to be inside
+    pub root: &'b Path,
+    // the --heading-id-prefix the page's ids are made with
+    pub prefix: &'b str,
+}
+
+// replace every include directive below `root` with the referenced document or
+// section, recursively. `stack` holds the files currently being expanded so
+// cycles are reported instead of recursing forever
+fn expand_in<'a>(
+    arena: &'a Arena<AstNode<'a>>,
+    root: &'a AstNode<'a>,
+    path: &Path,
+    syntax: &Syntax,
+    options: &ComrakOptions,
+    bounds: &Bounds,
+    stack: &mut Vec<PathBuf>,
+) -> Result<(), Diagnostic> {
+    let inside = fs::canonicalize(bounds.root).unwrap_or_else(|_| bounds.root.to_path_buf());
+    let directives: Vec<_> = root
+        .descendants()
+        .filter_map(|node| match node.data.borrow().value {
+            NodeValue::HtmlBlock(ref block) => parse_directive(&block.literal).map(|d| (node, d)),
+            _ => None,
+        })
+        .collect();
+
+    for (node, directive) in directives {
+        let line = explain::line(node);
+        let fail = |reason: String| {
+            Diagnostic::error(
+                "include",
+                path,
+                line,
+                format!("cannot include {:?}: {}", directive.file, reason),
+            )
+            .pointing_at(&directive.file)
+        };
+
+        let relative = Path::new(&directive.file);
+        let target = path.parent().unwrap_or(Path::new("")).join(relative);
+        let canonical = fs::canonicalize(&target).map_err(|err| fail(err.to_string()))?;
+        if !canonical.starts_with(&inside) {
+            return Err(fail(format!(
+                "it is outside {}",
+                paths::display(bounds.root)
+            )));
+        }
+        if stack.contains(&canonical) {
+            let chain: Vec<String> = stack
+                .iter()
+                .chain(Some(&canonical))
+                .map(|file| paths::display(file))
+                .collect();
+            return Err(fail(format!("include cycle {}", chain.join(" -> "))));
+        }
+        let markdown = fs::read_to_string(&target).map_err(|err| fail(err.to_string()))?;
+
+        let markdown = private::strip(markdown, &target)?;
+        let markdown = dialect::preprocess(markdown, syntax);
+        let included = comrak::parse_document(arena, &markdown, options);
+        stack.push(canonical);
+        expand_in(arena, included, &target, syntax, options, bounds, stack)?;
+        stack.pop();
+
+        let nodes =
+            select(included, directive.section.as_deref(), bounds.prefix).ok_or_else(|| {
+                fail(format!(
+                    "no section #{}",
+                    directive.section.as_ref().unwrap()
+                ))
+            })?;
+        let top = nodes.iter().filter_map(|node| heading_level(node)).min();
+        let offset = match (directive.offset, top, level_at(node)) {
+            (Some(offset), _, _) => offset,
+            (None, Some(top), Some(level)) => level as i32 + 1 - top as i32,
+            _ => 0,
+        };
+        shift(&nodes, offset);
+        for included_node in nodes {
+            rebase(included_node, relative.parent().unwrap_or(Path::new("")));
+            node.insert_before(included_node);
+        }
+        node.detach();
+    }
+    Ok(())
+}
+
+pub fn expand<'a>(
+    arena: &'a Arena<AstNode<'a>>,
+    root: &'a AstNode<'a>,
+    path: &Path,
+    syntax: &Syntax,
+    bounds: &Bounds,
+) -> Result<(), Diagnostic> {
+    let options = dialect::create_comrak_options(syntax);
+    let mut stack = Vec::new();
+    if let Ok(canonical) = fs::canonicalize(path) {
+        stack.push(canonical);
+    }
+    expand_in(arena, root, path, syntax, &options, bounds, &mut stack)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // the html of `markdown` in `directory`/page.md with its includes
+    // expanded inside `directory`
+    fn expanded(directory: &Path, markdown: &str) -> Result<String, Diagnostic> {
+        let syntax = Syntax::preset(dialect::Dialect::Gfm);
+        let options = dialect::create_comrak_options(&syntax);
+        let arena = Arena::new();
+        let root = comrak::parse_document(&arena, markdown, &options);
+        let bounds = Bounds {
+            root: directory,
+            prefix: "section-",
+        };
+        expand(&arena, root, &directory.join("page.md"), &syntax, &bounds)?;
+        let mut html = Vec::new();
+        comrak::format_html(root, &options, &mut html).unwrap();
+        Ok(String::from_utf8(html).unwrap())
+    }
+
+    #[test]
+    fn includes_stay_inside_the_root() {
+        let directory = std::env::temp_dir().join(format!("yamc-include-{}", std::process::id()));
+        let docs = directory.join("docs");
+        fs::create_dir_all(&docs).unwrap();
+        fs::write(directory.join("secret.md"), "secret\n").unwrap();
+        fs::write(docs.join("part.md"), "# 2024\n\nplans\n\n# Other\n\nrest\n").unwrap();
+        let outside = match expanded(&docs, "<!-- include: ../secret.md -->\n") {
+            Ok(html) => panic!("included from outside: {}", html),
+            Err(err) => err.message,
+        };
+        assert!(outside.contains("outside"), "{}", outside);
+        let absolute = format!(
+            "<!-- include: {} -->\n",
+            directory.join("secret.md").display()
+        );
+        assert!(expanded(&docs, &absolute).is_err());
+        // the section as the page names it, with --heading-id-prefix
+        let html = match expanded(&docs, "<!-- include: part.md#section-2024 -->\n") {
+            Ok(html) => html,
+            Err(err) => panic!("{}", err.message),
+        };
+        assert!(html.contains("plans") && !html.contains("rest"), "{}", html);
+    }
+
+    #[test]
+    fn urls_with_a_scheme_are_external() {
+        for url in [
+            "https://example.com",
+            "mailto:a@example.com",
+            "data:image/png;base64,AAAA",
+            "tel:+123",
+            "git+ssh:host",
+        ] {
+            assert!(has_scheme(url), "{}", url);
+        }
+        for url in ["notes.md", "images/a:b.png", "./x:y", "1tel:2", ":x", ""] {
+            assert!(!has_scheme(url), "{}", url);
+        }
+    }
+}
@@ -0,0 +1,108 @@
+use crate::{paths, stamp};
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+pub const FILE: &str = ".yamc-cache.json";
+
+// what the pages of earlier --incremental runs were made from, by page. a
+// page whose name is not valid unicode is not kept, it is converted again
+pub struct Cache {
+    path: PathBuf,
+    pages: BTreeMap<String, String>,
+}
+
+impl Cache {
+    // the cache in `directory`. a missing or unreadable one is empty, and
+    // the run converts everything
+    pub fn load(directory: &Path) -> Cache {
+        let path = directory.join(FILE);
+        let pages = fs::read_to_string(&path)
+            .ok()
+            .and_then(|text| serde_json::from_str::<serde_json::Value>(&text).ok())
+            .and_then(|cache| {
+                let pages = cache.get("pages")?.as_object()?;
+                Some(
+                    pages
+                        .iter()
+                        .filter_map(|(page, key)| Some((page.clone(), key.as_str()?.to_string())))
+                        .collect(),
+                )
+            })
+            .unwrap_or_default();
+        Cache { path, pages }
+    }
+
+    pub fn pages(&self) -> impl Iterator<Item = &Path> {
+        self.pages.keys().map(Path::new)
+    }
+
+    // whether `page` is there and was made from what `key` stands for
+    pub fn fresh(&self, page: &Path, key: &str) -> bool {
+        page.to_str()
+            .and_then(|page| self.pages.get(page))
+            .is_some_and(|recorded| recorded == key)
+            && page.is_file()
+    }
+
+    pub fn record(&mut self, page: &Path, key: String) {
+        if let Some(page) = page.to_str() {
+            self.pages.insert(page.to_string(), key);
+        }
+    }
+
+    pub fn save(&self) -> Result<(), String> {
+        let cache = serde_json::json!({ "version": 1, "pages": self.pages });
+        let text = serde_json::to_string_pretty(&cache).expect("the cache is valid json");
+        fs::write(&self.path, text + "\n")
+            .map_err(|err| format!("cannot write {}: {}", paths::display(&self.path), err))
+    }
+}
+
+// options that change how a run goes or what it says, not its pages
+const IGNORED: &[&str] = &[
+    "-f",
+    "--force",
+    "--incremental",
+    "-q",
+    "--quiet",
+    "-v",
+    "--verbose",
+    "--json",
+    "--watch",
+    "--dry-run",
+    "--open",
+];
+
+// the options of a run as they count for its pages
+pub fn fingerprint(arguments: &[String]) -> String {
+    let mut kept = Vec::new();
+    let mut arguments = arguments.iter();
+    while let Some(argument) = arguments.next() {
+        if argument == "-j" || argument == "--jobs" {
+            arguments.next();
+        } else if !IGNORED.contains(&argument.as_str())
+            && !argument.starts_with("--jobs=")
+            && !argument.strip_prefix("-j").is_some_and(|jobs| {
+                !jobs.is_empty() && jobs.bytes().all(|byte| byte.is_ascii_digit())
+            })
+        {
+            kept.push(argument.as_str());
+        }
+    }
+    kept.join("\0")
+}
+
+// what a page is made from: the input, the options it is converted with and
+// the version of yamc. none when the input cannot be read
+pub fn key(input: &Path, options: &str) -> Option<String> {
+    let text = fs::read(input).ok()?;
+    let mut hasher = Sha256::new();
+    hasher.update(env!("CARGO_PKG_VERSION").as_bytes());
+    hasher.update([0]);
+    hasher.update(options.as_bytes());
+    hasher.update([0]);
+    hasher.update(&text);
+    Some(stamp::hex(&hasher.finalize()))
+}
@@ -0,0 +1,157 @@
+use crate::paths;
+use std::env;
+use std::fs;
+use std::io;
+use std::path::{Component, Path, PathBuf};
+
+// the document `init --sample` writes
+pub const SAMPLE: &str = "example.md";
+
+pub const SAMPLE_TEXT: &str = "# Example
+
+This page was written by `yamc init --sample`. Convert it with
+
+```sh
+yamc convert example.md
+```
+
+and open `output.html`, or keep it open while editing with `yamc serve example.md`.
+
+## What yamc reads
+
+- **Emphasis**, `code` and [links](https://commonmark.org)
+- Tables, task lists and footnotes[^1]
+
+| Option | Set in |
+| --- | --- |
+| `css_url` | yamc.toml |
+| `--output` | the command line |
+
+- [x] write a document
+- [ ] change the stylesheet in yamc.toml
+
+[^1]: Footnotes are collected at the end of the page.
+";
+
+// the options yamc.toml starts with, set to their defaults so that changing
+// one means editing a value, and options without a default, left commented
+// out with an example value
+const SET: &[&str] = &["css-url", "css-class", "from", "code-wrap"];
+const SUGGESTED: &[(&str, &str)] = &[("html-flavor", "xhtml"), ("output-encoding", "ascii")];
+
+fn key(long: &str) -> String {
+    long.replace('-', "_")
+}
+
+fn quoted(value: &str) -> String {
+    toml::Value::String(value.to_string()).to_string()
+}
+
+// the text of a yamc.toml for `command`, setting output_dir when given
+pub fn config_text(command: &clap::Command, output_dir: Option<&str>) -> String {
+    let mut text = String::from(
+        "# options for yamc, read when it runs in this directory or one below it,\n\
+         # up to the root of the repository. keys are the long options of\n\
+         # `yamc convert --help` with _ for -, and the command line wins over them\n",
+    );
+    let argument = |long: &str| {
+        command
+            .get_arguments()
+            .find(|argument| argument.get_long() == Some(long))
+            .expect("the option is one of convert's")
+    };
+    for long in SET {
+        let argument = argument(long);
+        let value = argument
+            .get_default_values()
+            .first()
+            .and_then(|value| value.to_str())
+            .expect("the option has a default");
+        text.push_str(&format!(
+            "\n# {}\n{} = {}\n",
+            argument
+                .get_help()
+                .map(|help| help.to_string())
+                .unwrap_or_default(),
+            key(long),
+            quoted(value)
+        ));
+    }
+    for (long, example) in SUGGESTED {
+        text.push_str(&format!(
+            "\n# {}\n# {} = {}\n",
+            argument(long)
+                .get_help()
+                .map(|help| help.to_string())
+                .unwrap_or_default(),
+            key(long),
+            quoted(example)
+        ));
+    }
+    if let Some(directory) = output_dir {
+        text.push_str(&format!(
+            "\n# {}\noutput_dir = {}\n",
+            argument("output-dir")
+                .get_help()
+                .map(|help| help.to_string())
+                .unwrap_or_default(),
+            quoted(directory)
+        ));
+    }
+    text
+}
+
+// the top directory of the git repository the current directory is in
+pub fn repository() -> Option<PathBuf> {
+    let current = env::current_dir().ok()?;
+    current
+        .ancestors()
+        .find(|directory| directory.join(".git").exists())
+        .map(Path::to_path_buf)
+}
+
+// the .gitignore line for `directory`, relative to the current directory,
+// anchored at the top of `repository`
+pub fn ignore_line(repository: &Path, directory: &str) -> Result<String, String> {
+    let current = env::current_dir().map_err(|err| err.to_string())?;
+    let path = current.join(directory);
+    let mut parts = Vec::new();
+    for component in path
+        .strip_prefix(repository)
+        .map_err(|_| format!("{} is outside the repository", directory))?
+        .components()
+    {
+        match component {
+            Component::Normal(part) => parts.push(part.to_string_lossy().into_owned()),
+            Component::ParentDir if parts.pop().is_some() => {}
+            Component::CurDir => {}
+            _ => return Err(format!("{} is outside the repository", directory)),
+        }
+    }
+    if parts.is_empty() {
+        return Err(format!("{} is the whole repository", directory));
+    }
+    Ok(format!("/{}/", parts.join("/")))
+}
+
+// add `line` to the .gitignore at the top of `repository`, false when it
+// already has it
+pub fn ignore(repository: &Path, line: &str) -> Result<bool, String> {
+    let path = repository.join(".gitignore");
+    let mut text = match fs::read_to_string(&path) {
+        Ok(text) => text,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => String::new(),
+        Err(err) => return Err(format!("cannot read {}: {}", paths::display(&path), err)),
+    };
+    if text.lines().any(|existing| existing.trim() == line) {
+        return Ok(false);
+    }
+    if !text.is_empty() && !text.ends_with('\n') {
+        text.push('\n');
+    }
+    text.push_str(line);
+    text.push('\n');
+    fs::write(&path, text)
+        .map_err(|err| format!("cannot write {}: {}", paths::display(&path), err))?;
+    Ok(true)
+}
@@ -0,0 +1,342 @@
+use comrak::nodes::{AstNode, NodeLink, NodeValue};
+use comrak::Arena;
+
+fn text_nodes<'a>(root: &'a AstNode<'a>) -> Vec<&'a AstNode<'a>> {
+    root.descendants()
+        .filter(|node| matches!(node.data.borrow().value, NodeValue::Text(..)))
+        .collect()
+}
+
+fn text_of(node: &AstNode) -> String {
+    match node.data.borrow().value {
+        NodeValue::Text(ref literal) => String::from_utf8_lossy(literal).to_string(),
+        _ => String::new(),
+    }
+}
+
+fn new_node<'a>(arena: &'a Arena<AstNode<'a>>, value: NodeValue) -> &'a AstNode<'a> {
+    arena.alloc(AstNode::from(value))
+}
+
+fn text<'a>(arena: &'a Arena<AstNode<'a>>, text: &str) -> &'a AstNode<'a> {
+    new_node(arena, NodeValue::Text(text.as_bytes().to_vec()))
+}
+
+// replace a text node by the given nodes, dropping empty text pieces
+fn replace<'a>(node: &'a AstNode<'a>, pieces: Vec<&'a AstNode<'a>>) {
+    for piece in pieces {
+        if matches!(piece.data.borrow().value, NodeValue::Text(ref t) if t.is_empty()) {
+            continue;
+        }
+        node.insert_before(piece);
+    }
+    node.detach();
+}
+
+// the url a wikilink target points to, notes are converted to .html next to
+// each other
+fn wikilink_url(target: &str) -> String {
+    let (page, heading) = match target.split_once('#') {
+        Some((page, heading)) => (page.trim(), Some(heading.trim())),
+        None => (target.trim(), None),
+    };
+    let mut url = String::new();
+    if !page.is_empty() {
+        url.push_str(&page.replace(' ', "%20"));
+        url.push_str(".html");
+    }
+    if let Some(heading) = heading {
+        url.push('#');
+        url.push_str(&comrak::Anchorizer::new().anchorize(heading.to_string()));
+    }
+    url
+}
+
+fn in_link<'a>(node: &'a AstNode<'a>) -> bool {
+    node.ancestors().any(|ancestor| {
+        matches!(
+            ancestor.data.borrow().value,
+            NodeValue::Link(..) | NodeValue::Image(..)
+        )
+    })
+}
+
+// `[[Page]]`, `[[Page|label]]` and `[[Page#Heading]]` become links
+pub fn wikilinks<'a>(arena: &'a Arena<AstNode<'a>>, root: &'a AstNode<'a>) {
+    for node in text_nodes(root) {
+        let content = text_of(node);
+        if !content.contains("[[") || in_link(node) {
+            continue;
+        }
+        let mut pieces = Vec::new();
+        let mut rest = content.as_str();
+        while let Some(start) = rest.find("[[") {
+            let end = match rest[start..].find("]]") {
+                Some(end) => start + end,
+                None => break,
+            };
+            let inner = &rest[start + 2..end];
+            if inner.is_empty() || inner.contains('[') {
+                pieces.push(text(arena, &rest[..start + 2]));
+                rest = &rest[start + 2..];
+                continue;
+            }
+            let (target, label) = match inner.split_once('|') {
+                Some((target, label)) => (target, label.trim()),
+                None => (inner, inner.trim()),
+            };
+            pieces.push(text(arena, &rest[..start]));
+            let link = new_node(
+                arena,
+                NodeValue::Link(NodeLink {
+                    url: wikilink_url(target).into_bytes(),
+                    title: vec![],
+                }),
+            );
+            link.append(text(arena, label));
+            pieces.push(link);
+            rest = &rest[end + 2..];
+        }
+        pieces.push(text(arena, rest));
+        replace(node, pieces);
+    }
+}
+
+// wrap the inline content between pairs of `delimiter` in the given html tags.
+// pairs are matched in order within the same parent, so emphasis and links
+// inside the span work, and an unpaired delimiter stays literal text
+pub fn wrap_delimited<'a>(
+    arena: &'a Arena<AstNode<'a>>,
+    root: &'a AstNode<'a>,
+    delimiter: &str,
+    open: &str,
+    close: &str,
+) {
+    let mut delimiters: Vec<&'a AstNode<'a>> = Vec::new();
+    for node in text_nodes(root) {
+        let content = text_of(node);
+        if !content.contains(delimiter) {
+            continue;
+        }
+        let mut pieces = Vec::new();
+        for (i, part) in content.split(delimiter).enumerate() {
+            if i > 0 {
+                let marker = text(arena, delimiter);
+                delimiters.push(marker);
+                pieces.push(marker);
+            }
+            pieces.push(text(arena, part));
+        }
+        replace(node, pieces);
+    }
+
+    let mut pending: Option<&'a AstNode<'a>> = None;
+    for marker in delimiters {
+        match pending {
+            Some(opener) if same_parent(opener, marker) && flanking(marker, false) => {
+                opener.data.borrow_mut().value = NodeValue::HtmlInline(open.as_bytes().to_vec());
+                marker.data.borrow_mut().value = NodeValue::HtmlInline(close.as_bytes().to_vec());
+                pending = None;
+            }
+            _ if flanking(marker, true) => pending = Some(marker),
+            _ => pending = None,
+        }
+    }
+}
+
+fn same_parent<'a>(a: &'a AstNode<'a>, b: &'a AstNode<'a>) -> bool {
+    match (a.parent(), b.parent()) {
+        (Some(a), Some(b)) => a.same_node(b),
+        _ => false,
+    }
+}
+
+// an opening delimiter must be followed and a closing one preceded by
+// something other than whitespace, so `a == b` is left alone
+fn flanking<'a>(marker: &'a AstNode<'a>, opening: bool) -> bool {
+    let neighbour = if opening {
+        marker.next_sibling()
+    } else {
+        marker.previous_sibling()
+    };
+    let neighbour = match neighbour {
+        Some(neighbour) => neighbour,
+        None => return false,
+    };
+    let content = match neighbour.data.borrow().value {
+        NodeValue::Text(ref literal) => String::from_utf8_lossy(literal).to_string(),
+        NodeValue::SoftBreak | NodeValue::LineBreak => return false,
+        _ => return true,
+    };
+    let c = if opening {
+        content.chars().next()
+    } else {
+        content.chars().last()
+    };
+    c.is_some_and(|c| !c.is_whitespace())
+}
+
+pub fn highlight<'a>(arena: &'a Arena<AstNode<'a>>, root: &'a AstNode<'a>) {
+    wrap_delimited(arena, root, "==", "<mark>", "</mark>");
+}
+
+// stands in for the tildes of a subscript from `mark_subscripts` until
+// `subscript` turns them into tags, comrak treats it as plain text
+pub const SUBSCRIPT: char = '\u{E000}';
+
+fn escaped(chars: &[char], index: usize) -> bool {
+    chars[..index]
+        .iter()
+        .rev()
+        .take_while(|c| **c == '\\')
+        .count()
+        % 2
+        == 1
+}
+
+// comrak reads `~text~` as strikethrough, so subscripts are marked in the
+// source: a single unescaped tilde, then text without whitespace, then
+// another single unescaped tilde. `~~` is always strikethrough and `a ~ b`
+// stays as it is
+pub fn mark_subscripts(markdown: &str) -> String {
+    let chars: Vec<char> = markdown.chars().collect();
+    let single = |index: usize| {
+        chars[index] == '~'
+            && (index == 0 || chars[index - 1] != '~')
+            && chars.get(index + 1) != Some(&'~')
+            && !escaped(&chars, index)
+    };
+    let mut marked = chars.clone();
+    let mut index = 0;
+    while index < chars.len() {
+        if single(index) {
+            let close = (index + 1..chars.len())
+                .take_while(|&end| !chars[end].is_whitespace())
+                .find(|&end| chars[end] == '~');
+            if let Some(close) = close.filter(|&close| close > index + 1 && single(close)) {
+                marked[index] = SUBSCRIPT;
+                marked[close] = SUBSCRIPT;
+                index = close + 1;
+                continue;
+            }
+        }
+        index += 1;
+    }
+    marked.into_iter().collect()
+}
+
+fn restore(literal: &mut Vec<u8>) {
+    let text = String::from_utf8_lossy(literal);
+    if text.contains(SUBSCRIPT) {
+        *literal = text.replace(SUBSCRIPT, "~").into_bytes();
+    }
+}
+
+// put the marked tildes back wherever a subscript cannot be: code, urls,
+// front matter, raw html and the text of bare urls. has to run before
+// anything reads those
+pub fn restore_tildes<'a>(root: &'a AstNode<'a>) {
+    for node in root.descendants() {
+        let url = match node.data.borrow_mut().value {
+            NodeValue::Link(ref mut link) | NodeValue::Image(ref mut link) => {
+                restore(&mut link.url);
+                restore(&mut link.title);
+                String::from_utf8_lossy(&link.url).to_string()
+            }
+            NodeValue::Code(ref mut code) => {
+                restore(&mut code.literal);
+                continue;
+            }
+            NodeValue::CodeBlock(ref mut block) => {
+                restore(&mut block.info);
+                restore(&mut block.literal);
+                continue;
+            }
+            NodeValue::HtmlBlock(ref mut block) => {
+                restore(&mut block.literal);
+                continue;
+            }
+            NodeValue::HtmlInline(ref mut literal) | NodeValue::FrontMatter(ref mut literal) => {
+                restore(literal);
+                continue;
+            }
+            _ => continue,
+        };
+        // a bare url shows (the end of) its url as its text
+        let texts: Vec<&'a AstNode<'a>> = node.children().flat_map(text_nodes).collect();
+        let shown: String = texts.iter().map(|text| text_of(text)).collect();
+        if !shown.is_empty() && url.ends_with(&shown.replace(SUBSCRIPT, "~")) {
+            for text in texts {
+                if let NodeValue::Text(ref mut literal) = text.data.borrow_mut().value {
+                    restore(literal);
+                }
+            }
+        }
+    }
+}
+
+// marked pairs become <sub>, a marker left without its partner a tilde again
+pub fn subscript<'a>(arena: &'a Arena<AstNode<'a>>, root: &'a AstNode<'a>) {
+    wrap_delimited(arena, root, &SUBSCRIPT.to_string(), "<sub>", "</sub>");
+    for node in text_nodes(root) {
+        if let NodeValue::Text(ref mut literal) = node.data.borrow_mut().value {
+            restore(literal);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // `markdown` through the passes --subscript runs, with strikethrough on
+    fn render(markdown: &str) -> String {
+        let mut options = comrak::ComrakOptions::default();
+        options.extension.strikethrough = true;
+        options.extension.autolink = true;
+        options.render.unsafe_ = true;
+        let arena = Arena::new();
+        let root = comrak::parse_document(&arena, &mark_subscripts(markdown), &options);
+        restore_tildes(root);
+        subscript(&arena, root);
+        let mut html = vec![];
+        comrak::format_html(root, &options, &mut html).unwrap();
+        String::from_utf8(html).unwrap()
+    }
+
+    #[test]
+    fn double_tildes_strike() {
+        assert_eq!(render("~~strike~~\n"), "<p><del>strike</del></p>\n");
+        assert_eq!(render("~~H~2~O~~\n"), "<p><del>H<sub>2</sub>O</del></p>\n");
+    }
+
+    #[test]
+    fn single_tildes_around_a_word_are_a_subscript() {
+        assert_eq!(render("a~1~\n"), "<p>a<sub>1</sub></p>\n");
+        assert_eq!(
+            render("H~2~O and CO~2~\n"),
+            "<p>H<sub>2</sub>O and CO<sub>2</sub></p>\n"
+        );
+    }
+
+    #[test]
+    fn lone_and_spaced_tildes_stay() {
+        assert_eq!(render("a ~ b\n"), "<p>a ~ b</p>\n");
+        assert_eq!(render("~a b~\n"), "<p><del>a b</del></p>\n");
+        assert_eq!(render("about ~5 minutes\n"), "<p>about ~5 minutes</p>\n");
+        assert_eq!(render("a\\~1~\n"), "<p>a~1~</p>\n");
+    }
+
+    #[test]
+    fn code_and_urls_keep_their_tildes() {
+        assert_eq!(render("`a~1~`\n"), "<p><code>a~1~</code></p>\n");
+        assert_eq!(
+            render("https://example.com/~a~b\n"),
+            "<p><a href=\"https://example.com/~a~b\">https://example.com/~a~b</a></p>\n"
+        );
+        assert_eq!(
+            render("```\nx~1~\n```\n"),
+            "<pre><code>x~1~\n</code></pre>\n"
+        );
+    }
+}
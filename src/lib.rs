@@ -0,0 +1,4728 @@
+extern crate comrak;
+mod api;
+mod assets;
+mod autolink;
+mod binder;
+mod collate;
+mod config;
+mod console;
+mod context;
+mod degradations;
+mod diagnostic;
+mod dialect;
+mod doctor;
+#[cfg(not(feature = "offline-bundle"))]
+mod download;
+mod emoji;
+mod exit;
+mod explain;
+mod footnotes;
+mod front_matter;
+mod glossary;
+mod headings;
+mod highlight;
+mod hooks;
+mod html;
+mod include;
+mod incremental;
+mod init;
+mod inline;
+mod limits;
+mod links;
+mod math;
+mod mermaid;
+mod meta;
+mod offline;
+mod opener;
+mod output;
+mod paths;
+mod private;
+mod progress;
+mod quotes;
+mod replay;
+mod resources;
+mod sanitize;
+mod serve;
+mod source_map;
+mod stamp;
+mod summary;
+mod tables;
+mod template;
+mod terminal;
+mod themes;
+mod toc;
+mod watch;
+
+// the downloads need reqwest, which only an offline build leaves out
+#[cfg(not(any(feature = "network", feature = "offline-bundle")))]
+compile_error!("yamc needs the network feature, or offline-bundle for a build without downloads");
+pub use api::{render_document, Error, Heading, RenderOptions, RenderedDocument, Warning};
+use clap::parser::ValueSource;
+use clap::{
+    ArgAction, ArgMatches, Args, CommandFactory, FromArgMatches, Parser, Subcommand, ValueHint,
+};
+use comrak::nodes::AstNode;
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::env;
+use std::ffi::{OsStr, OsString};
+use std::fs;
+use std::io::{self, BufWriter, Read, Write};
+use std::panic;
+use std::path::{Path, PathBuf};
+use std::process;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{mpsc, Arc, Mutex, OnceLock};
+use std::thread;
+use std::time::{Duration, Instant};
+
+// eprintln! for everything yamc says: documents converted side by side keep
+// their messages together until each is done
+macro_rules! say {
+    ($($arg:tt)*) => {
+        console::write(format!("{}\n", format_args!($($arg)*)))
+    };
+}
+
+const STYLESHEET_URL: &str =
+    "https://cdnjs.cloudflare.com/ajax/libs/github-markdown-css/4.0.0/github-markdown.min.css";
+
+#[derive(Clone)]
+struct Options {
+    syntax: dialect::Syntax,
+    tables: tables::TableOptions,
+    binder: bool,
+    exhibit_format: String,
+    shift_headings: i32,
+    strip_title: bool,
+    title: Option<String>,
+    // none with --no-highlight
+    highlight_theme: Option<String>,
+    toc: bool,
+    toc_depth: u8,
+    anchors: bool,
+    // ```mermaid blocks drawn as diagrams
+    mermaid: bool,
+    // :shortcode: replaced with its emoji
+    emoji: bool,
+    max_input_size: Option<u64>,
+    summary_length: usize,
+    emit_resources: bool,
+    json: bool,
+    deny_domains: Vec<String>,
+    expose_env: Vec<String>,
+    no_autolink_in_headings: bool,
+    no_autolink_in_tables: bool,
+    spec_strict: bool,
+    show_events: bool,
+    line: Option<u32>,
+    output: PathBuf,
+    // in the order they go into the page, later ones win
+    stylesheets: Vec<Stylesheet>,
+    css_class: String,
+    // a handlebars template the pages are laid out with, unless their front
+    // matter names one
+    template: Option<PathBuf>,
+    // markup put at the end of the head as it is, in order
+    head_html: Vec<String>,
+    favicon: Option<assets::Icon>,
+    // the lang and dir of every page, before their front matter
+    lang: Option<String>,
+    dir: Option<String>,
+    // author and Open Graph tags from front matter
+    meta: bool,
+    // pull in the files include directives name, inside the input's root
+    includes: bool,
+    // what relative urls in those tags are relative to
+    base_url: Option<url::Url>,
+    html_flavor: html::Flavor,
+    output_encoding: Option<html::Encoding>,
+    wrap_html: bool,
+    keep_head_assets: bool,
+    strip_comments: bool,
+    minify: bool,
+    // the author's raw html is rendered instead of omitted
+    unsafe_html: bool,
+    sanitize: bool,
+    list_private: bool,
+    dry_run: bool,
+    // pages whose names differ only in case are the same page, whatever the
+    // file system says
+    case_insensitive_outputs: bool,
+    quote_citations: bool,
+    heading_attrs: bool,
+    pre_hook: Option<String>,
+    post_hook: Option<String>,
+    hook_timeout: u64,
+    asset_root: Option<String>,
+    limits: limits::Limits,
+    time_budget: Option<Duration>,
+    strict: bool,
+    deny_degradations: Vec<String>,
+    embed_images: bool,
+    fetch_remote_images: bool,
+    max_embed_size: u64,
+    source_map: bool,
+    glossary: Option<String>,
+    glossary_case_sensitive: bool,
+    code_wrap: html::CodeWrap,
+    css_variables: Vec<(String, String)>,
+    pager: bool,
+    open: bool,
+    open_with: Option<String>,
+    heading_id_prefix: String,
+    output_dir: Option<PathBuf>,
+    // directories given as inputs, their files keep their place below them
+    // in --output-dir
+    trees: Vec<PathBuf>,
+    // files in those directories that are not markdown
+    skipped: usize,
+    // every input goes to a page of its own
+    each: bool,
+    // links to other inputs point at their pages
+    rewrite_links: bool,
+    // files the pages refer to go along to --output-dir
+    copy_assets: bool,
+    // the canonical paths of the inputs, with --each and --rewrite-links
+    pages: Vec<PathBuf>,
+    hidden: bool,
+    // patterns leaving files out of input directories
+    excludes: Vec<String>,
+    watch: bool,
+    port: u16,
+    force: bool,
+    // documents converted at once
+    jobs: usize,
+    incremental: bool,
+    // the options the pages are converted with, for --incremental
+    fingerprint: String,
+    // pages this run wrote, --watch replaces them without --force
+    written: Vec<PathBuf>,
+    dump_config: Option<String>,
+    offline: bool,
+    verbosity: Verbosity,
+    // the document itself, for a caller of `render_document` that has it in
+    // memory. nothing is read from files then
+    text: Option<Arc<str>>,
+}
+
+// a stylesheet of the page and where it comes from
+#[derive(Clone)]
+enum Stylesheet {
+    // linked at this url
+    Linked(String),
+    // the text of a local or downloaded file, put into the page
+    Inline(String),
+}
+
+// how much yamc says on stderr. errors are always printed
+#[derive(Clone, Copy, PartialEq)]
+enum Verbosity {
+    // errors only
+    Quiet,
+    Normal,
+    // also the options in effect, stage timings and output sizes
+    Verbose,
+}
+
+// the html body of one markdown document plus what the page around it needs
+struct Rendered {
+    html: String,
+    title: String,
+    front_matter: Option<String>,
+    summary: Option<String>,
+    // the table of contents, for a template to place
+    toc: String,
+    // the headings, with their ids
+    outline: Vec<toc::Entry>,
+    enhanced_tables: bool,
+    math: bool,
+    diagrams: bool,
+    // relative urls of the files the page refers to
+    references: Vec<String>,
+    degradations: degradations::Degradations,
+    // what the passes warned about, reported by whoever asked for the page
+    warnings: Vec<diagnostic::Diagnostic>,
+    // the parse tree, with --show-events
+    events: Option<String>,
+    // the absolute urls of the links and images, for --emit-resources
+    resources: Vec<resources::Resource>,
+    timings: Timings,
+}
+
+// how long the stages of one document took, for --verbose
+#[derive(Clone, Copy, Default)]
+struct Timings {
+    parse: Duration,
+    render: Duration,
+}
+
+fn comrak_options(options: &Options) -> comrak::ComrakOptions {
+    if options.spec_strict {
+        dialect::strict_comrak_options()
+    } else {
+        dialect::create_comrak_options(&options.syntax)
+    }
+}
+
+// print a warning or error about a source file, one json object per line
+// with --json
+fn report(diagnostic: &diagnostic::Diagnostic, options: &Options) {
+    if options.verbosity == Verbosity::Quiet && diagnostic.severity == diagnostic::Severity::Warning
+    {
+        return;
+    }
+    if options.json {
+        say!("{}", diagnostic.to_json());
+        console::note(&diagnostic.message);
+    } else {
+        console::write(diagnostic.render(diagnostic::color()));
+    }
+}
+
+// a document that could not be converted, what went wrong has been printed.
+// the other documents of the run can still be converted
+#[derive(Clone, Copy, Debug)]
+enum Failed {
+    Conversion,
+    // the options ask for something that cannot be done
+    Usage,
+    // reading an input or writing the page
+    Io,
+    // the document took longer than --time-budget
+    Timeout,
+}
+
+impl Failed {
+    // for --json
+    fn kind(self) -> &'static str {
+        match self {
+            Failed::Conversion => "conversion",
+            Failed::Usage => "usage",
+            Failed::Io => "io",
+            Failed::Timeout => "timeout",
+        }
+    }
+
+    fn exit(self) -> ! {
+        match self {
+            Failed::Conversion => exit::Status::Failed.exit(),
+            Failed::Usage => exit::Status::Usage.exit(),
+            Failed::Io => exit::Status::Io.exit(),
+            Failed::Timeout => exit::Status::Timeout.exit(),
+        }
+    }
+}
+
+// the input name that stands for standard input
+const STDIN: &str = "-";
+// the output name that stands for standard output
+const STDOUT: &str = "-";
+
+fn is_stdin(path: &Path) -> bool {
+    path == Path::new(STDIN)
+}
+
+fn is_stdout(path: &Path) -> bool {
+    path == Path::new(STDOUT)
+}
+
+// fail the way reading would when `path` cannot be read, before anything
+// is decided about its output
+fn check_input(path: &Path) -> Result<(), Failed> {
+    if is_stdin(path) {
+        return Ok(());
+    }
+    match fs::File::open(path) {
+        Ok(_) => Ok(()),
+        Err(err) => {
+            say!("Cannot read {} : {}", paths::display(path), err);
+            Err(Failed::Io)
+        }
+    }
+}
+
+// the text of an input file. standard input can only be read once, it is kept
+// for passes that read the input again, such as --emit-resources
+fn read_input(path: &Path, options: &Options) -> Result<String, Failed> {
+    if let Some(text) = &options.text {
+        return Ok(text.to_string());
+    }
+    static STANDARD_INPUT: OnceLock<Result<String, String>> = OnceLock::new();
+    if is_stdin(path) {
+        let read = STANDARD_INPUT.get_or_init(|| {
+            let mut text = String::new();
+            io::stdin()
+                .read_to_string(&mut text)
+                .map(|_| text)
+                .map_err(|err| err.to_string())
+        });
+        return match read {
+            Ok(text) => Ok(text.clone()),
+            Err(err) => {
+                say!("Cannot read standard input : {}", err);
+                Err(Failed::Io)
+            }
+        };
+    }
+    fs::read_to_string(path).map_err(|err| {
+        say!("Cannot read {} : {}", paths::display(path), err);
+        Failed::Io
+    })
+}
+
+// extra detail for --verbose
+fn detail(options: &Options, message: String) {
+    if options.verbosity == Verbosity::Verbose {
+        say!("{}", message);
+    }
+}
+
+// read a document and run the passes that work on its markdown structure
+fn parse_markdown<'a>(
+    arena: &'a comrak::Arena<AstNode<'a>>,
+    path: &Path,
+    options: &Options,
+) -> Result<&'a AstNode<'a>, Failed> {
+    let mut warnings = Vec::new();
+    let parsed = parse_titled(arena, path, options, &mut warnings);
+    for warning in &warnings {
+        report(warning, options);
+    }
+    parsed.map(|(root, _)| root)
+}
+
+// refuse an input larger than --max-input-size before reading it
+fn check_size(path: &Path, options: &Options) -> Result<(), Failed> {
+    if let Some(limit) = options.max_input_size {
+        // standard input has no size until it is read
+        let size = if is_stdin(path) {
+            read_input(path, options)?.len() as u64
+        } else {
+            match fs::metadata(path) {
+                Ok(metadata) => metadata.len(),
+                Err(err) => {
+                    say!("Cannot read {} : {}", paths::display(path), err);
+                    return Err(Failed::Io);
+                }
+            }
+        };
+        if size > limit {
+            say!(
+                "{} is {} bytes, larger than --max-input-size {}",
+                paths::display(path),
+                size,
+                limit
+            );
+            return Err(Failed::Conversion);
+        }
+    }
+    Ok(())
+}
+
+// the options for one document, whose front matter, its _defaults.md files
+// included, can switch some syntax features for itself
+fn document_options<'o>(path: &Path, options: &'o Options) -> Result<Cow<'o, Options>, Failed> {
+    if options.spec_strict {
+        return Ok(Cow::Borrowed(options));
+    }
+    check_size(path, options)?;
+    let markdown = read_input(path, options)?;
+    // the warnings come again with the rest of the front matter
+    let mut layers = defaults(path, options, &mut Vec::new());
+    layers.extend(front_matter::leading(&markdown).map(|(own, _)| own.to_string()));
+    let front_matter = match front_matter::merge(&layers) {
+        Some(front_matter) => front_matter,
+        None => return Ok(Cow::Borrowed(options)),
+    };
+    let mut document = Cow::Borrowed(options);
+    for feature in dialect::DOCUMENT_FEATURES {
+        let enabled = match front_matter::value(&front_matter, feature).as_deref() {
+            Some("true") => true,
+            Some("false") => false,
+            _ => continue,
+        };
+        document
+            .to_mut()
+            .syntax
+            .set(feature, enabled)
+            .expect("document features are features");
+    }
+    Ok(document)
+}
+
+// `parse_markdown`, with the text of the first h1 as it was before
+// --strip-title took it out. warnings are added to `warnings`
+fn parse_titled<'a>(
+    arena: &'a comrak::Arena<AstNode<'a>>,
+    path: &Path,
+    options: &Options,
+    warnings: &mut Vec<diagnostic::Diagnostic>,
+) -> Result<(&'a AstNode<'a>, Option<String>), Failed> {
+    check_size(path, options)?;
+    let markdown = read_input(path, options)?;
+    if options.spec_strict {
+        let root = comrak::parse_document(arena, &markdown, &comrak_options(options));
+        return Ok((root, headings::title(root)));
+    }
+    let markdown = private::strip(markdown, path).map_err(|err| {
+        report(&err, options);
+        Failed::Conversion
+    })?;
+    let markdown = dialect::preprocess(markdown, &options.syntax);
+    let root = comrak::parse_document(arena, &markdown, &comrak_options(options));
+    if options.includes {
+        let bounds = include::Bounds {
+            root: input_root(path, options),
+            prefix: &options.heading_id_prefix,
+        };
+        if let Err(err) = include::expand(arena, root, path, &options.syntax, &bounds) {
+            report(&err, options);
+            return Err(Failed::Conversion);
+        }
+    }
+    if options.syntax.subscript {
+        inline::restore_tildes(root);
+    }
+    if let Err(err) = limits::check(root, &options.limits) {
+        say!("{}: {}", paths::display(path), err);
+        return Err(Failed::Conversion);
+    }
+    let mut context = context::Context::new(&options.expose_env);
+    if let Err(err) = context::substitute(root, &mut context, path) {
+        report(&err, options);
+        return Err(Failed::Conversion);
+    }
+    if let Some(asset_root) = &options.asset_root {
+        // relative urls only work from the current directory, and images
+        // are embedded from where they are
+        let in_place = options
+            .output
+            .parent()
+            .is_none_or(|parent| parent.as_os_str().is_empty());
+        let asset_root = match fs::canonicalize(asset_root) {
+            Ok(absolute) if !in_place || options.embed_images => absolute,
+            _ => Path::new(asset_root).to_path_buf(),
+        };
+        warnings.extend(assets::resolve_root(root, &asset_root, path));
+    }
+    if options.embed_images {
+        warnings.extend(assets::embed_images(root, path, options.max_embed_size));
+    }
+    if !options.pages.is_empty() {
+        let trees: Vec<PathBuf> = options
+            .trees
+            .iter()
+            .filter_map(|tree| fs::canonicalize(tree).ok())
+            .collect();
+        let site = links::Site {
+            pages: &options.pages,
+            trees: &trees,
+            extension: match options.html_flavor {
+                html::Flavor::Xhtml => "xhtml",
+                _ => "html",
+            },
+        };
+        warnings.extend(links::rewrite(root, path, &site));
+    }
+    #[cfg(not(feature = "offline-bundle"))]
+    if options.fetch_remote_images {
+        warnings.extend(assets::fetch_images(root, path, options.max_embed_size));
+    }
+    if options.syntax.autolink_domains {
+        autolink::link_domains(arena, root);
+    }
+    if options.no_autolink_in_headings || options.no_autolink_in_tables {
+        autolink::unwrap_in(
+            root,
+            options.no_autolink_in_headings,
+            options.no_autolink_in_tables,
+        );
+    }
+    let title = headings::title(root);
+    if options.strip_title {
+        headings::strip_title(root);
+    }
+    if options.shift_headings != 0 {
+        for line in headings::shift(root, options.shift_headings) {
+            warnings.push(diagnostic::Diagnostic::warning(
+                "clamped-heading",
+                path,
+                line,
+                "heading clamped to the h1..h6 range",
+            ));
+        }
+    }
+    Ok((root, title))
+}
+
+// the passes that need authored raw html out of the way first
+fn finish_markdown<'a>(
+    arena: &'a comrak::Arena<AstNode<'a>>,
+    root: &'a AstNode<'a>,
+    options: &Options,
+) -> Result<degradations::Degradations, Failed> {
+    let mut degradations = degradations::Degradations::default();
+    // plain commonmark still keeps raw html out unless --unsafe
+    if !options.unsafe_html {
+        html::omit_raw(root, &mut degradations);
+    }
+    if options.spec_strict {
+        return Ok(degradations);
+    }
+    if options.syntax.wikilinks {
+        inline::wikilinks(arena, root);
+    }
+    if options.syntax.highlight {
+        inline::highlight(arena, root);
+    }
+    if options.syntax.subscript {
+        inline::subscript(arena, root);
+    }
+    if options.emoji {
+        emoji::replace(root);
+    }
+    if let Some(path) = &options.glossary {
+        let glossary = glossary::Glossary::load(
+            Path::new(path),
+            &options.syntax,
+            options.glossary_case_sensitive,
+        )
+        .map_err(|err| {
+            say!("{}", err);
+            Failed::Usage
+        })?;
+        glossary::link(arena, root, glossary);
+    }
+    // last, nothing that works on text should reach into a formula
+    if options.syntax.math {
+        math::render(arena, root);
+    }
+    Ok(degradations)
+}
+
+fn format_html<'a>(node: &'a AstNode<'a>, options: &Options) -> String {
+    let mut html = vec![];
+    comrak::format_html(node, &comrak_options(options), &mut html).expect("Unable to render html");
+    String::from_utf8(html).expect("Rendered html should be utf-8")
+}
+
+// the front matter of the _defaults.md files above a document. one given as
+// text is in no directory
+fn defaults(
+    path: &Path,
+    options: &Options,
+    warnings: &mut Vec<diagnostic::Diagnostic>,
+) -> Vec<String> {
+    match options.text {
+        Some(_) => Vec::new(),
+        None => front_matter::defaults(path, warnings),
+    }
+}
+
+// the document's own front matter on top of the _defaults.md files above it
+fn effective_front_matter(
+    path: &Path,
+    own: Option<String>,
+    options: &Options,
+    warnings: &mut Vec<diagnostic::Diagnostic>,
+) -> Option<String> {
+    let mut layers = defaults(path, options, warnings);
+    layers.extend(own);
+    front_matter::merge(&layers)
+}
+
+// the body of a markdown document. what was said about a document that then
+// failed is reported along with why it failed
+fn render_markdown(path: &Path, options: &Options) -> Result<Rendered, Failed> {
+    let mut warnings = Vec::new();
+    match markdown_body(path, options, &mut warnings) {
+        Ok(rendered) => Ok(Rendered {
+            warnings,
+            ..rendered
+        }),
+        Err(failed) => {
+            for warning in &warnings {
+                report(warning, options);
+            }
+            Err(failed)
+        }
+    }
+}
+
+fn markdown_body(
+    path: &Path,
+    options: &Options,
+    warnings: &mut Vec<diagnostic::Diagnostic>,
+) -> Result<Rendered, Failed> {
+    let options = &*document_options(path, options)?;
+    let started = Instant::now();
+    let arena = comrak::Arena::new();
+    let (root, heading) = parse_titled(&arena, path, options, warnings)?;
+    let parse = started.elapsed();
+    let started = Instant::now();
+    let (table_plans, heading_attributes) = if options.spec_strict {
+        (Vec::new(), Vec::new())
+    } else {
+        (
+            tables::plan(root, &options.tables),
+            headings::take_attributes(root),
+        )
+    };
+    let front_matter = effective_front_matter(path, front_matter::raw(root), options, warnings);
+    let mut summary = summary::extract(root, front_matter.as_deref(), options.summary_length);
+    let mut heading = heading;
+    // both are taken before the shortcodes of the text are replaced
+    if options.emoji {
+        summary = summary.map(|summary| emoji::text(&summary));
+        heading = heading.map(|heading| emoji::text(&heading));
+    }
+    let title = page_title(path, front_matter.as_deref(), heading, options);
+    let toc = options.toc
+        || front_matter
+            .as_deref()
+            .and_then(|front_matter| front_matter::value(front_matter, "toc"))
+            .is_some_and(|value| value == "true");
+    if toc {
+        toc::mark_place(root);
+    }
+    let toc_hints = toc::hints(&heading_attributes);
+    let references = assets::local_references(root);
+    let degradations = finish_markdown(&arena, root, options)?;
+    if options.offline && options.mermaid && mermaid::found(root) {
+        say!(
+            "{}: --offline does not allow mermaid diagrams, mermaid is loaded over the network; --no-mermaid shows them as code",
+            paths::display(path)
+        );
+        return Err(Failed::Usage);
+    }
+    let enhanced_tables = tables::enhance(&arena, root, table_plans);
+    let diagrams = options.mermaid && mermaid::diagrams(root);
+    if let Some(theme) = &options.highlight_theme {
+        highlight::code_blocks(root, theme);
+    }
+    let cited = options.quote_citations
+        && !options.spec_strict
+        && quotes::cite(&arena, root, &comrak_options(options));
+    let events = options.show_events.then(|| explain::events(root));
+    let found = resources::collect(root);
+    if options.source_map {
+        source_map::mark(&arena, root);
+    }
+    let html = format_html(root, options);
+    let math = options.syntax.math && math::used(&html);
+    let html = if options.spec_strict {
+        html
+    } else {
+        headings::apply_attributes(
+            html,
+            heading_attributes,
+            options.heading_attrs,
+            &options.heading_id_prefix,
+        )
+        .map_err(|err| {
+            say!("{}: {}", paths::display(path), err);
+            Failed::Conversion
+        })?
+    };
+    // everything up to here can carry what the author wrote, what comes
+    // after is yamc's own
+    let html = if options.sanitize {
+        sanitize::clean(&html)
+    } else {
+        html
+    };
+    // after the ids are settled, so the links match them. a template gets
+    // it without --toc too
+    let outline = toc::entries(&html, &toc_hints);
+    let contents = toc::contents(&outline, options.toc_depth);
+    let html = if toc {
+        toc::insert(html, &contents)
+    } else {
+        html
+    };
+    let html = if options.anchors {
+        headings::permalinks(html)
+    } else {
+        html
+    };
+    let html = if options.source_map {
+        source_map::annotate(html)
+    } else {
+        html
+    };
+    // comrak's tagfilter would escape the style element
+    let html = if cited {
+        format!("{}{}", quotes::STYLE, html)
+    } else {
+        html
+    };
+    Ok(Rendered {
+        html,
+        title,
+        front_matter,
+        summary,
+        toc: contents,
+        outline,
+        enhanced_tables,
+        math,
+        diagrams,
+        references,
+        degradations,
+        warnings: Vec::new(),
+        events,
+        resources: found,
+        timings: Timings {
+            parse,
+            render: started.elapsed(),
+        },
+    })
+}
+
+// the title of a page: --title, the front matter's, the first h1 or the
+// name of the input, in that order
+fn page_title(
+    path: &Path,
+    front_matter: Option<&str>,
+    heading: Option<String>,
+    options: &Options,
+) -> String {
+    options
+        .title
+        .clone()
+        .or_else(|| {
+            front_matter.and_then(|front_matter| front_matter::value(front_matter, "title"))
+        })
+        .or(heading)
+        .or_else(|| {
+            path.file_stem()
+                .filter(|_| !is_stdin(path))
+                .map(|stem| stem.to_string_lossy().into_owned())
+        })
+        .unwrap_or_else(|| "Untitled".to_string())
+}
+
+fn is_html(path: &Path) -> bool {
+    path.extension()
+        .and_then(|extension| extension.to_str())
+        .is_some_and(|extension| {
+            extension.eq_ignore_ascii_case("html") || extension.eq_ignore_ascii_case("htm")
+        })
+}
+
+// a hand-written page: its body, optionally with the styles and scripts of
+// its head, goes into yamc's page as it is
+fn render_html(path: &Path, options: &Options) -> Result<Rendered, Failed> {
+    let document = read_input(path, options)?;
+    let mut html = String::new();
+    if options.keep_head_assets {
+        html.push_str(&html::head_assets(&document));
+    }
+    html.push_str(html::body(&document));
+    let mut warnings = Vec::new();
+    let front_matter = effective_front_matter(path, None, options, &mut warnings);
+    Ok(Rendered {
+        html,
+        title: page_title(path, front_matter.as_deref(), None, options),
+        front_matter,
+        summary: None,
+        toc: String::new(),
+        outline: Vec::new(),
+        enhanced_tables: false,
+        math: false,
+        diagrams: false,
+        references: Vec::new(),
+        degradations: degradations::Degradations::default(),
+        warnings,
+        events: None,
+        resources: Vec::new(),
+        timings: Timings::default(),
+    })
+}
+
+// run `work` on a thread with a large stack. a document that takes longer
+// than --time-budget fails on its own, its thread is left behind and the run
+// goes on with the other documents
+fn within_budget<T: Send + 'static>(
+    path: &Path,
+    options: &Options,
+    work: impl FnOnce(&Path, &Options) -> Result<T, Failed> + Send + 'static,
+) -> Result<T, Failed> {
+    let (done, finished) = mpsc::channel();
+    let (input, settings) = (path.to_path_buf(), options.clone());
+    thread::Builder::new()
+        .stack_size(limits::STACK_SIZE)
+        .spawn(console::carry(move || {
+            // a panic is caught here so that it can be raised again on the
+            // thread that waits
+            let result = panic::catch_unwind(panic::AssertUnwindSafe(|| work(&input, &settings)));
+            let _ = done.send(result);
+        }))
+        .expect("Unable to start a thread");
+    let result = match options.time_budget {
+        Some(budget) => match finished.recv_timeout(budget) {
+            Ok(result) => result,
+            Err(_) => {
+                say!(
+                    "{}: conversion took longer than --time-budget {}s",
+                    paths::display(path),
+                    budget.as_secs()
+                );
+                return Err(Failed::Timeout);
+            }
+        },
+        None => finished.recv().expect("the worker always sends its result"),
+    };
+    result.unwrap_or_else(|panic| panic::resume_unwind(panic))
+}
+
+fn render_input(path: &Path, options: &Options) -> Result<Rendered, Failed> {
+    let mut rendered = if is_html(path) {
+        render_html(path, options)?
+    } else {
+        render_markdown(path, options)?
+    };
+    if options.strip_comments {
+        rendered.html = html::strip_comments(&rendered.html);
+    }
+    Ok(rendered)
+}
+
+// a document made into its whole page in memory
+struct Document {
+    rendered: Rendered,
+    page: String,
+}
+
+// the page for `path`, the same for a conversion, serve and --watch. nothing
+// is written on the way, the warnings and timings come back in the result
+fn render_page(path: &Path, options: &Options) -> Result<Document, Failed> {
+    let rendered = render_input(path, options)?;
+    let template = page_template(path, rendered.front_matter.as_deref(), options);
+    let page = page_parts(
+        options,
+        &rendered.title,
+        rendered.summary.as_deref(),
+        &rendered.toc,
+        rendered.front_matter.as_deref(),
+        template.as_deref(),
+        &[
+            &rendered.html,
+            &assets(rendered.enhanced_tables, rendered.math, rendered.diagrams),
+        ],
+    )?
+    .concat();
+    Ok(Document { rendered, page })
+}
+
+// whether --offline replaces the default stylesheet link with yamc's own
+fn bundled_stylesheet(options: &Options) -> bool {
+    options.offline
+        && matches!(options.stylesheets.as_slice(), [Stylesheet::Linked(url)] if url == STYLESHEET_URL)
+}
+
+// the page around `body` in pieces, stamped, laid out by `template` or the
+// built-in layout. ascii output and the xhtml check need each piece as a
+// whole
+fn page_parts<'a>(
+    options: &Options,
+    title: &str,
+    description: Option<&str>,
+    toc: &str,
+    front_matter: Option<&str>,
+    template: Option<&Path>,
+    body: &[&'a str],
+) -> Result<Vec<Cow<'a, str>>, Failed> {
+    let xhtml = options.html_flavor == html::Flavor::Xhtml;
+    let void = if xhtml { " />" } else { ">" };
+    // the page around the rendered markdown
+    let mut head = String::new();
+    let encoding = options.output_encoding.unwrap_or(html::Encoding::Utf8);
+    head.push_str(&format!("<meta charset=\"{}\"{}", encoding.charset(), void));
+    head.push_str(&format!("<title>{}</title>", html::escape(title)));
+    let mut css_links = Vec::new();
+    let mut inline_css = String::new();
+    if bundled_stylesheet(options) {
+        inline_css.push_str(&offline::style(&options.css_class));
+        head.push_str(&inline_css);
+    } else {
+        for stylesheet in &options.stylesheets {
+            match stylesheet {
+                Stylesheet::Linked(url) => {
+                    css_links.push(url.as_str());
+                    head.push_str(&format!(
+                        "<link rel=\"stylesheet\" href=\"{}\"{}",
+                        html::escape(url),
+                        void
+                    ))
+                }
+                Stylesheet::Inline(css) => {
+                    let style = html::style_element(css);
+                    head.push_str(&style);
+                    inline_css.push_str(&style);
+                }
+            }
+        }
+    }
+    if let Some(description) = description {
+        head.push_str(&format!(
+            "<meta name=\"description\" content=\"{}\"{}",
+            html::escape(description),
+            void
+        ));
+    }
+    if let Some(front_matter) = front_matter.filter(|_| options.meta) {
+        head.push_str(&meta::tags(
+            front_matter,
+            title,
+            description,
+            options.base_url.as_ref(),
+            void,
+        ));
+    }
+    if let Some(style) = options.code_wrap.style(&options.css_class) {
+        head.push_str(&style);
+    }
+    if let Some(style) = html::variables_style(&options.css_variables, &options.css_class) {
+        head.push_str(&style);
+    }
+    if let Some(icon) = &options.favicon {
+        let media_type = icon
+            .media_type
+            .map(|media_type| format!(" type=\"{}\"", media_type))
+            .unwrap_or_default();
+        head.push_str(&format!(
+            "<link rel=\"icon\"{} href=\"{}\"{}",
+            media_type,
+            html::escape(&icon.href),
+            void
+        ));
+        // ios wants a png for the home screen
+        if icon.media_type == Some("image/png") {
+            head.push_str(&format!(
+                "<link rel=\"apple-touch-icon\" href=\"{}\"{}",
+                html::escape(&icon.href),
+                void
+            ));
+        }
+    }
+    let lang = options.lang.clone().or_else(|| {
+        front_matter.and_then(|front_matter| front_matter::value(front_matter, "lang"))
+    });
+    let dir = options.dir.clone().or_else(|| {
+        front_matter
+            .and_then(|front_matter| front_matter::value(front_matter, "dir"))
+            .filter(|dir| ["ltr", "rtl", "auto"].contains(&dir.as_str()))
+    });
+    if dir.as_deref() == Some("rtl") {
+        head.push_str(&html::rtl_style(&options.css_class, options.code_wrap));
+    }
+    for markup in &options.head_html {
+        head.push_str(markup);
+    }
+    let (name, source) = match template {
+        Some(template) => (
+            paths::display(template),
+            Cow::Owned(template::load(template).map_err(|err| {
+                say!("{}", err);
+                Failed::Conversion
+            })?),
+        ),
+        None if xhtml => (
+            "built-in xhtml layout".to_string(),
+            Cow::Borrowed(template::XHTML),
+        ),
+        None => (
+            "built-in layout".to_string(),
+            Cow::Borrowed(template::DEFAULT),
+        ),
+    };
+    let page = template::Page {
+        title,
+        description,
+        summary: description,
+        toc,
+        head: &head,
+        css_links,
+        inline_css,
+        css_class: &options.css_class,
+        lang: lang.as_deref(),
+        dir: dir.as_deref(),
+        front_matter: front_matter.map(front_matter::fields).unwrap_or_default(),
+    };
+    let (opening, closing) = template::render(&name, &source, &page).map_err(|err| {
+        say!("Template error : {}", err);
+        Failed::Conversion
+    })?;
+    let mut parts: Vec<Cow<str>> = vec![Cow::Owned(opening)];
+    parts.extend(body.iter().map(|part| Cow::Borrowed(*part)));
+    if !closing.is_empty() {
+        parts.push(Cow::Owned(closing));
+    }
+    if options.output_encoding == Some(html::Encoding::Ascii) {
+        parts = parts
+            .into_iter()
+            .map(|part| Cow::Owned(html::to_ascii(&part)))
+            .collect();
+    }
+    if options.minify {
+        let page = parts.concat();
+        let config = minify_html::Cfg {
+            minify_css: true,
+            ..minify_html::Cfg::new()
+        };
+        let minified =
+            String::from_utf8_lossy(&minify_html::minify(page.as_bytes(), &config)).into_owned();
+        detail(
+            options,
+            format!(
+                "minified {} bytes to {}, {}% smaller",
+                page.len(),
+                minified.len(),
+                100usize.saturating_sub(minified.len() * 100 / page.len().max(1))
+            ),
+        );
+        parts = vec![Cow::Owned(minified)];
+    }
+    if xhtml {
+        if let Err(err) = html::check_well_formed(&parts.concat()) {
+            say!("Generated XHTML is not well-formed : {}", err);
+            return Err(Failed::Conversion);
+        }
+    }
+    let stamp = stamp::stamp(&parts);
+    parts.push(Cow::Owned(stamp));
+    Ok(parts)
+}
+
+// write the page piece by piece, a binder's pieces are not put together in
+// memory a second time
+fn write_output(options: &Options, parts: &[Cow<str>]) -> Result<(), Failed> {
+    let size: usize = parts.iter().map(|part| part.len()).sum();
+    detail(
+        options,
+        format!(
+            "writing {} bytes to {}",
+            size,
+            paths::display(&options.output)
+        ),
+    );
+    if is_stdout(&options.output) {
+        let mut stdout = io::stdout().lock();
+        let written = parts
+            .iter()
+            .try_for_each(|part| stdout.write_all(part.as_bytes()))
+            .and_then(|()| stdout.flush());
+        match written {
+            // whoever reads the page may stop early, that is up to them
+            Ok(()) => return Ok(()),
+            Err(err) if err.kind() == io::ErrorKind::BrokenPipe => return Ok(()),
+            Err(err) => {
+                say!("Cannot write the page to standard output : {}", err);
+                return Err(Failed::Io);
+            }
+        }
+    }
+    let path = &options.output;
+    let file = fs::File::create(path).map_err(|err| {
+        say!("{}", output::describe(path, &err));
+        Failed::Io
+    })?;
+    let mut writer = BufWriter::new(file);
+    let written = parts
+        .iter()
+        .try_for_each(|part| writer.write_all(part.as_bytes()))
+        .and_then(|()| writer.flush());
+    if let Err(err) = written {
+        // a truncated page is worse than none, devices and pipes are left be
+        drop(writer);
+        if fs::metadata(path).is_ok_and(|metadata| metadata.is_file()) {
+            let _ = fs::remove_file(path);
+        }
+        say!("{}", output::describe(path, &err));
+        return Err(Failed::Io);
+    }
+    Ok(())
+}
+
+// whether a file at the output stays as it is: without --force only pages
+// this run wrote are replaced
+fn keeps_existing(options: &Options) -> bool {
+    !options.force
+        && !is_stdout(&options.output)
+        && !options.written.contains(&options.output)
+        && options.output.is_file()
+}
+
+// write yamc.toml, and example.md with `sample`, next to nothing that is
+// already there unless `force`
+fn init_project(
+    sample: bool,
+    force: bool,
+    output_dir: Option<&str>,
+    gitignore: bool,
+) -> Result<(), Failed> {
+    let command = command_line();
+    let convert = command
+        .find_subcommand("convert")
+        .expect("convert is a command");
+    let text = init::config_text(convert, output_dir);
+    let mut files = vec![(config::FILE, text.as_str())];
+    if sample {
+        files.push((init::SAMPLE, init::SAMPLE_TEXT));
+    }
+    let repository = init::repository();
+    let ignore_line = match (gitignore, &repository, output_dir) {
+        (true, None, _) => {
+            say!("--gitignore needs a git repository, none is above this directory");
+            return Err(Failed::Usage);
+        }
+        (true, Some(repository), Some(directory)) => {
+            Some(init::ignore_line(repository, directory).map_err(|err| {
+                say!("--gitignore: {}", err);
+                Failed::Usage
+            })?)
+        }
+        _ => None,
+    };
+    let existing: Vec<&str> = files
+        .iter()
+        .map(|(file, _)| *file)
+        .filter(|file| Path::new(file).exists())
+        .collect();
+    if !force && !existing.is_empty() {
+        let verb = if existing.len() == 1 {
+            "exists"
+        } else {
+            "exist"
+        };
+        say!(
+            "{} already {}, --force replaces it",
+            existing.join(", "),
+            verb
+        );
+        return Err(Failed::Conversion);
+    }
+    for (file, contents) in &files {
+        if let Err(err) = fs::write(file, contents) {
+            say!("cannot write {}: {}", file, err);
+            return Err(Failed::Io);
+        }
+        say!("Wrote {}", file);
+    }
+    match (repository, ignore_line) {
+        (Some(repository), Some(line)) => match init::ignore(&repository, &line) {
+            Ok(true) => say!("Added {} to .gitignore", line),
+            Ok(false) => say!(".gitignore already has {}", line),
+            Err(err) => {
+                say!("{}", err);
+                return Err(Failed::Io);
+            }
+        },
+        (Some(_), None) if output_dir.is_some() => {
+            say!("Pages go to a git repository, --gitignore keeps them out of it")
+        }
+        _ => {}
+    }
+    Ok(())
+}
+
+// whether every linked stylesheet can be loaded, for doctor
+fn stylesheets_reachable(stylesheets: &[Stylesheet]) -> Result<String, String> {
+    if stylesheets.is_empty() {
+        return Ok("--css none, pages have no stylesheet".to_string());
+    }
+    let mut found = Vec::new();
+    for stylesheet in stylesheets {
+        found.push(match stylesheet {
+            Stylesheet::Linked(url) => doctor::stylesheet(url, false)?,
+            Stylesheet::Inline(_) => "a local stylesheet is put into the page".to_string(),
+        });
+    }
+    found.dedup();
+    Ok(found.join("; "))
+}
+
+// doctor: every check, for pasting into a bug report. false when pages
+// cannot be written at all
+fn check_environment(options: &Options, settings: &[config::Setting]) -> bool {
+    let directory = match &options.output_dir {
+        Some(directory) => PathBuf::from(directory),
+        None => options
+            .output
+            .parent()
+            .filter(|parent| !parent.as_os_str().is_empty())
+            .unwrap_or(Path::new("."))
+            .to_path_buf(),
+    };
+    let opener = options
+        .open_with
+        .clone()
+        .unwrap_or_else(opener::system_program);
+    let checks = [
+        doctor::Check::new(
+            "version",
+            false,
+            Ok(format!("yamc {}", env!("CARGO_PKG_VERSION"))),
+            "",
+        ),
+        doctor::Check::new(
+            "settings",
+            false,
+            Ok(match settings.len() {
+                0 => "no yamc.toml or YAMC_* variables, defaults apply".to_string(),
+                count => format!(
+                    "{} options from {}",
+                    count,
+                    settings
+                        .iter()
+                        .map(|setting| setting.source.as_str())
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                ),
+            }),
+            "",
+        ),
+        doctor::Check::new(
+            "output",
+            true,
+            if is_stdout(&options.output) {
+                Ok("pages go to standard output".to_string())
+            } else {
+                doctor::writable(&directory)
+            },
+            "choose a writable --output or --output-dir",
+        ),
+        doctor::Check::new(
+            "stylesheet",
+            false,
+            if bundled_stylesheet(options) {
+                doctor::stylesheet(STYLESHEET_URL, true)
+            } else {
+                stylesheets_reachable(&options.stylesheets)
+            },
+            "pages show unstyled without it; --offline puts a stylesheet into the page",
+        ),
+        doctor::Check::new(
+            "opener",
+            false,
+            doctor::program(&opener),
+            "--open cannot show pages; name a browser with --open-with",
+        ),
+        doctor::Check::new(
+            "pager",
+            false,
+            doctor::program("less"),
+            "view --pager needs less",
+        ),
+        doctor::Check::new(
+            "clipboard",
+            false,
+            arboard::Clipboard::new()
+                .map(|_| "available".to_string())
+                .map_err(|err| err.to_string()),
+            "copy prints the HTML instead",
+        ),
+        doctor::Check::new(
+            "serve port",
+            false,
+            doctor::port(options.port),
+            "serve needs another --port",
+        ),
+    ];
+    doctor::report(&checks)
+}
+
+// stop before any conversion work when the output cannot be written or its
+// disk has no room for a page about twice the size of the inputs
+fn check_output(inputs: &[&Path], options: &Options) -> Result<(), Failed> {
+    if is_stdout(&options.output) {
+        return Ok(());
+    }
+    // even --force does not write a page over its own source
+    let output = fs::canonicalize(&options.output).ok();
+    if let Some(input) = inputs
+        .iter()
+        .find(|input| output.is_some() && fs::canonicalize(input).ok() == output)
+    {
+        say!(
+            "{}: the page would replace its input",
+            paths::display(input)
+        );
+        return Err(Failed::Usage);
+    }
+    if keeps_existing(options) {
+        say!(
+            "{} already exists, --force replaces it",
+            paths::display(&options.output)
+        );
+        return Err(Failed::Conversion);
+    }
+    // with --output-dir the page may go into directories yet to be made
+    if options.output_dir.is_some() {
+        let directory = options
+            .output
+            .parent()
+            .filter(|parent| !parent.as_os_str().is_empty());
+        if let Some(directory) = directory {
+            if let Err(err) = fs::create_dir_all(directory) {
+                say!("cannot create {}: {}", paths::display(directory), err);
+                return Err(Failed::Io);
+            }
+        }
+    }
+    let estimate = inputs
+        .iter()
+        .filter_map(|input| fs::metadata(input).ok())
+        .map(|metadata| metadata.len().saturating_mul(2))
+        .sum();
+    output::preflight(&options.output, estimate).map_err(|err| {
+        say!("{}", err);
+        Failed::Io
+    })
+}
+
+// report what came up while rendering a document, false when a degraded
+// feature is not allowed
+fn report_rendering(path: &Path, rendered: &Rendered, options: &Options) -> bool {
+    for warning in &rendered.warnings {
+        report(warning, options);
+    }
+    // with the other messages, standard output may be carrying the page
+    if let Some(events) = &rendered.events {
+        console::write(events.clone());
+    }
+    if let Some(front_matter) = &rendered.front_matter {
+        detail(
+            options,
+            format!(
+                "{}: front matter in effect\n{}",
+                paths::display(path),
+                front_matter.trim_end()
+            ),
+        );
+    }
+    detail(
+        options,
+        format!(
+            "{}: parsed in {:?}, rendered in {:?}",
+            paths::display(path),
+            rendered.timings.parse,
+            rendered.timings.render
+        ),
+    );
+    report_degradations(path, rendered, options)
+}
+
+// tell what did not survive the conversion, false when --strict or
+// --deny degradation:<feature> does not allow it
+fn report_degradations(path: &Path, rendered: &Rendered, options: &Options) -> bool {
+    if rendered.degradations.is_empty() {
+        return true;
+    }
+    let denied = rendered.degradations.iter().any(|degradation| {
+        options.strict
+            || options
+                .deny_degradations
+                .iter()
+                .any(|feature| feature == degradation.feature)
+    });
+    if denied || options.verbosity != Verbosity::Quiet {
+        say!("{}: {}", paths::display(path), rendered.degradations);
+    }
+    if denied {
+        say!(
+            "{}: degraded features are not allowed",
+            paths::display(path)
+        );
+    }
+    !denied
+}
+
+// run a --pre-hook or --post-hook for one document, false if it failed
+fn run_hook(hook: &Option<String>, input: &Path, status: &str, options: &Options) -> bool {
+    let command = match hook {
+        Some(command) => command,
+        None => return true,
+    };
+    let job = hooks::Job {
+        input,
+        output: &options.output,
+        status,
+    };
+    match hooks::run(command, &job, Duration::from_secs(options.hook_timeout)) {
+        Ok(output) => {
+            if !output.is_empty() {
+                detail(
+                    options,
+                    format!(
+                        "{}: hook {:?} printed\n{}",
+                        paths::display(input),
+                        command,
+                        output
+                    ),
+                );
+            }
+            true
+        }
+        Err(err) => {
+            say!("{}: hook {}", paths::display(input), err);
+            false
+        }
+    }
+}
+
+fn markdown_convert(path: &Path, options: &Options) -> Result<(), Failed> {
+    if !run_hook(&options.pre_hook, path, "pending", options) {
+        return Err(Failed::Conversion);
+    }
+    // the post-hook hears about a document that failed too, the failure is
+    // what the conversion reports
+    let converted = write_page(path, options);
+    let status = match converted {
+        Ok(_) => "success",
+        Err(_) => "failed",
+    };
+    let hooked = run_hook(&options.post_hook, path, status, options);
+    let rendered = converted?;
+    if !hooked {
+        return Err(Failed::Conversion);
+    }
+    if let Some(rendered) = rendered.filter(|_| options.emit_resources) {
+        emit_resources(path, &rendered, options)?;
+    }
+    Ok(())
+}
+
+// convert one document and write its page. html inputs that are copied
+// through have nothing rendered to give back
+fn write_page(path: &Path, options: &Options) -> Result<Option<Rendered>, Failed> {
+    check_input(path)?;
+    check_output(&[path], options)?;
+    // html inputs are copied through unless asked to look like the rest
+    if is_html(path) && !options.wrap_html {
+        let copied = if is_stdout(&options.output) {
+            fs::File::open(path).and_then(|mut file| io::copy(&mut file, &mut io::stdout()))
+        } else {
+            fs::copy(path, &options.output)
+        };
+        if let Err(err) = copied {
+            say!("{}", output::describe(&options.output, &err));
+            return Err(Failed::Io);
+        }
+        Ok(None)
+    } else {
+        let Document { rendered, page } = within_budget(path, options, render_page)?;
+        if !report_rendering(path, &rendered, options) {
+            return Err(Failed::Conversion);
+        }
+        write_output(options, &[Cow::Owned(page)])?;
+        if options.copy_assets && options.output_dir.is_some() && !is_stdout(&options.output) {
+            let warnings = assets::copy(
+                path,
+                &options.output,
+                input_root(path, options),
+                &rendered.references,
+            );
+            for warning in warnings {
+                report(&warning, options);
+            }
+        }
+        Ok(Some(rendered))
+    }
+}
+
+// the template a document is laid out with: the one its front matter names,
+// relative to the document, or --template
+fn page_template(path: &Path, front_matter: Option<&str>, options: &Options) -> Option<PathBuf> {
+    // a document given as text is in no directory to find its own in
+    match front_matter
+        .filter(|_| options.text.is_none())
+        .and_then(|front_matter| front_matter::value(front_matter, "template"))
+    {
+        Some(template) => Some(path.parent().unwrap_or(Path::new("")).join(template)),
+        None => options.template.clone(),
+    }
+}
+
+// the directory a document's files have to stay inside: the input directory
+// it was found in, or its own
+fn input_root<'p>(path: &'p Path, options: &'p Options) -> &'p Path {
+    options
+        .trees
+        .iter()
+        .map(PathBuf::as_path)
+        .filter(|tree| path.starts_with(tree))
+        .max_by_key(|tree| tree.components().count())
+        .or_else(|| path.parent())
+        .filter(|parent| !parent.as_os_str().is_empty())
+        .unwrap_or(Path::new("."))
+}
+
+// the external urls a page loads or links to: its stylesheets and icon,
+// `found` in the document and the scripts of its math and diagrams
+fn page_resources(
+    found: &[resources::Resource],
+    math: bool,
+    diagrams: bool,
+    options: &Options,
+) -> Vec<resources::Resource> {
+    let mut resources = Vec::new();
+    if !bundled_stylesheet(options) {
+        for stylesheet in &options.stylesheets {
+            if let Stylesheet::Linked(url) = stylesheet {
+                resources.push(resources::Resource::new(url, "stylesheet"));
+            }
+        }
+    }
+    if let Some(icon) = options
+        .favicon
+        .as_ref()
+        .filter(|icon| offline::is_remote(&icon.href))
+    {
+        resources.push(resources::Resource::new(&icon.href, "icon"));
+    }
+    resources.extend(found.iter().cloned());
+    if math {
+        for (url, kind) in math::resources() {
+            resources.push(resources::Resource::new(&url, kind));
+        }
+    }
+    if diagrams {
+        resources.push(resources::Resource::new(mermaid::resource(), "script"));
+    }
+    resources
+}
+
+// the report on `resources`, as text or json
+fn resource_report(resources: &[resources::Resource], options: &Options) -> (String, bool) {
+    let report = resources::Report::new(resources, &options.deny_domains);
+    let text = if options.json {
+        format!("{}\n", report.to_json())
+    } else {
+        report.to_string()
+    };
+    (text, report.denied())
+}
+
+// every external url the page of a markdown document would load or link to,
+// failing when one of them is on a denied domain
+fn list_resources(path: &Path, options: &Options) -> Result<(), Failed> {
+    let arena = comrak::Arena::new();
+    let root = parse_markdown(&arena, path, options)?;
+    let resources = page_resources(
+        &resources::collect(root),
+        options.syntax.math && math::found(root),
+        options.mermaid && mermaid::found(root),
+        options,
+    );
+    let (text, denied) = resource_report(&resources, options);
+    print!("{}", text);
+    if denied {
+        return Err(Failed::Conversion);
+    }
+    Ok(())
+}
+
+// --emit-resources: the report on the page just written, from what rendering
+// it found. it goes to standard error when standard output carries the page
+// or the --json outcome
+fn emit_resources(path: &Path, rendered: &Rendered, options: &Options) -> Result<(), Failed> {
+    let resources = page_resources(
+        &rendered.resources,
+        rendered.math,
+        rendered.diagrams,
+        options,
+    );
+    let (text, denied) = resource_report(&resources, options);
+    if is_stdout(&options.output) || options.json {
+        console::write(text);
+    } else {
+        print!("{}", text);
+    }
+    if denied {
+        say!(
+            "{}: the page refers to a denied domain",
+            paths::display(path)
+        );
+        return Err(Failed::Conversion);
+    }
+    Ok(())
+}
+
+// where private notes are, so they can be reviewed before publishing
+fn list_private(path: &Path, options: &Options) -> Result<(), Failed> {
+    let markdown = read_input(path, options)?;
+    let regions = private::regions(&markdown, path).map_err(|err| {
+        report(&err, options);
+        Failed::Conversion
+    })?;
+    for region in regions {
+        println!(
+            "{}:{}-{}",
+            paths::display(path),
+            region.start_line,
+            region.end_line
+        );
+    }
+    Ok(())
+}
+
+// show the written page with --open. not being able to is only worth a
+// warning, the conversion itself succeeded
+fn open_output(page: &Path, options: &Options) {
+    if !options.open {
+        return;
+    }
+    let opened = opener::open(page, options.open_with.as_deref());
+    if let Err(err) = opened {
+        if options.verbosity != Verbosity::Quiet {
+            say!("Warning : cannot open {}: {}", paths::display(page), err);
+        }
+    }
+}
+
+// put a document on the clipboard as html with a plain text alternative,
+// printing the html when there is no clipboard to put it on
+fn copy_to_clipboard(path: &Path, options: &Options) -> Result<(), Failed> {
+    let rendered = within_budget(path, options, render_input)?;
+    if !report_rendering(path, &rendered, options) {
+        return Err(Failed::Conversion);
+    }
+    let html = html::inline_styles(&rendered.html);
+    let text = html::to_text(&rendered.html);
+    let copied =
+        arboard::Clipboard::new().and_then(|mut clipboard| clipboard.set_html(&html, Some(&text)));
+    match copied {
+        Ok(()) if options.verbosity == Verbosity::Quiet => {}
+        Ok(()) => say!(
+            "Copied {} ({} bytes of HTML) to the clipboard",
+            paths::display(path),
+            html.len()
+        ),
+        Err(err) => {
+            if options.verbosity != Verbosity::Quiet {
+                say!(
+                    "Warning : no clipboard available ({}), printing the HTML instead",
+                    err
+                );
+            }
+            println!("{}", html);
+        }
+    }
+    Ok(())
+}
+
+// a quick look at a document in the terminal, as plain text when the output
+// is not a terminal
+fn view_document(path: &Path, options: &Options) -> Result<(), Failed> {
+    let text = within_budget(path, options, |path, options| {
+        if is_html(path) {
+            return Ok(html::to_text(&render_input(path, options)?.html));
+        }
+        let arena = comrak::Arena::new();
+        let root = parse_markdown(&arena, path, options)?;
+        if !options.spec_strict {
+            headings::take_attributes(root);
+        }
+        finish_markdown(&arena, root, options)?;
+        Ok(match terminal::Terminal::detect(options.pager) {
+            Some(terminal) => terminal::render(root, &terminal),
+            None => html::to_text(&format_html(root, options)),
+        })
+    })?;
+    if options.pager {
+        page(&text);
+    } else {
+        print!("{}", text);
+    }
+    Ok(())
+}
+
+// show text through `less -R`, printing it when less cannot be started
+fn page(text: &str) {
+    let pager = process::Command::new("less")
+        .arg("-R")
+        .stdin(process::Stdio::piped())
+        .spawn();
+    match pager {
+        Ok(mut less) => {
+            if let Some(mut input) = less.stdin.take() {
+                // the reader may quit before reading everything
+                let _ = input.write_all(text.as_bytes());
+            }
+            let _ = less.wait();
+        }
+        Err(_) => print!("{}", text),
+    }
+}
+
+// check written documents against their content hash stamps
+fn verify_hashes(paths: &[PathBuf]) -> Result<(), Failed> {
+    let mut failed = false;
+    for path in paths {
+        let verified = fs::read_to_string(path)
+            .map_err(|err| err.to_string())
+            .and_then(|document| stamp::verify(&document));
+        match verified {
+            Ok(hash) => println!("{}: ok, sha256 {}", paths::display(path), hash),
+            Err(err) => {
+                say!("{}: {}", paths::display(path), err);
+                failed = true;
+            }
+        }
+    }
+    if failed {
+        return Err(Failed::Conversion);
+    }
+    Ok(())
+}
+
+// the placeholders of the template at `path`, or what is wrong with it
+// said and `failure`
+fn check_template(path: &Path, failure: Failed) -> Result<Vec<template::Placeholder>, Failed> {
+    let source = template::load(path).map_err(|err| {
+        say!("{}", err);
+        Failed::Io
+    })?;
+    template::check(&paths::display(path), &source).map_err(|problems| {
+        for problem in problems {
+            say!("Template error : {}", problem);
+        }
+        failure
+    })
+}
+
+// lay out `sample`, or lorem ipsum, with the template at `path` and write
+// the page to `output`, replacing the last preview
+fn preview_template(
+    path: &Path,
+    sample: Option<&Path>,
+    output: PathBuf,
+    open: bool,
+) -> Result<(), Failed> {
+    check_template(path, Failed::Conversion)?;
+    let argv = [OsString::from("yamc"), OsString::from("convert")];
+    let matches = command_line()
+        .try_get_matches_from(&argv)
+        .map_err(refused)?;
+    let convert = matches.subcommand().expect("the command was given").1;
+    let arguments = Arguments::from_arg_matches(convert).map_err(refused)?;
+    let (_, mut options) = parse_options("convert", arguments, convert, &argv)?;
+    options.output = output;
+    options.open = open;
+    if open && is_stdout(&options.output) {
+        say!("--open needs an output file, not standard output");
+        return Err(Failed::Usage);
+    }
+    let page = match sample {
+        Some(sample) => {
+            let rendered = render_input(sample, &options)?;
+            if !report_rendering(sample, &rendered, &options) {
+                return Err(Failed::Conversion);
+            }
+            page_parts(
+                &options,
+                &rendered.title,
+                rendered.summary.as_deref(),
+                &rendered.toc,
+                rendered.front_matter.as_deref(),
+                Some(path),
+                &[
+                    &rendered.html,
+                    &assets(rendered.enhanced_tables, rendered.math, rendered.diagrams),
+                ],
+            )?
+            .concat()
+        }
+        None => page_parts(
+            &options,
+            template::SAMPLE_TITLE,
+            None,
+            &toc::contents(&toc::entries(template::SAMPLE, &[]), options.toc_depth),
+            Some(template::SAMPLE_FRONT_MATTER),
+            Some(path),
+            &[template::SAMPLE],
+        )?
+        .concat(),
+    };
+    write_output(&options, &[Cow::Owned(page)])?;
+    if !is_stdout(&options.output) {
+        say!("Wrote {}", paths::display(&options.output));
+    }
+    open_output(&options.output, &options);
+    Ok(())
+}
+
+// which blocks a source line ended up in and the html they render to
+fn explain_line(path: &Path, options: &Options) -> Result<(), Failed> {
+    let line = match options.line {
+        Some(line) => line,
+        None => {
+            say!("explain needs --line N");
+            return Err(Failed::Usage);
+        }
+    };
+    let arena = comrak::Arena::new();
+    let root = parse_markdown(&arena, path, options)?;
+    if !options.spec_strict {
+        headings::take_attributes(root);
+    }
+    finish_markdown(&arena, root, options)?;
+    let blocks = explain::covering(root, line);
+    let innermost = match blocks.last() {
+        Some(innermost) => *innermost,
+        None => {
+            say!("No block covers line {} of {}", line, paths::display(path));
+            return Err(Failed::Conversion);
+        }
+    };
+    for (depth, block) in blocks.iter().enumerate() {
+        println!("{}{}", "  ".repeat(depth), explain::describe_node(block));
+    }
+    println!();
+    println!("{}", format_html(innermost, options).trim_end());
+    Ok(())
+}
+
+// the page an input converted along with others is written to: next to it,
+// or in --output-dir, with the extension of the html flavor. files of an
+// input directory keep their place below it
+fn page_path(input: &Path, options: &Options) -> PathBuf {
+    let extension = match options.html_flavor {
+        html::Flavor::Xhtml => "xhtml",
+        _ => "html",
+    };
+    match &options.output_dir {
+        Some(directory) => {
+            let name = input.with_extension(extension);
+            // below the directory it was found in, or on its own
+            let inside = options
+                .trees
+                .iter()
+                .filter_map(|tree| name.strip_prefix(tree).ok())
+                .min_by_key(|inside| inside.components().count());
+            let name = inside.unwrap_or(Path::new(name.file_name().unwrap_or_default()));
+            directory.join(name)
+        }
+        None => input.with_extension(extension),
+    }
+}
+
+// `page_path`, refused for standard input and for a page that would replace
+// its input
+fn page_of(input: &Path, options: &Options) -> Result<PathBuf, Failed> {
+    if is_stdin(input) {
+        say!("Standard input needs an --output of its own, convert it separately");
+        return Err(Failed::Usage);
+    }
+    let page = page_path(input, options);
+    if page == input {
+        say!(
+            "{}: the page would replace its input",
+            paths::display(input)
+        );
+        return Err(Failed::Usage);
+    }
+    Ok(page)
+}
+
+// the inputs of a batch that would write the same page, with the page. with
+// --case-insensitive-outputs, or on a file system that does not tell case
+// apart, names that differ only in case are the same page
+fn collisions<'i>(inputs: &'i [PathBuf], options: &Options) -> Vec<(PathBuf, Vec<&'i Path>)> {
+    let mut folds: HashMap<PathBuf, bool> = HashMap::new();
+    let mut pages: Vec<(String, PathBuf, Vec<&Path>)> = Vec::new();
+    for input in inputs.iter().filter(|input| !is_stdin(input)) {
+        let page = paths::normal(&page_path(input, options));
+        let directory = page.parent().unwrap_or(Path::new(".")).to_path_buf();
+        let fold = options.case_insensitive_outputs
+            || *folds
+                .entry(directory)
+                .or_insert_with_key(|directory| paths::case_insensitive(directory));
+        let key = if fold {
+            page.to_string_lossy().to_lowercase()
+        } else {
+            page.to_string_lossy().into_owned()
+        };
+        match pages.iter_mut().find(|(each, _, _)| *each == key) {
+            Some((_, _, sources)) => sources.push(input),
+            None => pages.push((key, page, vec![input])),
+        }
+    }
+    pages
+        .into_iter()
+        .filter(|(_, _, sources)| sources.len() > 1)
+        .map(|(_, page, sources)| (page, sources))
+        .collect()
+}
+
+// refuse a batch in which one page would be written over another, before
+// anything is converted
+fn check_collisions(inputs: &[PathBuf], options: &Options) -> Result<(), Failed> {
+    let collisions = collisions(inputs, options);
+    if collisions.is_empty() {
+        return Ok(());
+    }
+    let mut message = String::from("Inputs would be written to the same page:");
+    for (page, sources) in &collisions {
+        let sources: Vec<String> = sources
+            .iter()
+            .map(|source| paths::display(source))
+            .collect();
+        message.push_str(&format!(
+            "\n  {} -> {}",
+            sources.join(" and "),
+            paths::display(page)
+        ));
+    }
+    say!("{}", message);
+    Err(Failed::Usage)
+}
+
+// print every input with the page it would go to, checking that the input can
+// be read and that no two inputs go to the same page. the first problem
+// found is the result. with --json the mapping is printed as one object
+fn dry_run(inputs: &[PathBuf], options: &Options) -> Result<(), Failed> {
+    let mut ready = Ok(());
+    let mut mapping = Vec::new();
+    for input in inputs {
+        let readable = if is_stdin(input) {
+            Ok(())
+        } else {
+            fs::File::open(input).map(|_| ())
+        };
+        if let Err(err) = &readable {
+            say!("Cannot read {} : {}", paths::display(input), err);
+            ready = ready.and(Err(Failed::Io));
+            if !options.json {
+                continue;
+            }
+        }
+        let output = if options.each {
+            match page_of(input, options) {
+                Ok(page) => page,
+                Err(failed) => {
+                    ready = ready.and(Err(failed));
+                    continue;
+                }
+            }
+        } else {
+            options.output.clone()
+        };
+        let exists = !is_stdout(&output) && output.exists();
+        // a page of its own is skipped, the only one is an error
+        if exists && !options.force && !options.each {
+            ready = ready.and(Err(Failed::Conversion));
+        }
+        if options.json {
+            let mut entry = serde_json::json!({
+                "input": paths::display(input),
+                "output": if is_stdout(&output) { "-".to_string() } else { paths::display(&output) },
+                "exists": exists,
+            });
+            if let Err(err) = &readable {
+                entry["error"] =
+                    serde_json::json!({ "kind": Failed::Io.kind(), "message": err.to_string() });
+            }
+            mapping.push(entry);
+            continue;
+        }
+        let destination = if is_stdout(&output) {
+            "standard output".to_string()
+        } else if exists && !options.force {
+            format!("{} (exists, kept without --force)", paths::display(&output))
+        } else if exists {
+            format!("{} (exists, would be replaced)", paths::display(&output))
+        } else {
+            format!("{} (new)", paths::display(&output))
+        };
+        println!("{} -> {}", paths::display(input), destination);
+    }
+    let collisions = if options.each {
+        collisions(inputs, options)
+    } else {
+        Vec::new()
+    };
+    if options.json {
+        let collisions: Vec<serde_json::Value> = collisions
+            .iter()
+            .map(|(page, sources)| {
+                let sources: Vec<String> = sources
+                    .iter()
+                    .map(|source| paths::display(source))
+                    .collect();
+                serde_json::json!({ "output": paths::display(page), "inputs": sources })
+            })
+            .collect();
+        println!(
+            "{}",
+            serde_json::json!({ "dry_run": true, "mapping": mapping, "collisions": collisions })
+        );
+    }
+    if options.each {
+        ready = ready.and(check_collisions(inputs, options));
+    }
+    ready
+}
+
+// `outcome` for a single input, with the bytes of its name in base64 when
+// they are not valid unicode
+fn input_outcome(
+    input: &Path,
+    options: &Options,
+    started: Instant,
+    result: Result<(), Failed>,
+    said: Option<String>,
+) -> serde_json::Value {
+    let mut outcome = outcome(paths::display(input).into(), options, started, result, said);
+    if let Some(raw) = paths::base64(input) {
+        outcome["input_base64"] = raw.into();
+    }
+    outcome
+}
+
+// a document of a convert run as --json reports it. `said` is the last
+// message about it, which tells why it failed
+fn outcome(
+    input: serde_json::Value,
+    options: &Options,
+    started: Instant,
+    result: Result<(), Failed>,
+    said: Option<String>,
+) -> serde_json::Value {
+    let format = match options.html_flavor {
+        html::Flavor::Xhtml => "xhtml",
+        _ => "html",
+    };
+    let mut outcome = serde_json::json!({
+        "input": input,
+        "output": paths::display(&options.output),
+        "format": format,
+        "duration_ms": started.elapsed().as_millis() as u64,
+    });
+    if let Some(raw) = paths::base64(&options.output) {
+        outcome["output_base64"] = raw.into();
+    }
+    match result {
+        Ok(()) => {
+            outcome["status"] = "converted".into();
+            outcome["bytes"] = fs::metadata(&options.output)
+                .map(|metadata| metadata.len())
+                .ok()
+                .into();
+        }
+        Err(failed) => {
+            outcome["status"] = "failed".into();
+            outcome["error"] = serde_json::json!({ "kind": failed.kind(), "message": said });
+        }
+    }
+    outcome
+}
+
+// --incremental: the record of what earlier runs made their pages from, in
+// --output-dir or the current directory. its pages are replaced without
+// --force, they are yamc's own
+fn page_cache(options: &mut Options) -> Option<incremental::Cache> {
+    if !options.incremental {
+        return None;
+    }
+    let directory = options.output_dir.as_deref().unwrap_or(Path::new("."));
+    let cache = incremental::Cache::load(directory);
+    options.written.extend(cache.pages().map(Path::to_path_buf));
+    Some(cache)
+}
+
+// whether the page of `input` was made from the same input, options and
+// yamc as it would be now. --force converts everything again
+fn up_to_date(input: &Path, options: &Options, cache: Option<&incremental::Cache>) -> bool {
+    match cache {
+        Some(cache) if !options.force => incremental::key(input, &options.fingerprint)
+            .is_some_and(|key| cache.fresh(&options.output, &key)),
+        _ => false,
+    }
+}
+
+// note in the cache what `input`'s page was made from
+fn record_page(cache: &mut Option<incremental::Cache>, input: &Path, options: &Options) {
+    if let Some(cache) = cache {
+        if let Some(key) = incremental::key(input, &options.fingerprint) {
+            cache.record(&options.output, key);
+        }
+    }
+}
+
+fn save_cache(cache: Option<incremental::Cache>, options: &Options) {
+    if let Some(Err(err)) = cache.map(|cache| cache.save()) {
+        if options.verbosity != Verbosity::Quiet {
+            say!("Warning : {}, the next run converts everything", err);
+        }
+    }
+}
+
+// what became of an input converted to a page of its own
+enum Step {
+    Converted,
+    Skipped,
+    Failed(Failed),
+}
+
+// convert `input` to its page with options of its own, so that several can
+// run at once. the page it went to and what --json says about it come along
+fn convert_one(
+    input: &Path,
+    options: &Options,
+    cache: Option<&incremental::Cache>,
+) -> (Step, PathBuf, serde_json::Value) {
+    let started = Instant::now();
+    let mut options = options.clone();
+    match console::noting(|| page_of(input, &options)) {
+        (Ok(page), _) => options.output = page,
+        (Err(failure), said) => {
+            let mut failed = input_outcome(input, &options, started, Err(failure), said);
+            failed["output"] = serde_json::Value::Null;
+            return (Step::Failed(failure), PathBuf::new(), failed);
+        }
+    }
+    if up_to_date(input, &options, cache) {
+        detail(
+            &options,
+            format!("{} is up to date", paths::display(&options.output)),
+        );
+        let mut skipped = input_outcome(input, &options, started, Ok(()), None);
+        skipped["status"] = "skipped".into();
+        skipped["reason"] = "up to date".into();
+        return (Step::Skipped, options.output, skipped);
+    }
+    if let (Err(failure), said) = console::noting(|| check_input(input)) {
+        let failed = input_outcome(input, &options, started, Err(failure), said);
+        return (Step::Failed(failure), options.output, failed);
+    }
+    if keeps_existing(&options) {
+        if options.verbosity != Verbosity::Quiet {
+            say!(
+                "Warning : {} already exists, skipping {} (--force replaces it)",
+                paths::display(&options.output),
+                paths::display(input)
+            );
+        }
+        let mut skipped = input_outcome(input, &options, started, Ok(()), None);
+        skipped["status"] = "skipped".into();
+        skipped["reason"] = "exists".into();
+        skipped["bytes"] = serde_json::Value::Null;
+        return (Step::Skipped, options.output, skipped);
+    }
+    let (converted, said) = console::noting(|| markdown_convert(input, &options));
+    let done = input_outcome(input, &options, started, converted, said);
+    let step = match converted {
+        Ok(()) => Step::Converted,
+        Err(failure) => Step::Failed(failure),
+    };
+    (step, options.output, done)
+}
+
+// convert every input to a page of its own, --jobs at a time, going on past
+// the ones that fail and naming them at the end. each document's messages
+// come out together once it is done. the run fails the way the first failed
+// input did
+fn convert_each(inputs: &[PathBuf], options: &mut Options) -> Result<(), Failed> {
+    check_collisions(inputs, options)?;
+    let run = Instant::now();
+    let progress = progress::Progress::new(
+        inputs.len(),
+        options.verbosity == Verbosity::Quiet || options.json,
+    );
+    let mut cache = page_cache(options);
+    let next = AtomicUsize::new(0);
+    let finished = Mutex::new(Vec::new());
+    let shared: &Options = options;
+    let cached = cache.as_ref();
+    thread::scope(|scope| {
+        for _ in 0..shared.jobs.min(inputs.len()) {
+            scope.spawn(|| {
+                while let Some(input) = inputs.get(next.fetch_add(1, Ordering::Relaxed)) {
+                    progress.start(&paths::display(input));
+                    let (done, messages) = console::hold(|| convert_one(input, shared, cached));
+                    progress.done(&paths::display(input), &messages);
+                    finished
+                        .lock()
+                        .expect("the result lock is never poisoned")
+                        .push((input, done));
+                }
+            });
+        }
+    });
+    progress.finish();
+    let mut finished = finished
+        .into_inner()
+        .expect("the result lock is never poisoned");
+    // in the order of the inputs, whatever order they finished in
+    finished.sort_by_key(|(input, _)| inputs.iter().position(|each| each == *input));
+    let mut failed = Vec::new();
+    let mut first = None;
+    let mut kept = 0;
+    let mut outcomes = Vec::new();
+    let mut opened = None;
+    for (input, (step, output, outcome)) in finished {
+        outcomes.push(outcome);
+        match step {
+            Step::Converted => {
+                options.output = output;
+                options.written.push(options.output.clone());
+                record_page(&mut cache, input, options);
+                opened = opened.or(Some(options.output.clone()));
+            }
+            Step::Skipped => kept += 1,
+            Step::Failed(failure) => {
+                failed.push(paths::display(input));
+                first = first.or(Some(failure));
+            }
+        }
+    }
+    save_cache(cache, options);
+    let converted = inputs.len() - failed.len() - kept;
+    // one window for the batch, not one for every page
+    if let Some(page) = opened.filter(|_| options.open) {
+        if converted > 1 && options.verbosity != Verbosity::Quiet && !options.json {
+            say!(
+                "--open opens only the first of the {} pages converted, {}",
+                converted,
+                paths::display(&page)
+            );
+        }
+        open_output(&page, options);
+    }
+    if options.json {
+        let report = serde_json::json!({
+            "documents": outcomes,
+            "summary": {
+                "converted": converted,
+                "skipped": options.skipped + kept,
+                "failed": failed.len(),
+                "assets_copied": assets::copied_count(),
+                "duration_ms": run.elapsed().as_millis() as u64,
+            },
+        });
+        println!("{}", report);
+        return first.map_or(Ok(()), Err);
+    }
+    if !failed.is_empty() {
+        say!(
+            "{} of {} documents failed: {}",
+            failed.len(),
+            inputs.len(),
+            failed.join(", ")
+        );
+    }
+    if options.verbosity != Verbosity::Quiet && (!options.trees.is_empty() || kept > 0) {
+        say!(
+            "{} converted, {} skipped, {} failed, {} assets copied",
+            converted,
+            options.skipped + kept,
+            failed.len(),
+            assets::copied_count()
+        );
+    }
+    first.map_or(Ok(()), Err)
+}
+
+// convert the inputs into one page, a page each or the binder, as the options
+// say. with --json the outcome is printed on standard output
+fn convert_all(inputs: &[PathBuf], options: &mut Options) -> Result<(), Failed> {
+    if !options.binder && (options.each || inputs.len() != 1) {
+        return convert_each(inputs, options);
+    }
+    let started = Instant::now();
+    // a binder is always put together again
+    let mut cache = page_cache(options).filter(|_| !options.binder);
+    let fresh = up_to_date(&inputs[0], options, cache.as_ref());
+    let (converted, said) = console::noting(|| {
+        if fresh {
+            if options.verbosity != Verbosity::Quiet && !options.json {
+                say!(
+                    "{} is up to date, --force converts it again",
+                    paths::display(&options.output)
+                );
+            }
+            Ok(())
+        } else if options.binder {
+            binder_convert(inputs, options)
+        } else {
+            markdown_convert(&inputs[0], options)
+        }
+    });
+    if options.json {
+        let mut outcome = if options.binder {
+            let input: Vec<String> = inputs.iter().map(|input| paths::display(input)).collect();
+            outcome(input.into(), options, started, converted, said)
+        } else {
+            input_outcome(&inputs[0], options, started, converted, said)
+        };
+        if fresh {
+            outcome["status"] = "skipped".into();
+            outcome["reason"] = "up to date".into();
+        }
+        println!("{}", outcome);
+    }
+    converted?;
+    if fresh {
+        return Ok(());
+    }
+    record_page(&mut cache, &inputs[0], options);
+    save_cache(cache, options);
+    options.written.push(options.output.clone());
+    open_output(&options.output, options);
+    Ok(())
+}
+
+// the inputs as they are now: input directories may have new files
+fn current_inputs(inputs: &[PathBuf], options: &Options) -> Vec<PathBuf> {
+    let in_tree = |input: &Path| options.trees.iter().any(|tree| input.starts_with(tree));
+    let mut current: Vec<PathBuf> = inputs
+        .iter()
+        .filter(|input| !in_tree(input))
+        .cloned()
+        .collect();
+    for tree in &options.trees {
+        match paths::markdown_files(tree, options.hidden, &options.excludes) {
+            Ok(listing) => current.extend(listing.files),
+            Err(err) => say!("{}", err),
+        }
+    }
+    current
+}
+
+// --watch: convert, then again whenever an input, a markdown file next to
+// one (such as an include) or a markdown file in an input directory changes.
+// a failed conversion is reported and the next change tries again
+fn watch_inputs(inputs: &[PathBuf], options: &mut Options) -> Result<(), Failed> {
+    if inputs.iter().any(|input| is_stdin(input)) {
+        say!("--watch cannot watch standard input");
+        return Err(Failed::Usage);
+    }
+    let _ = convert_all(inputs, options);
+    // the page is already open, a browser reloads it on its own
+    options.open = false;
+    let absolute = |path: &Path| fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf());
+    let mut directories: Vec<(PathBuf, bool)> = Vec::new();
+    for input in inputs {
+        let parent = input
+            .parent()
+            .filter(|parent| !parent.as_os_str().is_empty())
+            .unwrap_or(Path::new("."));
+        let parent = (absolute(parent), false);
+        if !directories.contains(&parent) {
+            directories.push(parent);
+        }
+    }
+    for tree in &options.trees {
+        directories.push((absolute(tree), true));
+    }
+    let watched: Vec<PathBuf> = inputs.iter().map(|input| absolute(input)).collect();
+    if options.verbosity != Verbosity::Quiet {
+        say!("Watching for changes, ctrl-c to stop");
+    }
+    let relevant =
+        |path: &Path| watched.iter().any(|input| input == path) || paths::is_markdown(path);
+    let watching = watch::run(&directories, relevant, || {
+        let current = current_inputs(inputs, options);
+        let names: Vec<String> = current.iter().map(|input| paths::display(input)).collect();
+        detail(options, format!("converting {} again", names.join(", ")));
+        let _ = convert_all(&current, options);
+    });
+    watching.map_err(|err| {
+        say!("{}", err);
+        Failed::Io
+    })
+}
+
+// the styles and scripts that go after the body for what it uses
+fn assets(enhanced_tables: bool, math: bool, diagrams: bool) -> String {
+    let mut assets = String::new();
+    if enhanced_tables {
+        assets.push_str(tables::ASSETS);
+    }
+    if math {
+        assets.push_str(math::ASSETS);
+    }
+    if diagrams {
+        assets.push_str(mermaid::ASSETS);
+    }
+    assets
+}
+
+// the page for `path` as served: the whole page in memory, with the script
+// that reloads it
+fn served_page(path: &Path, options: &Options) -> Result<String, Failed> {
+    let Document { rendered, mut page } = within_budget(path, options, render_page)?;
+    if !report_rendering(path, &rendered, options) {
+        return Err(Failed::Conversion);
+    }
+    page.push_str(serve::RELOAD);
+    Ok(page)
+}
+
+// serve: the page at http://127.0.0.1:PORT/ and the files next to the input
+// below it, so relative images load. the page is rendered again whenever
+// the input or a markdown file next to it changes; a failed rendering is
+// reported and the last good page stays up
+fn serve_input(inputs: &[PathBuf], options: &Options) -> Result<(), Failed> {
+    let path = match inputs {
+        [path] if !is_stdin(path) => path,
+        [_] => {
+            say!("serve cannot watch standard input");
+            return Err(Failed::Usage);
+        }
+        _ => {
+            say!("serve takes one document, not {}", inputs.len());
+            return Err(Failed::Usage);
+        }
+    };
+    let html = served_page(path, options).unwrap_or_else(|_| {
+        format!(
+            "<p>yamc could not convert {}, see the terminal.</p>{}",
+            html::escape(&paths::display(path)),
+            serve::RELOAD
+        )
+    });
+    let page = Arc::new(serve::Page::new(html));
+    let directory = path
+        .parent()
+        .filter(|parent| !parent.as_os_str().is_empty())
+        .unwrap_or(Path::new("."));
+    let directory = fs::canonicalize(directory).unwrap_or_else(|_| directory.to_path_buf());
+    let address =
+        serve::start(options.port, Arc::clone(&page), directory.clone()).map_err(|err| {
+            say!("Cannot listen on 127.0.0.1:{} : {}", options.port, err);
+            Failed::Io
+        })?;
+    let url = format!("http://{}/", address);
+    say!(
+        "Serving {} at {}, ctrl-c to stop",
+        paths::display(path),
+        url
+    );
+    if options.open {
+        let opened = opener::open(Path::new(&url), options.open_with.as_deref());
+        if let Err(err) = opened {
+            if options.verbosity != Verbosity::Quiet {
+                say!("Warning : cannot open {}: {}", url, err);
+            }
+        }
+    }
+    let input = fs::canonicalize(path).unwrap_or_else(|_| PathBuf::from(path));
+    let relevant = |changed: &Path| changed == input || paths::is_markdown(changed);
+    let watching = watch::run(&[(directory, false)], relevant, || {
+        detail(options, format!("rendering {} again", paths::display(path)));
+        if let Ok(html) = served_page(path, options) {
+            page.replace(html);
+        }
+    });
+    watching.map_err(|err| {
+        say!("{}", err);
+        Failed::Io
+    })
+}
+
+// render every input and print them as one document with cover sheets
+fn binder_convert(paths: &[PathBuf], options: &Options) -> Result<(), Failed> {
+    check_output(
+        &paths.iter().map(PathBuf::as_path).collect::<Vec<_>>(),
+        options,
+    )?;
+    let mut enhanced_tables = false;
+    let mut math = false;
+    let mut diagrams = false;
+    // a document whose pre-hook fails is left out, the rest still go in
+    let mut failed = false;
+    let mut degraded = false;
+    let exhibits: Vec<binder::Exhibit> = paths
+        .iter()
+        .filter(|path| {
+            let ready = run_hook(&options.pre_hook, path, "pending", options);
+            failed |= !ready;
+            ready
+        })
+        .map(|path| {
+            let rendered = within_budget(path, options, render_input)?;
+            degraded |= !report_rendering(path, &rendered, options);
+            enhanced_tables |= rendered.enhanced_tables;
+            math |= rendered.math;
+            diagrams |= rendered.diagrams;
+            Ok(binder::Exhibit {
+                path: path.clone(),
+                html: rendered.html,
+                front_matter: rendered.front_matter,
+                summary: rendered.summary,
+            })
+        })
+        .collect::<Result<_, Failed>>()?;
+    if degraded {
+        return Err(Failed::Conversion);
+    }
+    let html = binder::assemble(&exhibits, &options.exhibit_format);
+    let title = options.title.as_deref().unwrap_or("Binder");
+    let assets = assets(enhanced_tables, math, diagrams);
+    let parts = page_parts(
+        options,
+        title,
+        None,
+        "",
+        None,
+        options.template.as_deref(),
+        &[&html, &assets],
+    )?;
+    write_output(options, &parts)?;
+    for exhibit in &exhibits {
+        failed |= !run_hook(&options.post_hook, &exhibit.path, "success", options);
+    }
+    if failed {
+        return Err(Failed::Conversion);
+    }
+    Ok(())
+}
+
+// a byte count with an optional K, M or G suffix
+fn parse_size(value: &str) -> Result<u64, String> {
+    let size = || {
+        let (number, unit) = match value.char_indices().last()? {
+            (i, 'K') | (i, 'k') => (&value[..i], 1024),
+            (i, 'M') | (i, 'm') => (&value[..i], 1024 * 1024),
+            (i, 'G') | (i, 'g') => (&value[..i], 1024 * 1024 * 1024),
+            _ => (value, 1),
+        };
+        number.parse::<u64>().ok()?.checked_mul(unit)
+    };
+    size().ok_or_else(|| "expected a size such as 500K or 40M".to_string())
+}
+
+// an http(s) url taken as a directory, so relative urls join below it
+fn parse_base_url(value: &str) -> Result<url::Url, String> {
+    let mut url = url::Url::parse(value).map_err(|err| err.to_string())?;
+    if !matches!(url.scheme(), "http" | "https") {
+        return Err("expected an http or https url".to_string());
+    }
+    if !url.path().ends_with('/') {
+        url.set_path(&format!("{}/", url.path()));
+    }
+    Ok(url)
+}
+
+fn parse_denied(value: &str) -> Result<String, String> {
+    value
+        .strip_prefix("degradation:")
+        .map(str::to_string)
+        .ok_or_else(|| "expected degradation:<feature>, such as degradation:raw-html".to_string())
+}
+
+// the class goes into the body's class attribute and, after a `.`, into css
+// selectors, so it has to be a plain css identifier
+fn parse_css_class(value: &str) -> Result<String, String> {
+    let name = value.strip_prefix('-').unwrap_or(value);
+    if name.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_')
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+    {
+        Ok(value.to_string())
+    } else {
+        Err(
+            "expected a single class name of letters, digits, - and _, not starting with a digit"
+                .to_string(),
+        )
+    }
+}
+
+fn parse_id_prefix(value: &str) -> Result<String, String> {
+    if value.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_') {
+        Ok(value.to_string())
+    } else {
+        Err("the prefix must start with a letter or _".to_string())
+    }
+}
+
+// the html flavor the output file name asks for. an explicit --html-flavor
+// has to agree with it
+fn output_flavor(
+    output: &Path,
+    flavor: Option<html::Flavor>,
+    quiet: bool,
+) -> Result<html::Flavor, Failed> {
+    let extension = output
+        .extension()
+        .map(|extension| extension.to_string_lossy().to_ascii_lowercase());
+    let implied = match extension.as_deref() {
+        Some("html" | "htm") => html::Flavor::Html5,
+        Some("xhtml") => html::Flavor::Xhtml,
+        Some(format @ ("pdf" | "txt" | "epub" | "docx" | "md")) => {
+            say!(
+                "Cannot write {} : yamc does not produce {} output, only HTML",
+                paths::display(output),
+                format
+            );
+            return Err(Failed::Usage);
+        }
+        _ => {
+            if !quiet {
+                say!(
+                    "Notice : the format of {} is not clear from its name, writing HTML",
+                    paths::display(output)
+                );
+            }
+            return Ok(flavor.unwrap_or(html::Flavor::Html5));
+        }
+    };
+    match flavor {
+        Some(flavor) if flavor != implied => {
+            say!(
+                "--html-flavor does not match the extension of {}",
+                paths::display(output)
+            );
+            Err(Failed::Usage)
+        }
+        _ => Ok(implied),
+    }
+}
+
+#[derive(Parser)]
+#[command(
+    name = "yamc",
+    version,
+    about = "Yet Another Markdown Converter, converts markdown to static HTML",
+    after_help = exit::HELP
+)]
+struct Cli {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    #[command(
+        about = "Convert a document to HTML, or several into one with --binder",
+        after_help = exit::HELP
+    )]
+    Convert(Arguments),
+    #[command(about = "List the external urls a converted page references")]
+    Resources(Arguments),
+    #[command(about = "Put a rendered document on the clipboard")]
+    Copy(Arguments),
+    #[command(about = "Show the blocks covering a source line and the HTML they render to")]
+    Explain(Arguments),
+    #[command(about = "Preview a document in the terminal")]
+    View(Arguments),
+    #[command(about = "Serve a document over HTTP, reloading the browser when it changes")]
+    Serve(Arguments),
+    #[command(about = "Check what yamc needs from this machine and say what is missing")]
+    Doctor(Arguments),
+    #[command(about = "Start a project: write a yamc.toml with the defaults to edit")]
+    Init {
+        #[arg(long, help = "Also write example.md, a document to try yamc on")]
+        sample: bool,
+        #[arg(short, long, help = "Replace files that are already there")]
+        force: bool,
+        #[arg(long, value_name = "DIR", value_hint = ValueHint::DirPath, help = "Set output_dir, where pages are written")]
+        output_dir: Option<String>,
+        #[arg(
+            long,
+            requires = "output_dir",
+            help = "Add the output directory to the .gitignore of the repository"
+        )]
+        gitignore: bool,
+    },
+    #[command(about = "List the themes --theme can put into a page")]
+    Themes,
+    #[command(about = "Check written documents against their content hash stamps")]
+    VerifyHash {
+        #[arg(required = true, value_hint = ValueHint::FilePath, help = "Files written by yamc")]
+        files: Vec<PathBuf>,
+    },
+    #[command(about = "Check a --template or lay out a sample page with it")]
+    Template {
+        #[command(subcommand)]
+        command: TemplateCommand,
+    },
+    #[command(about = "Print a completion script for SHELL")]
+    Completions {
+        #[arg(value_enum, help = "The shell to complete in")]
+        shell: clap_complete::Shell,
+    },
+}
+
+#[derive(Subcommand)]
+enum TemplateCommand {
+    #[command(about = "List the placeholders a template uses and what yamc does not have")]
+    Check {
+        #[arg(value_hint = ValueHint::FilePath, help = "The handlebars template")]
+        file: PathBuf,
+    },
+    #[command(about = "Lay out lorem ipsum, or a document, with a template and write the page")]
+    Preview {
+        #[arg(value_hint = ValueHint::FilePath, help = "The handlebars template")]
+        file: PathBuf,
+        #[arg(long, value_name = "FILE", value_hint = ValueHint::FilePath, help = "Lay out FILE instead of lorem ipsum")]
+        sample: Option<PathBuf>,
+        #[arg(
+            short,
+            long,
+            value_name = "FILE",
+            value_hint = ValueHint::FilePath,
+            help = "Write to FILE instead of the template's name with .preview.html"
+        )]
+        output: Option<PathBuf>,
+        #[arg(long, help = "Open the page once it is written")]
+        open: bool,
+    },
+}
+
+// every command takes the same options, each uses the ones that apply to it.
+// an option given twice takes the later value, so the command line wins over
+// yamc.toml
+#[derive(Args)]
+#[group(skip)]
+#[command(args_override_self = true)]
+struct Arguments {
+    #[arg(
+        value_name = "INPUT",
+        value_hint = ValueHint::FilePath,
+        help = "Markdown or HTML files, - reads standard input"
+    )]
+    inputs: Vec<PathBuf>,
+    #[arg(
+        short,
+        long,
+        value_name = "FILE",
+        value_hint = ValueHint::FilePath,
+        help = "Write to FILE instead of output.html; .xhtml implies --html-flavor xhtml"
+    )]
+    output: Option<PathBuf>,
+    #[arg(long, value_name = "URL", default_value = STYLESHEET_URL, help = "Stylesheet the page links to")]
+    css_url: String,
+    #[arg(
+        long,
+        value_name = "URL|FILE|none",
+        value_hint = ValueHint::FilePath,
+        conflicts_with = "css_url",
+        help = "Link the stylesheet at URL, put the one in FILE into the page, or use none; repeat to layer stylesheets, later ones win"
+    )]
+    css: Vec<String>,
+    #[arg(
+        long,
+        value_name = "FILE|HTML",
+        help = "Put HTML, or the contents of FILE, at the end of the page head as it is; repeat to add more, in order"
+    )]
+    head_html: Vec<String>,
+    #[arg(
+        long,
+        value_name = "URL|FILE",
+        value_hint = ValueHint::FilePath,
+        help = "Icon of the page: a url is linked, an .ico, .png or .svg file is put into the page"
+    )]
+    favicon: Option<String>,
+    #[arg(
+        long,
+        value_name = "CODE",
+        help = "Language of the pages, such as en or ar; wins over lang in front matter"
+    )]
+    lang: Option<String>,
+    #[arg(
+        long,
+        value_parser = ["ltr", "rtl", "auto"],
+        help = "Direction of the text; rtl also mirrors the built-in styles. Wins over dir in front matter"
+    )]
+    dir: Option<String>,
+    #[arg(
+        long,
+        help = "Leave out the author and Open Graph tags made from the author, title, description and image in front matter"
+    )]
+    no_meta: bool,
+    #[arg(
+        long,
+        help = "Leave <!-- include: ... --> comments as they are instead of pulling in the files they name"
+    )]
+    no_includes: bool,
+    #[arg(
+        long,
+        value_name = "URL",
+        value_parser = parse_base_url,
+        help = "Where the pages are published; a relative image in front matter is made absolute against it"
+    )]
+    base_url: Option<url::Url>,
+    #[arg(
+        long,
+        value_name = "NAME",
+        value_parser = clap::builder::PossibleValuesParser::new(themes::names()),
+        conflicts_with = "css_url",
+        help = "Put a stylesheet bundled with yamc into the page; yamc themes lists them"
+    )]
+    theme: Option<String>,
+    #[arg(
+        long,
+        value_name = "CLASS",
+        default_value = "markdown-body",
+        value_parser = parse_css_class,
+        help = "Class of the page body the stylesheet expects"
+    )]
+    css_class: String,
+    #[arg(
+        long,
+        value_name = "FILE",
+        help = "Lay the page out with a handlebars template that places {{{content}}}"
+    )]
+    template: Option<PathBuf>,
+    #[arg(
+        long,
+        value_name = "NAME=VALUE",
+        alias = "css-vars",
+        value_parser = html::parse_css_variable,
+        help = "Set the css variable --yamc-NAME of the page, e.g. accent=#0969da"
+    )]
+    css_var: Vec<(String, String)>,
+    #[arg(
+        long,
+        help = "Add a sticky header and click-to-sort columns to tables with more rows than the threshold"
+    )]
+    enhanced_tables: bool,
+    #[arg(long, value_name = "N", default_value_t = tables::DEFAULT_THRESHOLD, help = "Row threshold for --enhanced-tables")]
+    enhanced_tables_threshold: usize,
+    #[arg(long, help = "Also add a filter input above every enhanced table")]
+    table_filter: bool,
+    #[arg(
+        long,
+        value_name = "N",
+        default_value_t = 0,
+        allow_negative_numbers = true,
+        help = "Move every heading down (or up, if negative) by N levels, clamped to h1..h6"
+    )]
+    shift_headings: i32,
+    #[arg(long, help = "Remove the first h1 from the output")]
+    strip_title: bool,
+    #[arg(
+        long,
+        value_name = "TEXT",
+        help = "Title of the page, instead of the front matter's title, the first h1 or the file name"
+    )]
+    title: Option<String>,
+    #[arg(
+        long,
+        value_name = "NAME",
+        default_value = highlight::DEFAULT_THEME,
+        value_parser = clap::builder::PossibleValuesParser::new(highlight::THEMES),
+        help = "Colours of highlighted code blocks"
+    )]
+    highlight_theme: String,
+    #[arg(
+        long,
+        conflicts_with = "highlight_theme",
+        help = "Leave fenced code blocks uncoloured"
+    )]
+    no_highlight: bool,
+    #[arg(
+        long,
+        help = "Put a table of contents at the top, or where the document has [TOC] or <!-- toc -->"
+    )]
+    toc: bool,
+    #[arg(
+        long,
+        value_name = "N",
+        default_value_t = toc::DEFAULT_DEPTH,
+        value_parser = clap::value_parser!(u8).range(1..=6),
+        help = "Levels of headings the table of contents lists"
+    )]
+    toc_depth: u8,
+    #[arg(
+        long,
+        help = "Leave out the link mark that shows next to a heading on hover"
+    )]
+    no_anchors: bool,
+    #[arg(
+        long,
+        help = "Show ```mermaid blocks as code instead of drawing the diagrams"
+    )]
+    no_mermaid: bool,
+    #[arg(
+        long,
+        overrides_with = "no_emoji",
+        help = "Replace :shortcode: with its emoji, which is the default"
+    )]
+    emoji: bool,
+    #[arg(
+        long,
+        overrides_with = "emoji",
+        help = "Keep :shortcode: as it is written"
+    )]
+    no_emoji: bool,
+    #[arg(
+        long,
+        overrides_with = "no_rewrite_links",
+        help = "Point links to other inputs' .md files at their pages, which is the default"
+    )]
+    rewrite_links: bool,
+    #[arg(
+        long,
+        overrides_with = "rewrite_links",
+        help = "Keep links to .md files as they are written"
+    )]
+    no_rewrite_links: bool,
+    #[arg(
+        long,
+        help = "Leave the images and files a page refers to where they are instead of copying them to --output-dir"
+    )]
+    no_copy_assets: bool,
+    #[arg(long, value_name = "DIALECT", value_parser = ["gfm", "commonmark", "obsidian"], default_value = "gfm", help = "Input dialect")]
+    from: String,
+    #[arg(long, value_name = "MODE", value_parser = ["off", "standard", "aggressive"], help = "standard links urls and www. addresses, aggressive also bare domains like example.com")]
+    autolink: Vec<String>,
+    #[arg(long, value_name = "PLACE", value_parser = ["headings", "tables"], help = "Keep automatically linked urls as plain text in headings or tables")]
+    no_autolink_in: Vec<String>,
+    #[arg(
+        long,
+        value_name = "FEATURE",
+        help = "Switch a single syntax feature on on top of the dialect"
+    )]
+    enable: Vec<String>,
+    #[arg(
+        long,
+        value_name = "FEATURE",
+        help = "Switch a single syntax feature off on top of the dialect"
+    )]
+    disable: Vec<String>,
+    #[arg(
+        long,
+        help = "Curly quotes, dashes for -- and --- and an ellipsis for ..., the same as --enable smart"
+    )]
+    smart: bool,
+    #[arg(
+        long,
+        help = "Keep every newline of a paragraph as a line break, the same as --enable hardbreaks"
+    )]
+    hardbreaks: bool,
+    #[arg(
+        long,
+        help = "Render $...$ and $$...$$ as math with KaTeX, the same as --enable math"
+    )]
+    math: bool,
+    #[arg(long, help = "Combine the inputs into one document with cover sheets")]
+    binder: bool,
+    #[arg(long, value_name = "FORMAT", default_value = binder::DEFAULT_EXHIBIT_FORMAT, help = "Label of binder exhibits, %d is the number")]
+    exhibit_format: String,
+    #[arg(
+        long,
+        help = "After converting, list the external urls the page references"
+    )]
+    emit_resources: bool,
+    #[arg(
+        long,
+        help = "Print reports, diagnostics and the outcome of convert as JSON"
+    )]
+    json: bool,
+    #[arg(
+        long,
+        value_name = "GLOB",
+        help = "Fail when a resource's domain matches GLOB"
+    )]
+    deny_domain: Vec<String>,
+    #[arg(
+        long,
+        value_name = "NAME",
+        help = "Allow documents to read the environment variable NAME as {{ env.NAME }}"
+    )]
+    expose_env: Vec<String>,
+    #[arg(long, value_name = "SIZE", value_parser = parse_size, help = "Refuse inputs larger than SIZE bytes (K, M and G suffixes allowed)")]
+    max_input_size: Option<u64>,
+    #[arg(long, value_name = "N", default_value_t = summary::DEFAULT_LENGTH, help = "Cut the generated page summary at N characters")]
+    summary_length: usize,
+    #[arg(
+        long,
+        help = "Render plain CommonMark: no extensions, header ids or yamc passes"
+    )]
+    spec_strict: bool,
+    #[arg(
+        long,
+        help = "Print the parsed node tree with source lines before rendering, on standard error"
+    )]
+    show_events: bool,
+    #[arg(long, value_name = "N", help = "Source line for explain")]
+    line: Option<u32>,
+    #[arg(long, value_name = "FLAVOR", value_parser = ["html5", "xhtml"], help = "Write an XHTML document and refuse to write it if it does not parse as XML")]
+    html_flavor: Option<String>,
+    #[arg(long, value_name = "ENCODING", value_parser = ["utf-8", "ascii"], help = "Charset the page declares, utf-8 by default; ascii writes every other character as a numeric reference")]
+    output_encoding: Option<String>,
+    #[arg(
+        long,
+        help = "Put .html inputs in yamc's page instead of copying them through unchanged"
+    )]
+    wrap_html: bool,
+    #[arg(
+        long,
+        help = "Keep the styles, scripts and stylesheet links of a wrapped page's head"
+    )]
+    keep_head_assets: bool,
+    #[arg(long, help = "Leave no HTML comments in the output")]
+    strip_comments: bool,
+    #[arg(
+        long,
+        help = "Minify the page: collapse whitespace outside <pre> and <textarea>, drop comments, minify styles"
+    )]
+    minify: bool,
+    #[arg(
+        long = "unsafe",
+        help = "Pass the document's raw HTML, scripts included, through as written. Only for input you trust"
+    )]
+    unsafe_html: bool,
+    #[arg(
+        long,
+        help = "Remove scripts, event handlers and unsafe urls from the page, also with --unsafe"
+    )]
+    sanitize: bool,
+    #[arg(
+        long,
+        help = "Render a quote's last line starting with — or -- as its caption in a figure"
+    )]
+    quote_citations: bool,
+    #[arg(
+        long,
+        help = "Print the line ranges of private blocks instead of converting"
+    )]
+    list_private: bool,
+    #[arg(
+        long,
+        help = "Write the id, classes and attributes of heading attribute blocks into the output"
+    )]
+    heading_attrs: bool,
+    #[arg(
+        long,
+        value_name = "CMD",
+        value_hint = ValueHint::CommandString,
+        help = "Run CMD before converting each document; a failure skips that document"
+    )]
+    pre_hook: Option<String>,
+    #[arg(
+        long,
+        value_name = "CMD",
+        value_hint = ValueHint::CommandString,
+        help = "Run CMD after each document is written; a failure makes yamc exit with status 1"
+    )]
+    post_hook: Option<String>,
+    #[arg(long, value_name = "SECS", default_value_t = hooks::DEFAULT_TIMEOUT, help = "Stop a hook that runs longer than this")]
+    hook_timeout: u64,
+    #[arg(
+        long,
+        value_name = "DIR",
+        value_hint = ValueHint::DirPath,
+        help = "Resolve root-relative links and images inside DIR, warning about missing files"
+    )]
+    asset_root: Option<String>,
+    #[arg(long, value_name = "N", default_value_t = limits::DEFAULT_MAX_DEPTH, help = "Refuse documents nested deeper than N levels")]
+    max_depth: usize,
+    #[arg(long, value_name = "N", default_value_t = limits::DEFAULT_MAX_NODES, help = "Refuse documents with more than N nodes")]
+    max_nodes: usize,
+    #[arg(
+        long,
+        value_name = "SECS",
+        help = "Fail a document that takes longer than this to convert"
+    )]
+    time_budget: Option<u64>,
+    #[arg(
+        long,
+        help = "Fail when any feature of a document is degraded in the output"
+    )]
+    strict: bool,
+    #[arg(long, value_name = "degradation:FEATURE", value_parser = parse_denied, help = "Fail when FEATURE (such as raw-html) is degraded")]
+    deny: Vec<String>,
+    #[arg(long, help = "Embed local images as data urls")]
+    embed_images: bool,
+    #[arg(long, help = "Download http(s) images and embed them as data urls")]
+    fetch_remote_images: bool,
+    #[arg(long, value_name = "SIZE", value_parser = parse_size, default_value_t = assets::DEFAULT_MAX_EMBED_SIZE, help = "Link images larger than SIZE bytes instead of embedding them (K, M and G suffixes allowed)")]
+    max_embed_size: u64,
+    #[arg(
+        long,
+        help = "Add data-source-line attributes with the source line of every top-level block"
+    )]
+    source_map: bool,
+    #[arg(
+        long,
+        value_name = "FILE",
+        value_hint = ValueHint::FilePath,
+        help = "Link the first use of every term defined in FILE and append a glossary"
+    )]
+    glossary: Option<String>,
+    #[arg(long, help = "Match glossary terms with their exact case")]
+    glossary_case_sensitive: bool,
+    #[arg(long, value_name = "MODE", value_parser = ["scroll", "wrap", "wrap-pdf-only"], default_value = "wrap-pdf-only", help = "Wrap long lines in code blocks instead of scrolling them, everywhere or only when printed")]
+    code_wrap: String,
+    #[arg(
+        long,
+        help = "Print the pages convert would write and whether they exist, without converting"
+    )]
+    dry_run: bool,
+    #[arg(
+        long,
+        help = "Take pages whose names differ only in case for the same page, as a case-insensitive file system would"
+    )]
+    case_insensitive_outputs: bool,
+    #[arg(
+        long,
+        help = "Put a stylesheet of yamc's own into the page instead of linking one, and refuse options that would load anything over the network"
+    )]
+    offline: bool,
+    #[arg(
+        long,
+        help = "Download the linked stylesheet and put it into the page, so the page looks the same offline"
+    )]
+    embed_css: bool,
+    #[arg(
+        long,
+        help = "Convert, then convert again whenever an input changes until ctrl-c"
+    )]
+    watch: bool,
+    #[arg(short, long, help = "Replace a page that already exists at the output")]
+    force: bool,
+    #[arg(
+        long,
+        help = "Skip inputs whose page was made from the same input and options, recorded in .yamc-cache.json"
+    )]
+    incremental: bool,
+    #[arg(
+        short,
+        long,
+        value_name = "N",
+        value_parser = clap::value_parser!(u16).range(1..),
+        help = "Convert up to N documents at once, as many as there are CPUs by default"
+    )]
+    jobs: Option<u16>,
+    #[arg(
+        long,
+        value_name = "PORT",
+        default_value_t = 3000,
+        help = "Port serve listens on, on 127.0.0.1; 0 picks a free one"
+    )]
+    port: u16,
+    #[arg(long, help = "Show view output through less -R")]
+    pager: bool,
+    #[arg(
+        long,
+        help = "Open the written page with the default application for it"
+    )]
+    open: bool,
+    #[arg(
+        long,
+        value_name = "PROGRAM",
+        value_hint = ValueHint::CommandName,
+        help = "Open the written page with PROGRAM, implies --open"
+    )]
+    open_with: Option<String>,
+    #[arg(long, value_name = "PREFIX", default_value = headings::DEFAULT_ID_PREFIX, value_parser = parse_id_prefix, help = "Put PREFIX before generated heading ids that would start with a digit, and PREFIX and the position for headings with no letters or digits")]
+    heading_id_prefix: String,
+    #[arg(
+        long,
+        value_name = "FILE",
+        value_hint = ValueHint::FilePath,
+        help = "Record the options of this run, with yamc and comrak versions, for a bug report"
+    )]
+    dump_effective_config: Option<String>,
+    #[arg(
+        long,
+        value_name = "FILE",
+        value_hint = ValueHint::FilePath,
+        help = "Take every option from a --dump-effective-config file instead of the command line"
+    )]
+    replay: Option<String>,
+    #[arg(
+        long,
+        help = "Let wildcards in INPUT patterns match hidden files and directories"
+    )]
+    hidden: bool,
+    #[arg(
+        long,
+        value_name = "GLOB",
+        help = "Leave files matching GLOB out of input directories, as a .yamcignore line would"
+    )]
+    exclude: Vec<String>,
+    #[arg(
+        long,
+        value_name = "FILE",
+        value_hint = ValueHint::FilePath,
+        help = "Read options from FILE instead of the yamc.toml in this or a parent directory"
+    )]
+    config: Option<String>,
+    #[arg(
+        long,
+        value_name = "DIR",
+        value_hint = ValueHint::DirPath,
+        alias = "out-dir",
+        conflicts_with = "output",
+        help = "Write pages into DIR, named after their inputs; made when missing"
+    )]
+    output_dir: Option<PathBuf>,
+    #[arg(
+        short,
+        long,
+        conflicts_with = "verbose",
+        help = "Print nothing but errors"
+    )]
+    quiet: bool,
+    #[arg(
+        short,
+        long,
+        help = "Also print the options in effect, stage timings and output sizes"
+    )]
+    verbose: bool,
+}
+
+// the options only some commands use, with those commands. every other
+// option shapes the rendered document and goes with every command that
+// takes options
+const ONLY: [(&str, &[&str]); 20] = [
+    ("output", &["convert"]),
+    ("output_dir", &["convert", "doctor"]),
+    ("binder", &["convert"]),
+    ("exhibit_format", &["convert"]),
+    ("emit_resources", &["convert"]),
+    ("deny_domain", &["convert", "resources"]),
+    ("line", &["explain"]),
+    ("list_private", &["convert"]),
+    ("pre_hook", &["convert"]),
+    ("post_hook", &["convert"]),
+    ("hook_timeout", &["convert"]),
+    ("dry_run", &["convert"]),
+    ("case_insensitive_outputs", &["convert"]),
+    ("watch", &["convert"]),
+    ("force", &["convert"]),
+    ("incremental", &["convert"]),
+    ("port", &["serve", "doctor"]),
+    ("pager", &["view"]),
+    ("open", &["convert", "serve"]),
+    ("open_with", &["convert", "serve", "doctor"]),
+];
+
+// whether the option `id` does anything for `command`
+fn applies(command: &str, id: &str) -> bool {
+    ONLY.iter()
+        .find(|(only, _)| *only == id)
+        .is_none_or(|(_, commands)| commands.contains(&command))
+}
+
+// the command line yamc takes. the options of a command that do nothing for
+// it stay out of its help and completions
+fn command_line() -> clap::Command {
+    let mut cli = Cli::command();
+    let names: Vec<String> = cli
+        .get_subcommands()
+        .filter(|command| {
+            command
+                .get_arguments()
+                .any(|argument| argument.get_id() == "config")
+        })
+        .map(|command| command.get_name().to_string())
+        .collect();
+    for name in names {
+        cli = cli.mut_subcommand(&name, |command| {
+            let unused: Vec<clap::Id> = command
+                .get_arguments()
+                .filter(|argument| !applies(&name, argument.get_id().as_str()))
+                .map(|argument| argument.get_id().clone())
+                .collect();
+            unused.into_iter().fold(command, |command, id| {
+                command.mut_arg(id, |argument| argument.hide(true))
+            })
+        });
+    }
+    cli
+}
+
+// the values of a repeatable option with their positions on the command line
+fn positioned<'m>(matches: &'m ArgMatches, id: &str) -> Vec<(usize, &'m String)> {
+    match (matches.indices_of(id), matches.get_many::<String>(id)) {
+        (Some(indices), Some(values)) => indices.zip(values).collect(),
+        _ => Vec::new(),
+    }
+}
+
+// the page for `inputs` inside `directory`: the name of a single input with
+// .html, output.html for a binder or standard input
+fn in_directory(directory: &Path, inputs: &[PathBuf], binder: bool) -> PathBuf {
+    let stem = match inputs {
+        [input] if !binder && !is_stdin(input) => input.file_stem(),
+        _ => None,
+    };
+    let mut name = stem.unwrap_or(OsStr::new("output")).to_os_string();
+    name.push(".html");
+    directory.join(name)
+}
+
+// turn the parsed arguments of `command` into input files and options.
+// `argv` is the command line they were parsed from, `matches` its matches
+fn parse_options(
+    command: &str,
+    mut arguments: Arguments,
+    matches: &ArgMatches,
+    argv: &[OsString],
+) -> Result<(Vec<PathBuf>, Options), Failed> {
+    // shells that do not expand `**` or any pattern at all leave it to us,
+    // a directory stands for the markdown files below it
+    let mut inputs = Vec::new();
+    let mut trees = Vec::new();
+    let mut skipped = 0;
+    for input in &arguments.inputs {
+        let expanded = if input.is_dir() {
+            trees.push(input.clone());
+            paths::markdown_files(input, arguments.hidden, &arguments.exclude).map(|listing| {
+                skipped += listing.skipped + listing.excluded.len();
+                if arguments.verbose {
+                    for (file, pattern) in &listing.excluded {
+                        say!("{}: excluded by {}", file, pattern);
+                    }
+                }
+                listing.files
+            })
+        } else {
+            paths::expand(input, arguments.hidden)
+        };
+        match expanded {
+            Ok(expanded) => inputs.extend(expanded),
+            Err(err) => {
+                say!("{}", err);
+                return Err(Failed::Io);
+            }
+        }
+    }
+    arguments.inputs = inputs;
+    // a replayed run takes every option from the dump and only the inputs
+    // from the command line
+    if let Some(replay) = &arguments.replay {
+        let (recorded, warnings) = replay::load(Path::new(replay)).map_err(|err| {
+            say!("{}", err);
+            Failed::Usage
+        })?;
+        if !arguments.quiet {
+            for warning in warnings {
+                say!("Warning : {}", warning);
+            }
+        }
+        let mut argv = vec![OsString::from("yamc"), OsString::from(command)];
+        argv.extend(recorded.into_iter().map(OsString::from));
+        let matches = command_line()
+            .try_get_matches_from(&argv)
+            .map_err(refused)?;
+        let replayed = matches.subcommand().expect("the command was given").1;
+        let parsed = Arguments::from_arg_matches(replayed).map_err(refused)?;
+        let (_, options) = parse_options(command, parsed, replayed, &argv)?;
+        return Ok((arguments.inputs, options));
+    }
+
+    let dialect = dialect::Dialect::parse(&arguments.from).expect("clap checks the dialect");
+    let mut options = Options {
+        syntax: dialect::Syntax::preset(dialect),
+        tables: tables::TableOptions {
+            enabled: arguments.enhanced_tables,
+            threshold: arguments.enhanced_tables_threshold,
+            filter: arguments.table_filter,
+        },
+        binder: arguments.binder,
+        exhibit_format: arguments.exhibit_format,
+        shift_headings: arguments.shift_headings,
+        strip_title: arguments.strip_title,
+        title: arguments.title,
+        highlight_theme: Some(arguments.highlight_theme).filter(|_| !arguments.no_highlight),
+        toc: arguments.toc,
+        toc_depth: arguments.toc_depth,
+        anchors: !arguments.no_anchors,
+        mermaid: !arguments.no_mermaid,
+        emoji: !arguments.no_emoji,
+        rewrite_links: !arguments.no_rewrite_links,
+        copy_assets: !arguments.no_copy_assets,
+        pages: Vec::new(),
+        max_input_size: arguments.max_input_size,
+        summary_length: arguments.summary_length,
+        emit_resources: arguments.emit_resources,
+        json: arguments.json,
+        deny_domains: arguments.deny_domain,
+        expose_env: arguments.expose_env,
+        no_autolink_in_headings: arguments
+            .no_autolink_in
+            .iter()
+            .any(|place| place == "headings"),
+        no_autolink_in_tables: arguments
+            .no_autolink_in
+            .iter()
+            .any(|place| place == "tables"),
+        spec_strict: arguments.spec_strict,
+        show_events: arguments.show_events,
+        line: arguments.line,
+        output: PathBuf::from("output.html"),
+        stylesheets: vec![Stylesheet::Linked(arguments.css_url)],
+        css_class: arguments.css_class,
+        template: arguments.template,
+        head_html: Vec::new(),
+        favicon: None,
+        lang: arguments.lang.clone(),
+        dir: arguments.dir.clone(),
+        meta: !arguments.no_meta,
+        includes: !arguments.no_includes,
+        base_url: arguments.base_url.clone(),
+        html_flavor: html::Flavor::Html5,
+        output_encoding: match arguments.output_encoding.as_deref() {
+            Some("utf-8") => Some(html::Encoding::Utf8),
+            Some(_) => Some(html::Encoding::Ascii),
+            None => None,
+        },
+        wrap_html: arguments.wrap_html,
+        keep_head_assets: arguments.keep_head_assets,
+        strip_comments: arguments.strip_comments,
+        minify: arguments.minify,
+        unsafe_html: arguments.unsafe_html,
+        sanitize: arguments.sanitize,
+        quote_citations: arguments.quote_citations,
+        list_private: arguments.list_private,
+        dry_run: arguments.dry_run,
+        case_insensitive_outputs: arguments.case_insensitive_outputs,
+        heading_attrs: arguments.heading_attrs,
+        pre_hook: arguments.pre_hook,
+        post_hook: arguments.post_hook,
+        hook_timeout: arguments.hook_timeout,
+        asset_root: arguments.asset_root,
+        limits: limits::Limits {
+            max_depth: arguments.max_depth,
+            max_nodes: arguments.max_nodes,
+        },
+        time_budget: arguments.time_budget.map(Duration::from_secs),
+        strict: arguments.strict,
+        deny_degradations: arguments.deny,
+        embed_images: arguments.embed_images,
+        fetch_remote_images: arguments.fetch_remote_images,
+        max_embed_size: arguments.max_embed_size,
+        source_map: arguments.source_map,
+        glossary: arguments.glossary,
+        glossary_case_sensitive: arguments.glossary_case_sensitive,
+        code_wrap: html::CodeWrap::parse(&arguments.code_wrap).expect("clap checks the mode"),
+        css_variables: arguments.css_var,
+        pager: arguments.pager,
+        open: arguments.open || arguments.open_with.is_some(),
+        open_with: arguments.open_with,
+        heading_id_prefix: arguments.heading_id_prefix,
+        output_dir: arguments.output_dir.clone(),
+        trees,
+        skipped,
+        each: false,
+        hidden: arguments.hidden,
+        excludes: arguments.exclude.clone(),
+        watch: arguments.watch,
+        port: arguments.port,
+        force: arguments.force,
+        incremental: arguments.incremental,
+        fingerprint: String::new(),
+        jobs: arguments.jobs.map_or_else(
+            || thread::available_parallelism().map_or(1, |jobs| jobs.get()),
+            usize::from,
+        ),
+        written: Vec::new(),
+        dump_config: arguments.dump_effective_config,
+        offline: arguments.offline || offline::forced(),
+        verbosity: if arguments.quiet {
+            Verbosity::Quiet
+        } else if arguments.verbose {
+            Verbosity::Verbose
+        } else {
+            Verbosity::Normal
+        },
+        text: None,
+    };
+    // the dialect is only a starting point, explicit toggles win regardless of
+    // where they appear on the command line. among themselves the last wins
+    let mut toggles: Vec<(usize, &str, bool)> = Vec::new();
+    for (position, feature) in positioned(matches, "enable") {
+        toggles.push((position, feature, true));
+    }
+    for (position, feature) in positioned(matches, "disable") {
+        toggles.push((position, feature, false));
+    }
+    for (position, mode) in positioned(matches, "autolink") {
+        toggles.push((position, "autolink", mode != "off"));
+        toggles.push((position, "autolink-domains", mode == "aggressive"));
+    }
+    if arguments.math {
+        toggles.push((matches.index_of("math").unwrap_or(0), "math", true));
+    }
+    if arguments.smart {
+        toggles.push((matches.index_of("smart").unwrap_or(0), "smart", true));
+    }
+    if arguments.hardbreaks {
+        toggles.push((
+            matches.index_of("hardbreaks").unwrap_or(0),
+            "hardbreaks",
+            true,
+        ));
+    }
+    toggles.sort_by_key(|(position, _, _)| *position);
+    for (_, feature, enabled) in toggles {
+        if let Err(err) = options.syntax.set(feature, enabled) {
+            say!("{}", err);
+            return Err(Failed::Usage);
+        }
+    }
+    // the tagfilter would still escape the author's <script> and <style>
+    if options.unsafe_html {
+        options.syntax.tagfilter = false;
+    }
+    let flavor = arguments.html_flavor.as_deref().map(|flavor| match flavor {
+        "xhtml" => html::Flavor::Xhtml,
+        _ => html::Flavor::Html5,
+    });
+    // each of several inputs gets a page named after it
+    options.each = command == "convert"
+        && !options.binder
+        && (arguments.inputs.len() > 1 || !options.trees.is_empty());
+    if options.each && arguments.output.is_some() {
+        say!(
+            "--output names a single page, leave it out to convert several inputs or use --binder"
+        );
+        return Err(Failed::Usage);
+    }
+    let output = arguments.output.or_else(|| {
+        let directory = arguments.output_dir.as_ref()?;
+        Some(in_directory(directory, &arguments.inputs, options.binder))
+    });
+    options.html_flavor = match output {
+        Some(output) if is_stdout(&output) => {
+            options.output = output;
+            flavor.unwrap_or(html::Flavor::Html5)
+        }
+        Some(output) => {
+            let implied = output_flavor(&output, flavor, options.verbosity == Verbosity::Quiet)?;
+            options.output = output;
+            implied
+        }
+        None => flavor.unwrap_or(html::Flavor::Html5),
+    };
+    if options.minify && options.html_flavor == html::Flavor::Xhtml {
+        say!("--minify cannot make xhtml, a minified page leaves out quotes and closing tags xml needs");
+        return Err(Failed::Usage);
+    }
+    // --css takes a url the way --css-url does, anything else is a file
+    let given = arguments.css.len() + usize::from(arguments.theme.is_some());
+    if given > 1 && arguments.css.iter().any(|css| css == "none") {
+        say!("--css none leaves out every stylesheet, it cannot be given with others or --theme");
+        return Err(Failed::Usage);
+    }
+    // a theme goes first, so --css can override it
+    let theme = arguments.theme.as_deref().map(|name| {
+        let theme = themes::find(name).expect("clap checks the theme");
+        Stylesheet::Inline(theme.css(&options.css_class))
+    });
+    if given > 0 {
+        options.stylesheets = theme.into_iter().collect();
+        for css in arguments.css.iter().filter(|css| *css != "none") {
+            if offline::is_remote(css) {
+                options.stylesheets.push(Stylesheet::Linked(css.clone()));
+                continue;
+            }
+            let text = fs::read_to_string(css).map_err(|err| {
+                say!("cannot read stylesheet {}: {}", css, err);
+                Failed::Io
+            })?;
+            options.stylesheets.push(Stylesheet::Inline(text));
+        }
+    }
+    // --head-html takes markup as it is, anything else is a file, read now so
+    // a missing one stops the run before any page is written
+    options.head_html = arguments
+        .head_html
+        .iter()
+        .map(|head_html| {
+            if head_html.contains('<') {
+                return Ok(head_html.clone());
+            }
+            fs::read_to_string(head_html).map_err(|err| {
+                say!("cannot read --head-html file {}: {}", head_html, err);
+                Failed::Io
+            })
+        })
+        .collect::<Result<_, _>>()?;
+    if let Some(favicon) = &arguments.favicon {
+        match assets::icon(favicon) {
+            Ok(icon) => options.favicon = Some(icon),
+            Err(err) => {
+                say!("--favicon: {}", err);
+                return Err(Failed::Io);
+            }
+        }
+    }
+    if let Some(icon) = options
+        .favicon
+        .as_ref()
+        .filter(|icon| options.offline && offline::is_remote(&icon.href))
+    {
+        say!(
+            "--offline does not allow the icon {}, the page would load it over the network",
+            icon.href
+        );
+        return Err(Failed::Usage);
+    }
+    if options.offline && !bundled_stylesheet(&options) {
+        for stylesheet in &options.stylesheets {
+            match stylesheet {
+                Stylesheet::Linked(url) if offline::is_remote(url) => {
+                    say!(
+                        "--offline does not allow the stylesheet {}, the page would load it over the network",
+                        url
+                    );
+                    return Err(Failed::Usage);
+                }
+                _ => {}
+            }
+        }
+    }
+    if arguments.embed_css && options.offline {
+        say!("--offline does not allow --embed-css, it downloads the stylesheet");
+        return Err(Failed::Usage);
+    }
+    if options.fetch_remote_images && options.offline {
+        say!("--offline does not allow --fetch-remote-images, it downloads the images");
+        return Err(Failed::Usage);
+    }
+    // downloaded once, every page of the run gets the same copy. an offline
+    // run was refused above
+    #[cfg(not(feature = "offline-bundle"))]
+    if arguments.embed_css {
+        let verbosity = options.verbosity;
+        for stylesheet in &mut options.stylesheets {
+            let url = match stylesheet {
+                Stylesheet::Linked(url) if offline::is_remote(url) => url.clone(),
+                _ => continue,
+            };
+            match download::text(&url) {
+                Ok(css) => {
+                    *stylesheet = Stylesheet::Inline(css);
+                    if verbosity == Verbosity::Verbose {
+                        say!("Embedding {}", url);
+                    }
+                }
+                Err(err) if verbosity != Verbosity::Quiet => say!(
+                    "Warning : cannot download {} ({}), linking it instead",
+                    url,
+                    err
+                ),
+                Err(_) => {}
+            }
+        }
+    }
+    // nothing of yamc's own on top of the spec
+    if options.spec_strict {
+        options.syntax = dialect::Syntax::preset(dialect::Dialect::CommonMark);
+        options.tables.enabled = false;
+        options.shift_headings = 0;
+        options.strip_title = false;
+        options.no_autolink_in_headings = false;
+        options.no_autolink_in_tables = false;
+        options.highlight_theme = None;
+        options.toc = false;
+        options.anchors = false;
+        options.mermaid = false;
+        options.emoji = false;
+    }
+    if options.syntax.math && options.offline {
+        say!("--offline does not allow math, KaTeX is loaded over the network");
+        return Err(Failed::Usage);
+    }
+    // a broken template fails the run here, not halfway through a batch
+    if let Some(template) = &options.template {
+        check_template(template, Failed::Usage)?;
+    }
+    let positions: Vec<usize> = matches
+        .indices_of("inputs")
+        .map(|indices| indices.collect())
+        .unwrap_or_default();
+    // everything after the command that is not an input, settings from
+    // yamc.toml and the environment included. clap does not count the
+    // program name in its indices
+    let recorded: Vec<String> = argv
+        .iter()
+        .enumerate()
+        .skip(2)
+        .filter(|(position, _)| !positions.contains(&(position - 1)))
+        .map(|(_, arg)| arg.to_string_lossy().into_owned())
+        .collect();
+    options.fingerprint = incremental::fingerprint(&recorded);
+    // a page made with a local stylesheet is out of date when the file changes
+    for stylesheet in &options.stylesheets {
+        if let Stylesheet::Inline(css) = stylesheet {
+            options.fingerprint.push('\0');
+            options.fingerprint.push_str(css);
+        }
+    }
+    if let Some(dump) = &options.dump_config {
+        let dumped = replay::dump(
+            Path::new(dump),
+            &recorded,
+            &options.syntax.enabled(),
+            &comrak_options(&options),
+        );
+        if let Err(err) = dumped {
+            say!("{}", err);
+            return Err(Failed::Io);
+        }
+    }
+    // only pages of their own can be linked to
+    if options.each && options.rewrite_links {
+        options.pages = arguments
+            .inputs
+            .iter()
+            .filter_map(|input| fs::canonicalize(input).ok())
+            .collect();
+    }
+    detail(
+        &options,
+        format!(
+            "syntax features: {}\ncomrak options: {:?}",
+            options.syntax.enabled().join(", "),
+            comrak_options(&options)
+        ),
+    );
+    Ok((arguments.inputs, options))
+}
+
+// the command line with the options of the command's config file and of
+// environment variables in front of its own, for commands that take options
+// and are not replaying a dump. later options win, so the command line comes
+// first, then the environment, then the file. also returns where the options
+// the command line did not give came from
+fn with_settings(
+    argv: Vec<OsString>,
+    matches: &ArgMatches,
+) -> Result<(Vec<OsString>, Vec<config::Setting>), Failed> {
+    let (name, submatches) = matches.subcommand().expect("clap requires a command");
+    let takes_options = submatches.try_contains_id("config").is_ok();
+    if !takes_options || submatches.contains_id("replay") {
+        return Ok((argv, Vec::new()));
+    }
+    let command = command_line();
+    let subcommand = command
+        .find_subcommand(name)
+        .expect("the command was parsed");
+    let path = match submatches.get_one::<String>("config") {
+        Some(path) => Some(PathBuf::from(path)),
+        None => config::find(),
+    };
+    let mut settings = match path {
+        Some(path) => config::arguments(&path, subcommand),
+        None => Ok(Vec::new()),
+    }
+    .map_err(|err| {
+        say!("{}", err);
+        Failed::Usage
+    })?;
+    settings.extend(config::environment(subcommand).map_err(|err| {
+        say!("{}", err);
+        Failed::Usage
+    })?);
+    // a setting for convert alone does not stop the other commands
+    settings.retain(|setting| applies(name, &setting.id));
+    // an option the command line rules out, like --output-dir next to
+    // --output, is dropped instead of refused
+    let given = |id: &str| submatches.value_source(id) == Some(ValueSource::CommandLine);
+    settings.retain(|setting| {
+        let argument = subcommand
+            .get_arguments()
+            .find(|argument| argument.get_id() == setting.id.as_str())
+            .expect("settings are options of the command");
+        // clap lists a conflict under the option that declares it only
+        let ruled_out = subcommand
+            .get_arguments()
+            .filter(|other| given(other.get_id().as_str()))
+            .any(|other| {
+                subcommand
+                    .get_arg_conflicts_with(other)
+                    .iter()
+                    .any(|conflict| conflict.get_id() == argument.get_id())
+            });
+        !ruled_out
+            && !subcommand
+                .get_arg_conflicts_with(argument)
+                .iter()
+                .any(|conflict| given(conflict.get_id().as_str()))
+    });
+    let mut merged = argv[..2].to_vec();
+    for setting in &settings {
+        merged.extend(setting.arguments.iter().map(OsString::from));
+    }
+    merged.extend_from_slice(&argv[2..]);
+    // the command line replaces both, the environment a single value from
+    // the file
+    let repeatable = |id: &str| {
+        subcommand.get_arguments().any(|argument| {
+            argument.get_id() == id && matches!(argument.get_action(), ArgAction::Append)
+        })
+    };
+    let ids: Vec<String> = settings.iter().map(|setting| setting.id.clone()).collect();
+    let mut position = 0;
+    settings.retain(|setting| {
+        position += 1;
+        let replaced = !repeatable(&setting.id) && ids[position..].contains(&setting.id);
+        !replaced && !given(&setting.id)
+    });
+    Ok((merged, settings))
+}
+
+// the command line as completions offer it: clap_complete lists hidden
+// options too, so each command is rebuilt with the options that apply to it
+fn completed() -> clap::Command {
+    let mut completed = command_line();
+    let names: Vec<String> = completed
+        .get_subcommands()
+        .map(|command| command.get_name().to_string())
+        .collect();
+    for name in names {
+        completed = completed.mut_subcommand(&name, |command| {
+            if !command
+                .get_arguments()
+                .any(|argument| argument.is_hide_set())
+            {
+                return command;
+            }
+            // what they conflict with or require may be gone
+            let shown: Vec<clap::Arg> = command
+                .get_arguments()
+                .filter(|argument| !argument.is_hide_set())
+                .map(|argument| {
+                    argument
+                        .clone()
+                        .conflicts_with(clap::builder::Resettable::<clap::Id>::Reset)
+                        .requires(clap::builder::Resettable::<clap::Id>::Reset)
+                })
+                .collect();
+            clap::Command::new(name.clone())
+                .about(command.get_about().cloned().unwrap_or_default())
+                .args(shown)
+        });
+    }
+    completed
+}
+
+// refuse an option given on the command line to a command it does nothing
+// for, such as --watch to explain. yamc.toml may set them for convert
+fn refuse_unused(matches: &ArgMatches) -> Result<(), Failed> {
+    let (name, submatches) = matches.subcommand().expect("clap requires a command");
+    let unused = submatches.ids().find(|id| {
+        submatches.value_source(id.as_str()) == Some(ValueSource::CommandLine)
+            && !applies(name, id.as_str())
+    });
+    match unused {
+        Some(id) => {
+            say!(
+                "--{} does nothing for {}, leave it out",
+                id.as_str().replace('_', "-"),
+                name
+            );
+            Err(Failed::Usage)
+        }
+        None => Ok(()),
+    }
+}
+
+// what clap found wrong with a command line, printed the way clap prints it
+fn refused(err: clap::Error) -> Failed {
+    let _ = err.print();
+    Failed::Usage
+}
+
+// the yamc command. the process ends here and nowhere else, with the status
+// of what failed
+pub fn main() {
+    if let Err(failed) = run() {
+        failed.exit();
+    }
+}
+
+fn run() -> Result<(), Failed> {
+    // inputs and outputs are paths, not text, so a name that is not valid
+    // unicode goes through as it is
+    let argv: Vec<OsString> = env::args_os().collect();
+    let matches = match command_line().try_get_matches_from(&argv) {
+        Ok(matches) => matches,
+        // --help and --version are what was asked for
+        Err(err) if !err.use_stderr() => {
+            let _ = err.print();
+            return Ok(());
+        }
+        Err(err) => return Err(refused(err)),
+    };
+    refuse_unused(&matches)?;
+    let (argv, settings) = with_settings(argv, &matches)?;
+    let matches = command_line()
+        .try_get_matches_from(&argv)
+        .map_err(refused)?;
+    let cli = Cli::from_arg_matches(&matches).map_err(refused)?;
+    let (name, submatches) = matches.subcommand().expect("clap requires a command");
+    if matches!(submatches.try_get_one::<bool>("verbose"), Ok(Some(true))) {
+        for setting in &settings {
+            say!("{} from {}", setting.arguments[0], setting.source);
+        }
+    }
+    match cli.command {
+        Command::Convert(arguments) => {
+            let (mut inputs, mut options) = parse_options(name, arguments, submatches, &argv)?;
+            // `convert doc.md -` prints the page, same as `--output -`
+            if !options.binder && inputs.len() == 2 && is_stdout(&inputs[1]) {
+                inputs.pop();
+                options.output = PathBuf::from(STDOUT);
+            }
+            if options.json && is_stdout(&options.output) && !options.dry_run {
+                say!("--json prints its report on standard output, write the page to a file");
+                return Err(Failed::Usage);
+            }
+            if options.open && is_stdout(&options.output) {
+                say!("--open needs an output file, not standard output");
+                return Err(Failed::Usage);
+            }
+            if options.dry_run {
+                dry_run(&inputs, &options)?;
+            } else if options.list_private {
+                for input in &inputs {
+                    list_private(input, &options)?;
+                }
+            } else if options.watch {
+                watch_inputs(&inputs, &mut options)?;
+            } else {
+                convert_all(&inputs, &mut options)?;
+            }
+        }
+        Command::Resources(arguments) => {
+            let (inputs, options) = parse_options(name, arguments, submatches, &argv)?;
+            for input in &inputs {
+                list_resources(input, &options)?;
+            }
+        }
+        Command::Copy(arguments) => {
+            let (inputs, mut options) = parse_options(name, arguments, submatches, &argv)?;
+            options.embed_images = true;
+            // a pasted page has no stylesheet to hide the marks
+            options.anchors = false;
+            for input in &inputs {
+                copy_to_clipboard(input, &options)?;
+            }
+        }
+        Command::Explain(arguments) => {
+            let (inputs, options) = parse_options(name, arguments, submatches, &argv)?;
+            for input in &inputs {
+                explain_line(input, &options)?;
+            }
+        }
+        Command::View(arguments) => {
+            let (inputs, options) = parse_options(name, arguments, submatches, &argv)?;
+            for input in &inputs {
+                view_document(input, &options)?;
+            }
+        }
+        Command::Serve(arguments) => {
+            let (inputs, options) = parse_options(name, arguments, submatches, &argv)?;
+            serve_input(&inputs, &options)?;
+        }
+        Command::Doctor(arguments) => {
+            let (_, options) = parse_options(name, arguments, submatches, &argv)?;
+            if !check_environment(&options, &settings) {
+                return Err(Failed::Conversion);
+            }
+        }
+        Command::Init {
+            sample,
+            force,
+            output_dir,
+            gitignore,
+        } => init_project(sample, force, output_dir.as_deref(), gitignore)?,
+        Command::Themes => {
+            let width = themes::names().map(str::len).max().unwrap_or(0);
+            for theme in themes::THEMES {
+                println!(
+                    "{:width$}  {}",
+                    theme.name,
+                    theme.description,
+                    width = width
+                );
+            }
+        }
+        Command::VerifyHash { files } => verify_hashes(&files)?,
+        Command::Template { command } => match command {
+            TemplateCommand::Check { file } => {
+                for placeholder in check_template(&file, Failed::Conversion)? {
+                    println!(
+                        "{}:{}:{}: {}",
+                        paths::display(&file),
+                        placeholder.line,
+                        placeholder.column,
+                        placeholder.text
+                    );
+                }
+                println!("{}: ok", paths::display(&file));
+            }
+            TemplateCommand::Preview {
+                file,
+                sample,
+                output,
+                open,
+            } => {
+                let output = output.unwrap_or_else(|| file.with_extension("preview.html"));
+                preview_template(&file, sample.as_deref(), output, open)?;
+            }
+        },
+        Command::Completions { shell } => {
+            clap_complete::generate(shell, &mut completed(), "yamc", &mut io::stdout());
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // the inputs and options of `yamc convert <arguments>`, the way main
+    // gets them
+    fn parse(arguments: &[&str]) -> (Vec<PathBuf>, Options) {
+        let mut argv = vec![OsString::from("yamc"), OsString::from("convert")];
+        argv.extend(arguments.iter().map(OsString::from));
+        parse_argv(&argv)
+    }
+
+    fn parse_argv(argv: &[OsString]) -> (Vec<PathBuf>, Options) {
+        match try_parse_argv(argv) {
+            Ok(parsed) => parsed,
+            Err(failed) => panic!("{:?} is refused: {}", argv, failed.kind()),
+        }
+    }
+
+    fn try_parse_argv(argv: &[OsString]) -> Result<(Vec<PathBuf>, Options), Failed> {
+        let matches = command_line()
+            .try_get_matches_from(argv)
+            .expect("the command line parses");
+        let (name, submatches) = matches.subcommand().expect("a command is given");
+        let arguments = Arguments::from_arg_matches(submatches).expect("convert's arguments");
+        parse_options(name, arguments, submatches, argv)
+    }
+
+    // a directory of its own for `test`, empty
+    fn scratch(test: &str) -> PathBuf {
+        let directory = env::temp_dir().join(format!("yamc-{}-{}", test, process::id()));
+        let _ = fs::remove_dir_all(&directory);
+        fs::create_dir_all(&directory).expect("the temporary directory is writable");
+        directory
+    }
+
+    // the rendered body of `markdown` with the options of `arguments`
+    fn render(test: &str, markdown: &str, arguments: &[&str]) -> String {
+        let page = scratch(test).join("page.md");
+        fs::write(&page, markdown).unwrap();
+        let path = page.to_str().unwrap();
+        let mut arguments = arguments.to_vec();
+        arguments.push(path);
+        let (_, options) = parse(&arguments);
+        match render_input(&page, &options) {
+            Ok(rendered) => rendered.html,
+            Err(failed) => panic!("{} failed: {}", path, failed.kind()),
+        }
+    }
+
+    // the page `page_parts` puts around `body` with the options of
+    // `arguments`
+    fn page(arguments: &[&str], title: &str, front_matter: Option<&str>, body: &str) -> String {
+        let mut arguments = arguments.to_vec();
+        arguments.push("page.md");
+        let (_, options) = parse(&arguments);
+        let parts = page_parts(&options, title, None, "", front_matter, None, &[body])
+            .unwrap_or_else(|failed| panic!("{}", failed.kind()));
+        parts.concat()
+    }
+
+    #[test]
+    fn options_a_command_does_nothing_with_are_refused() {
+        let matches = |argv: &[&str]| {
+            command_line()
+                .try_get_matches_from(argv)
+                .expect("the command line parses")
+        };
+        let explain = matches(&["yamc", "explain", "--watch", "page.md", "--line", "1"]);
+        assert!(matches!(refuse_unused(&explain), Err(Failed::Usage)));
+        assert!(refuse_unused(&matches(&["yamc", "convert", "--watch", "page.md"])).is_ok());
+        assert!(refuse_unused(&matches(&["yamc", "serve", "--port", "0", "page.md"])).is_ok());
+        // nor do completions offer them
+        let completed = completed();
+        let explain = completed
+            .find_subcommand("explain")
+            .expect("explain is a command");
+        assert!(explain
+            .get_arguments()
+            .all(|argument| argument.get_id() != "watch"));
+        assert!(explain
+            .get_arguments()
+            .any(|argument| argument.get_id() == "line"));
+    }
+
+    #[test]
+    fn init_config_loads_and_converts() {
+        let command = command_line();
+        let convert = command
+            .find_subcommand("convert")
+            .expect("convert is a command");
+        let text = init::config_text(convert, Some("site"));
+        // what init writes has to load the way a hand-written file does
+        let settings = config::settings(&text, Path::new(config::FILE), convert)
+            .expect("the generated config loads");
+        let mut arguments: Vec<String> = settings
+            .into_iter()
+            .flat_map(|setting| setting.arguments)
+            .collect();
+        let sample = scratch("init").join(init::SAMPLE);
+        fs::write(&sample, init::SAMPLE_TEXT).unwrap();
+        arguments.push(sample.to_str().unwrap().to_string());
+        let (inputs, options) = parse(&arguments.iter().map(String::as_str).collect::<Vec<_>>());
+        assert_eq!(options.output_dir.as_deref(), Some(Path::new("site")));
+        let rendered = render_input(&inputs[0], &options)
+            .unwrap_or_else(|_| panic!("{}", paths::display(&inputs[0])));
+        assert_eq!(rendered.title, "Example");
+        assert!(rendered.html.contains("<table>"));
+    }
+
+    #[test]
+    fn sanitized_pages_keep_embedded_images() {
+        let directory = scratch("sanitize");
+        // a 1x1 png
+        fs::write(
+            directory.join("dot.png"),
+            b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR\0\0\0\x01\0\0\0\x01\x08\x06\0\0\0\x1f\x15\xc4\x89",
+        )
+        .unwrap();
+        let page = directory.join("page.md");
+        fs::write(&page, "![a dot](dot.png)\n").unwrap();
+        let path = page.to_str().unwrap();
+        let (_, options) = parse(&["--embed-images", "--sanitize", path]);
+        let html = render_input(&page, &options).ok().unwrap().html;
+        assert!(
+            html.contains("<img src=\"data:image/png;base64,"),
+            "{}",
+            html
+        );
+    }
+
+    #[test]
+    fn images_under_the_asset_root_are_embedded() {
+        let directory = scratch("asset-root-embed");
+        let images = directory.join("root").join("img");
+        fs::create_dir_all(&images).unwrap();
+        fs::write(images.join("a.png"), b"\x89PNG\r\n\x1a\n").unwrap();
+        let root = directory.join("root");
+        for output in ["page.html", directory.join("page.html").to_str().unwrap()] {
+            let html = render(
+                "asset-root-embed-page",
+                "![a](/img/a.png)\n",
+                &[
+                    "--asset-root",
+                    root.to_str().unwrap(),
+                    "--embed-images",
+                    "--output",
+                    output,
+                ],
+            );
+            assert!(
+                html.contains("<img src=\"data:image/png;base64,"),
+                "{}",
+                html
+            );
+        }
+    }
+
+    #[test]
+    fn failures_carry_their_message_into_the_json_report() {
+        let missing = scratch("json-failure").join("missing.md");
+        let (_, options) = parse(&["--json", missing.to_str().unwrap()]);
+        let started = Instant::now();
+        let (rendered, said) = console::noting(|| render_input(&missing, &options).map(|_| ()));
+        let outcome = input_outcome(&missing, &options, started, rendered, said);
+        assert_eq!(outcome["status"], "failed");
+        assert_eq!(outcome["error"]["kind"], "io");
+        let message = outcome["error"]["message"].as_str().unwrap_or_default();
+        assert!(message.starts_with("Cannot read"), "{}", outcome);
+    }
+
+    #[test]
+    fn pages_have_a_head() {
+        let page = page(&["--head-html", "<meta name=\"x\">"], "A", None, "<p>a</p>");
+        assert!(
+            page.starts_with("<!DOCTYPE html>\n<html><head><meta charset=\"utf-8\">"),
+            "{}",
+            page
+        );
+        assert!(
+            page.contains(
+                "<meta name=\"x\"></head><body class=\"markdown-body\"><p>a</p></body></html>\n"
+            ),
+            "{}",
+            page
+        );
+    }
+
+    #[test]
+    fn language_and_direction_are_on_the_html_element() {
+        let page = page(&["--lang", "ar"], "A", Some("dir: rtl\n"), "");
+        assert!(
+            page.starts_with("<!DOCTYPE html>\n<html lang=\"ar\" dir=\"rtl\"><head>"),
+            "{}",
+            page
+        );
+        assert!(page.contains("<body class=\"markdown-body\">"), "{}", page);
+    }
+
+    #[test]
+    fn author_and_open_graph_tags_are_in_the_head() {
+        let page = page(
+            &["--base-url", "https://example.com/docs/"],
+            "A & B",
+            Some("author: Ann\nimage: cover.png\n"),
+            "<p>a</p>",
+        );
+        let head = &page[..page.find("</head>").expect("the page has a head")];
+        assert!(
+            head.contains("<meta name=\"author\" content=\"Ann\">"),
+            "{}",
+            page
+        );
+        assert!(
+            head.contains("<meta property=\"og:title\" content=\"A &amp; B\">"),
+            "{}",
+            page
+        );
+        assert!(
+            head.contains(
+                "<meta property=\"og:image\" content=\"https://example.com/docs/cover.png\">"
+            ),
+            "{}",
+            page
+        );
+    }
+
+    #[test]
+    fn titles_fall_back_in_order() {
+        let options = |arguments: &[&str]| {
+            let mut arguments = arguments.to_vec();
+            arguments.push("notes.md");
+            parse(&arguments).1
+        };
+        let front_matter = Some("title: From front matter\n");
+        let heading = || Some("From the h1".to_string());
+        let titled = options(&["--title", "From the option"]);
+        assert_eq!(
+            page_title(Path::new("notes.md"), front_matter, heading(), &titled),
+            "From the option"
+        );
+        let options = options(&[]);
+        assert_eq!(
+            page_title(Path::new("notes.md"), front_matter, heading(), &options),
+            "From front matter"
+        );
+        assert_eq!(
+            page_title(Path::new("notes.md"), None, heading(), &options),
+            "From the h1"
+        );
+        assert_eq!(
+            page_title(Path::new("notes.md"), None, None, &options),
+            "notes"
+        );
+        assert_eq!(
+            page_title(Path::new(STDIN), None, None, &options),
+            "Untitled"
+        );
+    }
+
+    #[test]
+    fn a_template_places_the_toc_and_the_summary() {
+        let directory = scratch("template-toc");
+        let template = directory.join("page.hbs");
+        fs::write(
+            &template,
+            "<aside>{{{toc}}}</aside><p>{{summary}}</p><main>{{{content}}}</main>",
+        )
+        .unwrap();
+        let page = directory.join("page.md");
+        fs::write(&page, "# Intro\n\nFirst words.\n\n## Usage\n").unwrap();
+        let (_, options) = parse(&[
+            "--template",
+            template.to_str().unwrap(),
+            page.to_str().unwrap(),
+        ]);
+        let document = match render_page(&page, &options) {
+            Ok(document) => document.page,
+            Err(failed) => panic!("{}", failed.kind()),
+        };
+        let aside = &document[..document.find("</aside>").expect("the template is used")];
+        assert!(
+            aside.contains("<a href=\"#usage\">Usage</a>"),
+            "{}",
+            document
+        );
+        assert!(
+            document.contains("<p>First words.</p><main>"),
+            "{}",
+            document
+        );
+        // without --toc the content has none of its own
+        assert_eq!(document.matches("<nav class=\"toc\">").count(), 1);
+    }
+
+    #[test]
+    fn title_and_description_are_escaped_in_the_head() {
+        let (_, options) = parse(&["page.md"]);
+        let parts = page_parts(
+            &options,
+            "<b>Tom & \"Jerry\"</b>",
+            Some("a < b"),
+            "",
+            None,
+            None,
+            &["<p>a</p>"],
+        )
+        .unwrap_or_else(|failed| panic!("{}", failed.kind()));
+        let page = parts.concat();
+        let head = &page[..page.find("</head>").expect("the page has a head")];
+        assert!(
+            head.contains("<title>&lt;b&gt;Tom &amp; &quot;Jerry&quot;&lt;/b&gt;</title>"),
+            "{}",
+            page
+        );
+        assert!(
+            head.contains("<meta name=\"description\" content=\"a &lt; b\">"),
+            "{}",
+            page
+        );
+    }
+
+    #[test]
+    fn css_classes_are_plain_identifiers() {
+        for class in ["markdown-body", "_page", "-dark", "page2", "a_b-c"] {
+            assert_eq!(parse_css_class(class).as_deref(), Ok(class));
+        }
+        for class in [
+            "",
+            "-",
+            "2col",
+            "-2col",
+            "a b",
+            "a.b",
+            "page\"><script>",
+            "été",
+        ] {
+            assert!(parse_css_class(class).is_err(), "{}", class);
+        }
+    }
+
+    // the only test that sets variables, so no other sees them
+    #[test]
+    fn environment_variables_sit_between_the_file_and_the_command_line() {
+        let config = scratch("environment").join(config::FILE);
+        fs::write(&config, "css_class = \"from-file\"\ncode_wrap = \"wrap\"\n").unwrap();
+        let config = config.to_str().unwrap();
+        let run = |arguments: &[&str]| {
+            let mut argv: Vec<OsString> = ["yamc", "convert", "--config", config]
+                .iter()
+                .chain(arguments)
+                .map(OsString::from)
+                .collect();
+            argv.push(OsString::from("page.md"));
+            let matches = command_line().try_get_matches_from(&argv).unwrap();
+            let (argv, settings) = with_settings(argv, &matches).unwrap();
+            let sources: Vec<(String, String)> = settings
+                .into_iter()
+                .map(|setting| (setting.id, setting.source))
+                .collect();
+            (parse_argv(&argv).1, sources)
+        };
+        env::set_var("YAMC_CSS_CLASS", "from-env");
+        let (options, sources) = run(&[]);
+        assert_eq!(options.css_class, "from-env");
+        assert!(options.code_wrap == html::CodeWrap::Wrap);
+        assert!(sources.contains(&("css_class".to_string(), "YAMC_CSS_CLASS".to_string())));
+        assert!(sources.contains(&("code_wrap".to_string(), config.to_string())));
+        let (options, sources) = run(&["--css-class", "from-line"]);
+        assert_eq!(options.css_class, "from-line");
+        assert!(!sources.iter().any(|(id, _)| id == "css_class"));
+        env::set_var("YAMC_CSS_CLASS", "");
+        assert_eq!(run(&[]).0.css_class, "from-file");
+        env::set_var("YAMC_CSS_CLASS", "2col");
+        let command = command_line();
+        let convert = command
+            .find_subcommand("convert")
+            .expect("convert is a command");
+        let refused = config::environment(convert).err().unwrap_or_default();
+        env::remove_var("YAMC_CSS_CLASS");
+        assert_eq!(
+            refused,
+            "YAMC_CSS_CLASS: \"2col\" is not a valid --css-class"
+        );
+    }
+
+    #[test]
+    fn pages_go_next_to_their_input_by_default() {
+        let (_, options) = parse(&["docs/guide.md"]);
+        assert_eq!(
+            page_of(Path::new("docs/guide.md"), &options)
+                .ok()
+                .as_deref(),
+            Some(Path::new("docs/guide.html"))
+        );
+        assert_eq!(
+            page_of(Path::new("notes.markdown"), &options)
+                .ok()
+                .as_deref(),
+            Some(Path::new("notes.html"))
+        );
+        assert!(page_of(Path::new(STDIN), &options).is_err());
+        assert!(page_of(Path::new("page.html"), &options).is_err());
+        let (_, options) = parse(&["--html-flavor", "xhtml", "--output", "page.xhtml", "a.md"]);
+        assert_eq!(
+            page_of(Path::new("docs/guide.md"), &options)
+                .ok()
+                .as_deref(),
+            Some(Path::new("docs/guide.xhtml"))
+        );
+    }
+
+    #[test]
+    fn pages_keep_their_place_below_the_output_directory() {
+        let directory = scratch("output-dir");
+        let docs = directory.join("docs");
+        fs::create_dir_all(docs.join("part")).unwrap();
+        fs::write(docs.join("guide.md"), "# Guide\n").unwrap();
+        fs::write(docs.join("part").join("one.md"), "# One\n").unwrap();
+        let build = directory.join("build");
+        let (inputs, options) = parse(&[
+            "--output-dir",
+            build.to_str().unwrap(),
+            docs.to_str().unwrap(),
+            "loose.md",
+        ]);
+        let pages: Vec<PathBuf> = inputs
+            .iter()
+            .map(|input| page_of(input, &options).ok().unwrap())
+            .collect();
+        let expected = [
+            build.join("guide.html"),
+            build.join("part").join("one.html"),
+            build.join("loose.html"),
+        ];
+        assert_eq!(pages, expected);
+    }
+
+    #[test]
+    fn inputs_that_go_to_the_same_page_collide() {
+        let directory = scratch("collisions");
+        for name in ["a/x.md", "b/x.md", "Readme.md", "readme.md", "notes.md"] {
+            let file = directory.join(name);
+            fs::create_dir_all(file.parent().unwrap()).unwrap();
+            fs::write(&file, "# Page\n").unwrap();
+        }
+        let file = |name: &str| directory.join(name).to_str().unwrap().to_string();
+        let build = directory.join("build");
+        let collided = |arguments: &[&str]| -> Vec<(PathBuf, usize)> {
+            let (inputs, options) = parse(arguments);
+            collisions(&inputs, &options)
+                .into_iter()
+                .map(|(page, sources)| (page, sources.len()))
+                .collect()
+        };
+        // --output-dir flattens loose files
+        let flattened = collided(&[
+            "--output-dir",
+            build.to_str().unwrap(),
+            &file("a/x.md"),
+            &file("b/x.md"),
+            &file("notes.md"),
+        ]);
+        assert_eq!(flattened, [(build.join("x.html"), 2)]);
+        let both = [file("Readme.md"), file("readme.md")];
+        let folded = collided(&["--case-insensitive-outputs", &both[0], &both[1]]);
+        assert_eq!(folded, [(directory.join("Readme.html"), 2)]);
+        // the rest depends on the file system the test runs on
+        if !paths::case_insensitive(&directory) {
+            assert!(collided(&[&both[0], &both[1]]).is_empty());
+        }
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn names_that_are_not_unicode_are_converted_like_the_rest() {
+        use std::os::unix::ffi::OsStrExt;
+        let directory = scratch("not-unicode");
+        let name = OsStr::from_bytes(b"caf\xE9.md");
+        fs::write(directory.join(name), "# Menu\n").unwrap();
+        fs::write(directory.join("plain.md"), "# Plain\n").unwrap();
+        let (inputs, mut options) = parse(&["--quiet", directory.to_str().unwrap()]);
+        assert_eq!(inputs, [directory.join(name), directory.join("plain.md")]);
+        assert!(paths::display(&inputs[0]).ends_with("caf\\xE9.md"));
+        assert!(convert_all(&inputs, &mut options).is_ok());
+        let page = directory.join(OsStr::from_bytes(b"caf\xE9.html"));
+        assert!(fs::read_to_string(page).unwrap().contains("Menu"));
+    }
+
+    #[test]
+    fn single_pages_in_a_directory_are_named_after_their_input() {
+        let build = Path::new("build");
+        let one = [PathBuf::from("docs/guide.md")];
+        assert_eq!(in_directory(build, &one, false), build.join("guide.html"));
+        assert_eq!(in_directory(build, &one, true), build.join("output.html"));
+        assert_eq!(
+            in_directory(build, &[PathBuf::from(STDIN)], false),
+            build.join("output.html")
+        );
+        let two = [PathBuf::from("a.md"), PathBuf::from("b.md")];
+        assert_eq!(in_directory(build, &two, false), build.join("output.html"));
+    }
+
+    #[test]
+    fn hardbreaks_only_change_paragraph_lines() {
+        let fixture = |name: &str| {
+            fs::read_to_string(
+                Path::new(env!("CARGO_MANIFEST_DIR"))
+                    .join("tests/fixtures")
+                    .join(name),
+            )
+            .unwrap()
+        };
+        let markdown = fixture("hardbreaks.md");
+        assert_eq!(
+            render("hardbreaks-off", &markdown, &[]),
+            fixture("hardbreaks.html")
+        );
+        let broken = fixture("hardbreaks-on.html");
+        assert_eq!(
+            render("hardbreaks-on", &markdown, &["--hardbreaks"]),
+            broken
+        );
+        let front_matter = format!("---\nhardbreaks: true\n---\n{}", markdown);
+        assert_eq!(
+            render("hardbreaks-front-matter", &front_matter, &[]),
+            broken
+        );
+    }
+
+    // everything a usual page has, each of which could reach for the
+    // network: the default stylesheet, remote and local images, math,
+    // diagrams, highlighted code and an enhanced table. the download stub
+    // panics on any request
+    #[test]
+    fn conversions_make_no_requests() {
+        let directory = scratch("no-network");
+        let page = directory.join("page.md");
+        fs::write(
+            &page,
+            "# Page\n\n![remote](https://example.com/a.png) ![local](dot.png)\n\n\
+             $e^x$ and\n\n```mermaid\ngraph TD; A-->B\n```\n\n```rust\nfn main() {}\n```\n\n\
+             <!-- table: enhanced -->\n\n| a |\n| - |\n| 1 |\n",
+        )
+        .unwrap();
+        fs::write(directory.join("dot.png"), b"\x89PNG\r\n\x1a\n").unwrap();
+        let input = page.to_str().unwrap();
+        // offline builds refuse the diagram too
+        let plain: &[&str] = if offline::forced() {
+            &["--no-mermaid"]
+        } else {
+            &[]
+        };
+        let mut runs = vec![plain, &["--offline", "--no-mermaid", "--embed-images"]];
+        let math = ["yamc", "convert", "--enable", "math", input].map(OsString::from);
+        // offline builds refuse math, its stylesheet is remote
+        if offline::forced() {
+            assert!(matches!(try_parse_argv(&math), Err(Failed::Usage)));
+        } else {
+            runs.push(&["--embed-images", "--enable", "math"]);
+        }
+        for flags in runs {
+            let output = directory.join("page.html");
+            let mut arguments = flags.to_vec();
+            arguments.extend(["--force", "--output", output.to_str().unwrap(), input]);
+            let (inputs, mut options) = parse(&arguments);
+            assert!(convert_all(&inputs, &mut options).is_ok(), "{:?}", flags);
+            assert!(fs::read_to_string(&output).unwrap().contains("<h1>"));
+        }
+    }
+
+    #[test]
+    fn offline_pages_refuse_mermaid_diagrams() {
+        let directory = scratch("offline-mermaid");
+        let page = directory.join("page.md");
+        fs::write(&page, "```mermaid\ngraph TD; A-->B\n```\n").unwrap();
+        let (_, options) = parse(&["--offline", page.to_str().unwrap()]);
+        assert!(matches!(render_input(&page, &options), Err(Failed::Usage)));
+        let (_, options) = parse(&["--offline", "--no-mermaid", page.to_str().unwrap()]);
+        match render_input(&page, &options) {
+            Ok(rendered) => assert!(
+                rendered.html.contains("language-mermaid"),
+                "{}",
+                rendered.html
+            ),
+            Err(failed) => panic!("{}", failed.kind()),
+        }
+    }
+
+    #[test]
+    fn rendering_a_document_writes_and_prints_nothing() {
+        let directory = scratch("render-document");
+        let page = directory.join("page.md");
+        fs::write(&page, "# Title\n\n###### Deep\n").unwrap();
+        let input = page.to_str().unwrap();
+        let output = directory.join("page.html");
+        let (_, options) = parse(&[
+            "--shift-headings",
+            "1",
+            "--show-events",
+            "--output",
+            output.to_str().unwrap(),
+            input,
+        ]);
+        let document = match render_page(&page, &options) {
+            Ok(document) => document,
+            Err(failed) => panic!("{} failed: {}", input, failed.kind()),
+        };
+        assert!(document.page.starts_with("<!DOCTYPE html>"));
+        assert!(document.page.contains(&document.rendered.html));
+        let codes: Vec<&str> = document
+            .rendered
+            .warnings
+            .iter()
+            .map(|warning| warning.code)
+            .collect();
+        assert_eq!(codes, ["clamped-heading"]);
+        assert!(document.rendered.events.is_some());
+        assert!(!output.exists());
+    }
+
+    #[test]
+    fn a_document_over_its_time_budget_fails_alone() {
+        let (_, mut options) = parse(&["page.md"]);
+        options.time_budget = Some(Duration::from_millis(100));
+        let slow = within_budget(Path::new("slow.md"), &options, |_, _| {
+            thread::sleep(Duration::from_secs(2));
+            Ok(())
+        });
+        assert!(matches!(slow, Err(Failed::Timeout)));
+        let quick = within_budget(Path::new("quick.md"), &options, |path, _| {
+            Ok(path.to_path_buf())
+        });
+        assert_eq!(quick.unwrap(), Path::new("quick.md"));
+    }
+
+    #[test]
+    fn spec_strict_pages_drop_raw_html() {
+        let markdown = "<script>alert(1)</script>\n\n<img src=x onerror=alert(1)>\n";
+        let html = render("strict", markdown, &["--spec-strict"]);
+        assert!(
+            !html.contains("<script>") && !html.contains("onerror"),
+            "{}",
+            html
+        );
+        let html = render("strict", markdown, &["--spec-strict", "--unsafe"]);
+        assert!(
+            html.contains("<script>") && html.contains("onerror"),
+            "{}",
+            html
+        );
+    }
+}
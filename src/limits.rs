@@ -0,0 +1,109 @@
+use comrak::arena_tree::NodeEdge;
+use comrak::nodes::AstNode;
+
+// generous enough for any real document, they only stop pathological input
+pub const DEFAULT_MAX_DEPTH: usize = 1000;
+pub const DEFAULT_MAX_NODES: usize = 5_000_000;
+// documents are rendered on a thread with this much stack
+pub const STACK_SIZE: usize = 256 * 1024 * 1024;
+
+#[derive(Clone)]
+pub struct Limits {
+    pub max_depth: usize,
+    pub max_nodes: usize,
+}
+
+// fail before the transform passes when the tree is nested deeper or has more
+// nodes than allowed, several passes walk a node's ancestors
+pub fn check<'a>(root: &'a AstNode<'a>, limits: &Limits) -> Result<(), String> {
+    let mut depth = 0;
+    let mut nodes = 0;
+    for edge in root.traverse() {
+        match edge {
+            NodeEdge::Start(_) => {
+                depth += 1;
+                nodes += 1;
+                if depth > limits.max_depth {
+                    return Err(format!(
+                        "nesting is deeper than --max-depth {}",
+                        limits.max_depth
+                    ));
+                }
+                if nodes > limits.max_nodes {
+                    return Err(format!("more than --max-nodes {} nodes", limits.max_nodes));
+                }
+            }
+            NodeEdge::End(_) => depth -= 1,
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use comrak::{Arena, ComrakOptions};
+    use std::thread;
+
+    const LIMITS: Limits = Limits {
+        max_depth: DEFAULT_MAX_DEPTH,
+        max_nodes: DEFAULT_MAX_NODES,
+    };
+
+    // what `check` says about `markdown`, parsed with the stack documents
+    // get
+    fn checked(markdown: String, limits: Limits) -> Result<(), String> {
+        thread::Builder::new()
+            .stack_size(STACK_SIZE)
+            .spawn(move || {
+                let arena = Arena::new();
+                let root = comrak::parse_document(&arena, &markdown, &ComrakOptions::default());
+                check(root, &limits)
+            })
+            .unwrap()
+            .join()
+            .unwrap()
+    }
+
+    #[test]
+    fn deep_quotes_are_refused() {
+        let markdown = format!("{}deep\n", ">".repeat(5000));
+        assert_eq!(
+            checked(markdown, LIMITS),
+            Err("nesting is deeper than --max-depth 1000".to_string())
+        );
+    }
+
+    #[test]
+    fn deep_lists_are_refused() {
+        // a list and an item for every level
+        let markdown: String = (0..600)
+            .map(|level| format!("{}- item\n", "  ".repeat(level)))
+            .collect();
+        assert!(checked(markdown, LIMITS).is_err());
+    }
+
+    #[test]
+    fn many_nodes_are_refused() {
+        let limits = Limits {
+            max_nodes: 1000,
+            ..LIMITS
+        };
+        assert_eq!(
+            checked("para\n\n".repeat(1000), limits),
+            Err("more than --max-nodes 1000 nodes".to_string())
+        );
+    }
+
+    #[test]
+    fn unclosed_emphasis_is_fine() {
+        assert_eq!(checked("*a _b ".repeat(100_000), LIMITS), Ok(()));
+        assert_eq!(checked("[".repeat(100_000), LIMITS), Ok(()));
+    }
+
+    #[test]
+    fn documents_within_the_limits_pass() {
+        let markdown = format!("{}ok\n", "> ".repeat(100));
+        assert_eq!(checked(markdown, LIMITS), Ok(()));
+    }
+}
@@ -0,0 +1,63 @@
+use crate::diagnostic::Diagnostic;
+use crate::explain;
+use crate::paths;
+use comrak::nodes::{AstNode, NodeValue};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+// what rewriting links needs to know about the run
+pub struct Site<'s> {
+    // the canonical paths of the documents getting pages of their own
+    pub pages: &'s [PathBuf],
+    // the input directories, canonical
+    pub trees: &'s [PathBuf],
+    // of the pages, html or xhtml
+    pub extension: &'s str,
+}
+
+// point relative links to markdown documents of the run at their pages,
+// keeping `?query` and `#fragment`. links to anything else stay as they are,
+// and a link to a document missing from an input directory gets a warning
+pub fn rewrite<'a>(root: &'a AstNode<'a>, path: &Path, site: &Site) -> Vec<Diagnostic> {
+    let directory = match fs::canonicalize(path) {
+        Ok(absolute) => absolute.parent().map(Path::to_path_buf).unwrap_or_default(),
+        Err(_) => return Vec::new(),
+    };
+    let mut warnings = Vec::new();
+    for node in root.descendants() {
+        let line = explain::line(node);
+        let mut data = node.data.borrow_mut();
+        let link = match data.value {
+            NodeValue::Link(ref mut link) => link,
+            _ => continue,
+        };
+        let url = String::from_utf8_lossy(&link.url).to_string();
+        if url.contains(':') || url.starts_with('/') || url.starts_with('#') {
+            continue;
+        }
+        let end = url.find(['?', '#']).unwrap_or(url.len());
+        let (file, rest) = url.split_at(end);
+        if !paths::is_markdown(Path::new(file)) {
+            continue;
+        }
+        let target = paths::normal(&directory.join(file));
+        if site.pages.contains(&target) {
+            let page = Path::new(file).with_extension(site.extension);
+            link.url = format!("{}{}", page.to_string_lossy(), rest).into_bytes();
+        } else if !target.exists() && site.trees.iter().any(|tree| target.starts_with(tree)) {
+            warnings.push(
+                Diagnostic::warning(
+                    "missing-link",
+                    path,
+                    line,
+                    format!(
+                        "{} does not exist in the input directory, the link is kept",
+                        file
+                    ),
+                )
+                .pointing_at(&url),
+            );
+        }
+    }
+    warnings
+}
@@ -0,0 +1,266 @@
+// `$inline$` and `$$display$$` tex. comrak 0.15 has no math extension, so
+// the source is rewritten before parsing: each formula becomes a code span
+// whose text is wrapped in `MARK`, which keeps markdown from touching it.
+// `render` later turns those code spans into the spans KaTeX typesets
+
+use crate::footnotes;
+use comrak::nodes::{AstNode, NodeValue};
+use comrak::Arena;
+
+// wraps the tex of a formula inside its code span, twice for display math
+pub const MARK: char = '\u{E001}';
+
+const KATEX: &str = "https://cdn.jsdelivr.net/npm/katex@0.16.9/dist";
+
+// what a page with math needs: KaTeX's stylesheet and script, and a script
+// that typesets the spans `render` made
+pub const ASSETS: &str = r#"<link rel="stylesheet" href="https://cdn.jsdelivr.net/npm/katex@0.16.9/dist/katex.min.css" />
+<script defer="defer" src="https://cdn.jsdelivr.net/npm/katex@0.16.9/dist/katex.min.js"></script>
+<script>
+document.addEventListener("DOMContentLoaded", function () {
+  document.querySelectorAll("span.math").forEach(function (span) {
+    katex.render(span.textContent.slice(2, -2), span, {
+      displayMode: span.classList.contains("math-display"),
+      throwOnError: false
+    });
+  });
+});
+</script>
+"#;
+
+// the urls `ASSETS` loads, with what they are
+pub fn resources() -> [(String, &'static str); 2] {
+    [
+        (format!("{}/katex.min.css", KATEX), "stylesheet"),
+        (format!("{}/katex.min.js", KATEX), "script"),
+    ]
+}
+
+fn escaped(chars: &[char], index: usize) -> bool {
+    chars[..index]
+        .iter()
+        .rev()
+        .take_while(|c| **c == '\\')
+        .count()
+        % 2
+        == 1
+}
+
+// `tex` as a code span markdown leaves alone. a formula has no backticks
+fn protected(tex: &str, display: bool) -> String {
+    let mark = if display {
+        format!("{}{}", MARK, MARK)
+    } else {
+        MARK.to_string()
+    };
+    format!("`{}{}{}`", mark, tex, mark)
+}
+
+// the end of the `$` that closes inline math opened just before `start`:
+// not preceded by whitespace, not followed by a digit, so `$5 and $10`
+// stays text. a backtick ends the search, a formula does not reach into
+// a code span
+fn inline_end(chars: &[char], start: usize) -> Option<usize> {
+    if chars
+        .get(start)
+        .is_none_or(|c| c.is_whitespace() || *c == '$')
+    {
+        return None;
+    }
+    (start + 1..chars.len())
+        .take_while(|&end| chars[end] != '`')
+        .find(|&end| {
+            chars[end] == '$'
+                && !escaped(chars, end)
+                && !chars[end - 1].is_whitespace()
+                && !chars.get(end + 1).is_some_and(|c| c.is_ascii_digit())
+        })
+}
+
+// the start of the `$$` that closes display math opened just before `start`
+fn display_end(chars: &[char], start: usize) -> Option<usize> {
+    (start..chars.len().saturating_sub(1))
+        .take_while(|&end| chars[end] != '`')
+        .find(|&end| chars[end] == '$' && chars[end + 1] == '$' && !escaped(chars, end))
+        .filter(|&end| chars[start..end].iter().any(|c| !c.is_whitespace()))
+}
+
+// protect the formulas of a run of lines outside code blocks
+fn scan(text: &str, output: &mut String) {
+    let chars: Vec<char> = text.chars().collect();
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        if c == '\\' && i + 1 < chars.len() {
+            output.push(c);
+            output.push(chars[i + 1]);
+            i += 2;
+            continue;
+        }
+        if c == '`' {
+            let run = chars[i..].iter().take_while(|x| **x == '`').count();
+            let mut end = i + run;
+            let mut j = i + run;
+            while j < chars.len() {
+                let closing = chars[j..].iter().take_while(|x| **x == '`').count();
+                if closing == run {
+                    end = j + closing;
+                    break;
+                }
+                j += closing.max(1);
+            }
+            output.extend(&chars[i..end]);
+            i = end;
+            continue;
+        }
+        if c == '$' {
+            if chars.get(i + 1) == Some(&'$') {
+                if let Some(end) = display_end(&chars, i + 2) {
+                    let tex: String = chars[i + 2..end].iter().collect();
+                    output.push_str(&protected(&tex, true));
+                    i = end + 2;
+                    continue;
+                }
+                output.push_str("$$");
+                i += 2;
+                continue;
+            }
+            if let Some(end) = inline_end(&chars, i + 1) {
+                let tex: String = chars[i + 1..end].iter().collect();
+                output.push_str(&protected(&tex, false));
+                i = end + 1;
+                continue;
+            }
+        }
+        output.push(c);
+        i += 1;
+    }
+}
+
+fn indent(line: &str) -> usize {
+    line.chars()
+        .take_while(|c| *c == ' ' || *c == '\t')
+        .map(|c| if c == '\t' { 4 } else { 1 })
+        .sum()
+}
+
+// rewrite the formulas of `markdown` into protected code spans. fenced and
+// indented code, code spans and escaped dollars are left alone, and a
+// formula never reaches past a blank line
+pub fn protect(markdown: &str) -> String {
+    if !markdown.contains('$') {
+        return markdown.to_string();
+    }
+    let mut output = String::with_capacity(markdown.len());
+    let mut open_fence = None;
+    let mut indented = false;
+    let mut previous_blank = true;
+    // the lines of the paragraph being read, scanned together so display
+    // math can span them
+    let mut lines = String::new();
+
+    for line in markdown.split_inclusive('\n') {
+        let content = line.trim_end_matches(['\n', '\r']);
+        let blank = content.trim().is_empty();
+        if let Some(open) = open_fence {
+            output.push_str(line);
+            if footnotes::closes(content, open) {
+                open_fence = None;
+            }
+            previous_blank = blank;
+            continue;
+        }
+        if let Some(opened) = footnotes::fence(content) {
+            scan(&lines, &mut output);
+            lines.clear();
+            open_fence = Some(opened);
+            output.push_str(line);
+            previous_blank = false;
+            continue;
+        }
+        if !blank && indent(content) >= 4 && (previous_blank || indented) {
+            indented = true;
+            output.push_str(line);
+            previous_blank = false;
+            continue;
+        }
+        indented = false;
+        if blank {
+            scan(&lines, &mut output);
+            lines.clear();
+            output.push_str(line);
+        } else {
+            lines.push_str(line);
+        }
+        previous_blank = blank;
+    }
+    scan(&lines, &mut output);
+    output
+}
+
+// the tex of a code span `protect` made, and whether it is display math
+fn formula(literal: &str) -> Option<(&str, bool)> {
+    let double = format!("{}{}", MARK, MARK);
+    if let Some(tex) = literal
+        .strip_prefix(&double)
+        .and_then(|rest| rest.strip_suffix(&double))
+    {
+        return Some((tex, true));
+    }
+    literal
+        .strip_prefix(MARK)
+        .and_then(|rest| rest.strip_suffix(MARK))
+        .map(|tex| (tex, false))
+}
+
+fn new_node<'a>(arena: &'a Arena<AstNode<'a>>, value: NodeValue) -> &'a AstNode<'a> {
+    arena.alloc(AstNode::from(value))
+}
+
+// turn the code spans `protect` made into `<span class="math ...">` around
+// the tex between \( \) or \[ \], the delimiters KaTeX reads. runs after
+// `html::omit_raw`, which would take the tags for authored html
+pub fn render<'a>(arena: &'a Arena<AstNode<'a>>, root: &'a AstNode<'a>) {
+    let spans: Vec<&'a AstNode<'a>> = root
+        .descendants()
+        .filter(|node| matches!(node.data.borrow().value, NodeValue::Code(..)))
+        .collect();
+    for span in spans {
+        let literal = match span.data.borrow().value {
+            NodeValue::Code(ref code) => String::from_utf8_lossy(&code.literal).into_owned(),
+            _ => continue,
+        };
+        let (tex, display) = match formula(&literal) {
+            Some(formula) => formula,
+            None => continue,
+        };
+        let (class, open, close) = if display {
+            ("math-display", "\\[", "\\]")
+        } else {
+            ("math-inline", "\\(", "\\)")
+        };
+        span.insert_before(new_node(
+            arena,
+            NodeValue::HtmlInline(format!("<span class=\"math {}\">", class).into_bytes()),
+        ));
+        span.insert_before(new_node(
+            arena,
+            NodeValue::Text(format!("{}{}{}", open, tex.trim(), close).into_bytes()),
+        ));
+        span.data.borrow_mut().value = NodeValue::HtmlInline(b"</span>".to_vec());
+    }
+}
+
+// whether rendered html has formulas from `render`
+pub fn used(html: &str) -> bool {
+    html.contains("<span class=\"math math-")
+}
+
+// whether a parsed document has formulas, before `render`
+pub fn found<'a>(root: &'a AstNode<'a>) -> bool {
+    root.descendants()
+        .any(|node| match node.data.borrow().value {
+            NodeValue::Code(ref code) => formula(&String::from_utf8_lossy(&code.literal)).is_some(),
+            _ => false,
+        })
+}
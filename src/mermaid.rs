@@ -0,0 +1,54 @@
+use crate::html::escape;
+use comrak::nodes::{AstNode, NodeHtmlBlock, NodeValue};
+
+const SCRIPT_URL: &str = "https://cdn.jsdelivr.net/npm/mermaid@10/dist/mermaid.esm.min.mjs";
+
+// what a page with diagrams needs: mermaid, which draws every
+// `<pre class="mermaid">` when the page loads
+pub const ASSETS: &str = r#"<script type="module">
+import mermaid from "https://cdn.jsdelivr.net/npm/mermaid@10/dist/mermaid.esm.min.mjs";
+mermaid.initialize({ startOnLoad: true });
+</script>
+"#;
+
+// the url `ASSETS` loads
+pub fn resource() -> &'static str {
+    SCRIPT_URL
+}
+
+fn is_diagram(info: &[u8]) -> bool {
+    String::from_utf8_lossy(info)
+        .split_whitespace()
+        .next()
+        .is_some_and(|language| language.eq_ignore_ascii_case("mermaid"))
+}
+
+// whether a parsed document has a ```mermaid block
+pub fn found<'a>(root: &'a AstNode<'a>) -> bool {
+    root.descendants()
+        .any(|node| match node.data.borrow().value {
+            NodeValue::CodeBlock(ref block) => block.fenced && is_diagram(&block.info),
+            _ => false,
+        })
+}
+
+// turn ```mermaid blocks into the `<pre class="mermaid">` mermaid draws,
+// true when there were any
+pub fn diagrams<'a>(root: &'a AstNode<'a>) -> bool {
+    let mut found = false;
+    for node in root.descendants() {
+        let html = match node.data.borrow().value {
+            NodeValue::CodeBlock(ref block) if block.fenced && is_diagram(&block.info) => format!(
+                "<pre class=\"mermaid\">{}</pre>\n",
+                escape(&String::from_utf8_lossy(&block.literal))
+            ),
+            _ => continue,
+        };
+        node.data.borrow_mut().value = NodeValue::HtmlBlock(NodeHtmlBlock {
+            block_type: 0,
+            literal: html.into_bytes(),
+        });
+        found = true;
+    }
+    found
+}
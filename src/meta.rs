@@ -0,0 +1,52 @@
+// the author and Open Graph tags of a page, from the `author`, `title`,
+// `description` and `image` keys of its front matter. other keys are not
+// looked at
+use crate::front_matter;
+use crate::html::escape;
+use url::Url;
+
+// the tags for the head, `void` ends each element. the Open Graph ones are
+// only there when the front matter says something about the page, so a
+// plain document keeps the head it had
+pub fn tags(
+    front_matter: &str,
+    title: &str,
+    description: Option<&str>,
+    base_url: Option<&Url>,
+    void: &str,
+) -> String {
+    let mut tags = String::new();
+    let mut tag = |attribute: &str, name: &str, content: &str| {
+        tags.push_str(&format!(
+            "<meta {}=\"{}\" content=\"{}\"{}",
+            attribute,
+            name,
+            escape(content),
+            void
+        ));
+    };
+    if let Some(author) = front_matter::value(front_matter, "author") {
+        tag("name", "author", &author);
+    }
+    let image = front_matter::value(front_matter, "image");
+    if !["title", "description", "image"]
+        .iter()
+        .any(|key| front_matter::value(front_matter, key).is_some())
+    {
+        return tags;
+    }
+    tag("property", "og:title", title);
+    if let Some(description) = description {
+        tag("property", "og:description", description);
+    }
+    if let Some(image) = image {
+        // Open Graph wants an absolute url, a relative one is taken as
+        // relative to --base-url
+        let image = match base_url.and_then(|base_url| base_url.join(&image).ok()) {
+            Some(absolute) if Url::parse(&image).is_err() => absolute.to_string(),
+            _ => image,
+        };
+        tag("property", "og:image", &image);
+    }
+    tags
+}
@@ -0,0 +1,18 @@
+use crate::{resources, themes};
+
+// builds made with the offline-bundle feature are always offline
+pub fn forced() -> bool {
+    cfg!(feature = "offline-bundle")
+}
+
+// the stylesheet of yamc's own put into the page instead of linking the
+// GitHub one when the page must not load anything
+pub fn style(class: &str) -> String {
+    let theme = themes::find("github").expect("github is a bundled theme");
+    format!("<style>\n{}\n</style>", theme.css(class))
+}
+
+// whether the page would load `url` from another machine
+pub fn is_remote(url: &str) -> bool {
+    resources::domain(url).is_some() || url.starts_with("//")
+}
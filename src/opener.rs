@@ -0,0 +1,48 @@
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+// the platform's command for opening a file with its default application
+fn system_opener() -> Command {
+    if cfg!(target_os = "macos") {
+        Command::new("open")
+    } else if cfg!(windows) {
+        let mut command = Command::new("cmd");
+        // the empty argument is the window title `start` expects first
+        command.args(["/C", "start", ""]);
+        command
+    } else {
+        Command::new("xdg-open")
+    }
+}
+
+// the name of the program that opens files when no other is given
+pub fn system_program() -> String {
+    system_opener().get_program().to_string_lossy().to_string()
+}
+
+// open `path` with `program`, or with the default handler for its type. the
+// system openers hand the file over and return, `program` is left running
+pub fn open(path: &Path, program: Option<&str>) -> Result<(), String> {
+    if let Some(program) = program {
+        return Command::new(program)
+            .arg(path)
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .spawn()
+            .map(|_| ())
+            .map_err(|err| format!("cannot start {}: {}", program, err));
+    }
+    let mut command = system_opener();
+    let name = command.get_program().to_string_lossy().to_string();
+    let status = command
+        .arg(path)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .status()
+        .map_err(|err| format!("cannot start {}: {}", name, err))?;
+    if status.success() {
+        Ok(())
+    } else {
+        Err(format!("{} failed ({})", name, status))
+    }
+}
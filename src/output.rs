@@ -0,0 +1,51 @@
+use crate::paths;
+use std::fs;
+use std::io::{self, ErrorKind};
+use std::path::Path;
+use std::process;
+
+// what a page adds on top of its text: the head, table scripts and markup
+const OVERHEAD: u64 = 64 * 1024;
+
+// a message naming the file that could not be written and what to do about it
+pub fn describe(path: &Path, err: &io::Error) -> String {
+    let hint = match err.kind() {
+        ErrorKind::StorageFull => "the disk is full, free some space or choose another --output",
+        ErrorKind::ReadOnlyFilesystem => "the file system is read-only, choose another --output",
+        ErrorKind::PermissionDenied => {
+            "permission denied, check the file and its directory or choose another --output"
+        }
+        ErrorKind::NotFound => "its directory does not exist",
+        _ => return format!("Cannot write {} : {}", paths::display(path), err),
+    };
+    format!("Cannot write {} : {} ({})", paths::display(path), hint, err)
+}
+
+// make sure `path` can be written and has room for about `estimate` bytes
+// before any conversion work is done for it
+pub fn preflight(path: &Path, estimate: u64) -> Result<(), String> {
+    let directory = match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    };
+    if path.exists() {
+        fs::OpenOptions::new()
+            .write(true)
+            .open(path)
+            .map_err(|err| describe(path, &err))?;
+    } else {
+        let probe = directory.join(format!(".yamc-write-test-{}", process::id()));
+        fs::File::create(&probe).map_err(|err| describe(path, &err))?;
+        let _ = fs::remove_file(&probe);
+    }
+    let needed = estimate.saturating_add(OVERHEAD);
+    match fs2::available_space(directory) {
+        Ok(available) if available < needed => Err(format!(
+            "Cannot write {} : about {} bytes are needed but only {} are free",
+            paths::display(path),
+            needed,
+            available
+        )),
+        _ => Ok(()),
+    }
+}
@@ -0,0 +1,279 @@
+use crate::collate;
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
+use ignore::Match;
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+
+// the bytes of a path that is not valid unicode, where the platform has them
+#[cfg(unix)]
+fn raw_bytes(path: &Path) -> Option<Vec<u8>> {
+    use std::os::unix::ffi::OsStrExt;
+    Some(path.as_os_str().as_bytes().to_vec())
+}
+
+#[cfg(not(unix))]
+fn raw_bytes(_path: &Path) -> Option<Vec<u8>> {
+    None
+}
+
+// a path that is not valid unicode with its invalid bytes as `\xNN`, or with
+// replacement characters where there are no bytes to show
+fn escaped(path: &Path) -> String {
+    let bytes = match raw_bytes(path) {
+        Some(bytes) => bytes,
+        None => return path.to_string_lossy().into_owned(),
+    };
+    let mut text = String::new();
+    for chunk in bytes.utf8_chunks() {
+        text.push_str(chunk.valid());
+        for byte in chunk.invalid() {
+            text.push_str(&format!("\\x{:02X}", byte));
+        }
+    }
+    text
+}
+
+// user facing form of a path, used by every message that names one. a path
+// that is not valid unicode shows its other bytes escaped (see `escaped`).
+// canonicalize on windows returns verbatim `\\?\C:\...` paths, which are
+// valid but confusing to read
+pub fn display(path: &Path) -> String {
+    let text = match path.to_str() {
+        Some(text) => text.to_string(),
+        None => escaped(path),
+    };
+    match text.strip_prefix(r"\\?\UNC\") {
+        Some(rest) => format!(r"\\{}", rest),
+        None => text.strip_prefix(r"\\?\").unwrap_or(&text).to_string(),
+    }
+}
+
+// the raw bytes of a path that is not valid unicode in base64, for json
+// reports to carry next to `display` so tools can find the file again
+pub fn base64(path: &Path) -> Option<String> {
+    match path.to_str() {
+        Some(_) => None,
+        None => raw_bytes(path).map(|bytes| STANDARD.encode(bytes)),
+    }
+}
+
+// a relative path joined with `/` for use inside urls, whatever the platform
+// separator is
+pub fn to_url(path: &Path) -> String {
+    path.components()
+        .filter_map(|component| match component {
+            Component::Normal(part) => Some(part.to_string_lossy().into_owned()),
+            Component::ParentDir => Some("..".to_string()),
+            _ => None,
+        })
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+// an absolute path as a `file://` url
+pub fn to_file_url(path: &Path) -> String {
+    let parts: Vec<String> = path
+        .components()
+        .filter_map(|component| match component {
+            Component::Prefix(prefix) => Some(display(Path::new(prefix.as_os_str()))),
+            Component::Normal(part) => Some(part.to_string_lossy().into_owned()),
+            Component::ParentDir => Some("..".to_string()),
+            _ => None,
+        })
+        .collect();
+    format!("file:///{}", parts.join("/"))
+}
+
+// the inputs an argument stands for: itself when it names a file or has no
+// wildcards, otherwise the files its glob pattern matches in yamc's listing
+// order. hidden files and directories only match with `hidden`
+pub fn expand(argument: &Path, hidden: bool) -> Result<Vec<PathBuf>, String> {
+    // a pattern is text, a name that is not valid unicode is taken as it is
+    let pattern = match argument.to_str() {
+        Some(pattern) if !argument.is_file() && pattern.contains(['*', '?', '[']) => pattern,
+        _ => return Ok(vec![argument.to_path_buf()]),
+    };
+    let options = glob::MatchOptions {
+        require_literal_leading_dot: !hidden,
+        ..Default::default()
+    };
+    let entries = glob::glob_with(pattern, options)
+        .map_err(|err| format!("Invalid pattern {:?} : {}", pattern, err))?;
+    let mut matches: Vec<PathBuf> = entries
+        .filter_map(Result::ok)
+        .filter(|path| path.is_file())
+        .collect();
+    if matches.is_empty() {
+        return Err(format!("No files match {:?}", pattern));
+    }
+    sort(&mut matches);
+    Ok(matches)
+}
+
+// paths in yamc's listing order, by how they are shown
+fn sort(paths: &mut [PathBuf]) {
+    paths.sort_by(|a, b| collate::compare(&display(a), &display(b)));
+}
+
+// `path` with `.` and `..` taken out without asking the file system, so it
+// works for files that do not exist
+pub fn normal(path: &Path) -> PathBuf {
+    let mut normal = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                normal.pop();
+            }
+            other => normal.push(other),
+        }
+    }
+    normal
+}
+
+// whether two paths name the same file
+#[cfg(unix)]
+fn same_file(a: &Path, b: &Path) -> bool {
+    use std::os::unix::fs::MetadataExt;
+    match (fs::metadata(a), fs::metadata(b)) {
+        (Ok(a), Ok(b)) => a.dev() == b.dev() && a.ino() == b.ino(),
+        _ => false,
+    }
+}
+
+#[cfg(not(unix))]
+fn same_file(_a: &Path, b: &Path) -> bool {
+    b.exists()
+}
+
+// whether the file system `directory` is on takes names that differ only in
+// case for the same file. told by looking up the nearest existing directory
+// with letters in its name under that name in the other case
+pub fn case_insensitive(directory: &Path) -> bool {
+    let existing = directory
+        .ancestors()
+        .find(|ancestor| !ancestor.as_os_str().is_empty() && ancestor.exists())
+        .unwrap_or(Path::new("."));
+    let existing = match fs::canonicalize(existing) {
+        Ok(existing) => existing,
+        Err(_) => return false,
+    };
+    for ancestor in existing.ancestors() {
+        let name = match ancestor.file_name().and_then(|name| name.to_str()) {
+            Some(name) => name,
+            None => continue,
+        };
+        let other: String = name
+            .chars()
+            .map(|c| {
+                if c.is_lowercase() {
+                    c.to_ascii_uppercase()
+                } else {
+                    c.to_ascii_lowercase()
+                }
+            })
+            .collect();
+        if other != name {
+            return same_file(ancestor, &ancestor.with_file_name(other));
+        }
+    }
+    false
+}
+
+pub fn is_markdown(path: &Path) -> bool {
+    path.extension()
+        .and_then(|extension| extension.to_str())
+        .is_some_and(|extension| {
+            extension.eq_ignore_ascii_case("md") || extension.eq_ignore_ascii_case("markdown")
+        })
+}
+
+// the file in an input directory listing what to leave out of it, in
+// .gitignore syntax
+pub const IGNORE_FILE: &str = ".yamcignore";
+
+// what a walk of an input directory found
+pub struct Listing {
+    // markdown files in yamc's listing order
+    pub files: Vec<PathBuf>,
+    // other files
+    pub skipped: usize,
+    // files left out by a pattern, with the pattern and where it is from
+    pub excluded: Vec<(String, String)>,
+}
+
+// the rules for leaving files out of `directory`: its .yamcignore, then
+// `patterns` given on the command line, so those win
+fn exclusions(directory: &Path, patterns: &[String]) -> Result<Gitignore, String> {
+    let mut builder = GitignoreBuilder::new(directory);
+    let file = directory.join(IGNORE_FILE);
+    if file.is_file() {
+        if let Some(err) = builder.add(&file) {
+            return Err(format!("{}: {}", display(&file), err));
+        }
+    }
+    for pattern in patterns {
+        builder
+            .add_line(None, pattern)
+            .map_err(|err| format!("--exclude {}: {}", pattern, err))?;
+    }
+    builder
+        .build()
+        .map_err(|err| format!("{}: {}", display(&file), err))
+}
+
+// the markdown files below `directory` and what else was there. symlinked
+// directories are not followed so a link back up cannot loop, hidden entries
+// are left out unless `hidden`, and files matching .yamcignore or `excludes`
+// are counted apart
+pub fn markdown_files(
+    directory: &Path,
+    hidden: bool,
+    excludes: &[String],
+) -> Result<Listing, String> {
+    let rules = exclusions(directory, excludes)?;
+    let mut files = Vec::new();
+    let mut skipped = 0;
+    let mut excluded = Vec::new();
+    let mut pending = vec![directory.to_path_buf()];
+    while let Some(current) = pending.pop() {
+        let entries = fs::read_dir(&current)
+            .map_err(|err| format!("Cannot read {} : {}", display(&current), err))?;
+        for entry in entries {
+            let entry =
+                entry.map_err(|err| format!("Cannot read {} : {}", display(&current), err))?;
+            let path = entry.path();
+            if !hidden && entry.file_name().to_string_lossy().starts_with('.') {
+                continue;
+            }
+            let kind = entry
+                .file_type()
+                .map_err(|err| format!("Cannot read {} : {}", display(&path), err))?;
+            if kind.is_dir() {
+                pending.push(path);
+            } else if !path.is_file() || !is_markdown(&path) {
+                skipped += 1;
+            } else if let Match::Ignore(glob) = rules.matched_path_or_any_parents(&path, false) {
+                let source = match glob.from() {
+                    Some(file) => display(file),
+                    None => "--exclude".to_string(),
+                };
+                excluded.push((display(&path), format!("{} ({})", glob.original(), source)));
+            } else {
+                files.push(path);
+            }
+        }
+    }
+    if files.is_empty() {
+        return Err(format!("No markdown files in {}", display(directory)));
+    }
+    sort(&mut files);
+    excluded.sort_by(|a, b| collate::compare(&a.0, &b.0));
+    Ok(Listing {
+        files,
+        skipped,
+        excluded,
+    })
+}
@@ -0,0 +1,100 @@
+use crate::diagnostic::Diagnostic;
+use crate::footnotes;
+use regex::Regex;
+use std::path::Path;
+
+// `<!-- private -->` ... `<!-- /private -->` marks notes that must never be
+// published. the markers may nest, only the outermost pair counts
+pub struct Region {
+    pub start_line: usize,
+    pub end_line: usize,
+    start: usize,
+    end: usize,
+}
+
+// markers outside fenced code, as (byte offset, byte end, line, closing)
+fn markers(markdown: &str) -> Vec<(usize, usize, usize, bool)> {
+    let pattern = Regex::new(r"<!--\s*(/?)private\s*-->").expect("marker pattern should compile");
+    let mut found = Vec::new();
+    let mut offset = 0;
+    let mut open_fence = None;
+    for (number, line) in markdown.split_inclusive('\n').enumerate() {
+        let content = line.trim_end_matches(['\n', '\r']);
+        if let Some(open) = open_fence {
+            if footnotes::closes(content, open) {
+                open_fence = None;
+            }
+        } else if let Some(opened) = footnotes::fence(content) {
+            open_fence = Some(opened);
+        } else {
+            for captures in pattern.captures_iter(line) {
+                let whole = captures.get(0).unwrap();
+                found.push((
+                    offset + whole.start(),
+                    offset + whole.end(),
+                    number + 1,
+                    !captures[1].is_empty(),
+                ));
+            }
+        }
+        offset += line.len();
+    }
+    found
+}
+
+pub fn regions(markdown: &str, path: &Path) -> Result<Vec<Region>, Diagnostic> {
+    let mut regions = Vec::new();
+    let mut open: Vec<(usize, usize, usize)> = Vec::new();
+    for (start, end, line, closing) in markers(markdown) {
+        if !closing {
+            open.push((start, end, line));
+            continue;
+        }
+        match open.pop() {
+            Some((start, _, start_line)) if open.is_empty() => regions.push(Region {
+                start_line,
+                end_line: line,
+                start,
+                end,
+            }),
+            Some(_) => {}
+            None => {
+                return Err(Diagnostic::error(
+                    "private-marker",
+                    path,
+                    line,
+                    "<!-- /private --> without an opening <!-- private -->",
+                )
+                .with_span(start..end))
+            }
+        }
+    }
+    match open.last() {
+        Some((start, end, line)) => Err(Diagnostic::error(
+            "private-marker",
+            path,
+            *line,
+            "<!-- private --> is never closed",
+        )
+        .with_span(*start..*end)),
+        None => Ok(regions),
+    }
+}
+
+// remove private regions, markers included. line breaks stay so later
+// messages still point at the right line
+pub fn strip(markdown: String, path: &Path) -> Result<String, Diagnostic> {
+    let regions = regions(&markdown, path)?;
+    if regions.is_empty() {
+        return Ok(markdown);
+    }
+    let mut output = String::with_capacity(markdown.len());
+    let mut last = 0;
+    for region in regions {
+        output.push_str(&markdown[last..region.start]);
+        output.extend(markdown[region.start..region.end].matches('\n'));
+        last = region.end;
+    }
+    output.push_str(&markdown[last..]);
+    Ok(output)
+}
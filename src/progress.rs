@@ -0,0 +1,65 @@
+use indicatif::{ProgressBar, ProgressStyle};
+use std::io::{self, IsTerminal};
+use std::sync::Mutex;
+
+const TEMPLATE: &str = "{bar:30} {pos}/{len} eta {eta} {wide_msg}";
+
+// how far a run over several documents got: a bar on a terminal, a line per
+// document otherwise, nothing when quiet. documents converted side by side
+// share it
+pub enum Progress {
+    Bar(ProgressBar),
+    Lines { done: Mutex<usize>, total: usize },
+    Silent,
+}
+
+impl Progress {
+    pub fn new(total: usize, quiet: bool) -> Progress {
+        if quiet || total < 2 {
+            return Progress::Silent;
+        }
+        if !io::stderr().is_terminal() {
+            return Progress::Lines {
+                done: Mutex::new(0),
+                total,
+            };
+        }
+        let bar = ProgressBar::new(total as u64);
+        bar.set_style(
+            ProgressStyle::with_template(TEMPLATE)
+                .expect("the template is valid")
+                .progress_chars("=> "),
+        );
+        Progress::Bar(bar)
+    }
+
+    pub fn start(&self, input: &str) {
+        if let Progress::Bar(bar) = self {
+            bar.set_message(input.to_string());
+        }
+    }
+
+    // count `input` as done, printing the messages it held back above the bar
+    pub fn done(&self, input: &str, messages: &str) {
+        match self {
+            Progress::Bar(bar) => {
+                if !messages.is_empty() {
+                    bar.suspend(|| eprint!("{}", messages));
+                }
+                bar.inc(1);
+            }
+            Progress::Lines { done, total } => {
+                let mut done = done.lock().expect("the count lock is never poisoned");
+                *done += 1;
+                eprintln!("{}[{}/{}] {}", messages, done, total, input);
+            }
+            Progress::Silent => eprint!("{}", messages),
+        }
+    }
+
+    pub fn finish(&self) {
+        if let Progress::Bar(bar) = self {
+            bar.finish_and_clear();
+        }
+    }
+}
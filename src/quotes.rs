@@ -0,0 +1,186 @@
+use comrak::nodes::{AstNode, NodeHtmlBlock, NodeValue};
+use comrak::{Arena, ComrakOptions};
+
+// put in front of a page with cited quotes
+pub const STYLE: &str = "<style>
+figure.quote { margin: 0 0 16px; }
+figure.quote blockquote { margin-bottom: 0; }
+figure.quote figcaption { padding: 0 1em; color: #57606a; }
+figure.quote figcaption::before { content: \"\\2014\\00A0\"; }
+@media print {
+  figure.quote { break-inside: avoid; }
+}
+</style>
+";
+
+fn html_block<'a>(arena: &'a Arena<AstNode<'a>>, html: &str) -> &'a AstNode<'a> {
+    arena.alloc(AstNode::from(NodeValue::HtmlBlock(NodeHtmlBlock {
+        block_type: 0,
+        literal: html.as_bytes().to_vec(),
+    })))
+}
+
+fn is_break(node: &AstNode) -> bool {
+    matches!(
+        node.data.borrow().value,
+        NodeValue::SoftBreak | NodeValue::LineBreak
+    )
+}
+
+// the text of an attribution line without its leading `—` or `--`
+fn attribution(text: &str) -> Option<&str> {
+    let rest = text.strip_prefix('—').or_else(|| text.strip_prefix("--"))?;
+    if !rest.starts_with(char::is_whitespace) {
+        return None;
+    }
+    Some(rest.trim_start())
+}
+
+// take the attribution line off the end of a quote, returning the nodes of
+// its text. the quote has to keep something of its own
+fn take_attribution<'a>(quote: &'a AstNode<'a>) -> Option<Vec<&'a AstNode<'a>>> {
+    let paragraph = quote.last_child()?;
+    if !matches!(paragraph.data.borrow().value, NodeValue::Paragraph) {
+        return None;
+    }
+    let inlines: Vec<&'a AstNode<'a>> = paragraph.children().collect();
+    let start = inlines
+        .iter()
+        .rposition(|node| is_break(node))
+        .map_or(0, |position| position + 1);
+    let line = &inlines[start..];
+    if start == 0 && paragraph.previous_sibling().is_none() {
+        return None;
+    }
+    let first = line.first()?;
+    {
+        let mut data = first.data.borrow_mut();
+        let literal = match data.value {
+            NodeValue::Text(ref mut literal) => literal,
+            _ => return None,
+        };
+        let text = String::from_utf8_lossy(literal).to_string();
+        let rest = attribution(&text)?;
+        if rest.is_empty() && line.len() == 1 {
+            return None;
+        }
+        *literal = rest.as_bytes().to_vec();
+    }
+    if start > 0 {
+        inlines[start - 1].detach();
+    }
+    for node in line {
+        node.detach();
+    }
+    if paragraph.first_child().is_none() {
+        paragraph.detach();
+    }
+    Some(line.to_vec())
+}
+
+// blockquotes ending in an attribution line (`— Ada Lovelace, 1843` or
+// `-- Ada Lovelace`) become a figure with the line as its caption. nested
+// quotes and quotes without one are left as they are. true if any was cited,
+// the page then needs `STYLE`
+pub fn cite<'a>(
+    arena: &'a Arena<AstNode<'a>>,
+    root: &'a AstNode<'a>,
+    options: &ComrakOptions,
+) -> bool {
+    let quotes: Vec<&'a AstNode<'a>> = root
+        .descendants()
+        .filter(|node| matches!(node.data.borrow().value, NodeValue::BlockQuote))
+        .filter(|node| {
+            !node
+                .ancestors()
+                .skip(1)
+                .any(|ancestor| matches!(ancestor.data.borrow().value, NodeValue::BlockQuote))
+        })
+        .collect();
+    let mut cited = false;
+    for quote in quotes {
+        let line = match take_attribution(quote) {
+            Some(line) => line,
+            None => continue,
+        };
+        // comrak renders a paragraph only as part of a document
+        let document = arena.alloc(AstNode::from(NodeValue::Document));
+        let caption = arena.alloc(AstNode::from(NodeValue::Paragraph));
+        document.append(caption);
+        for node in line {
+            caption.append(node);
+        }
+        let mut html = vec![];
+        comrak::format_html(document, options, &mut html).expect("Unable to render html");
+        let html = String::from_utf8_lossy(&html);
+        let text = html
+            .trim_end()
+            .trim_start_matches("<p>")
+            .trim_end_matches("</p>");
+        quote.insert_before(html_block(arena, "<figure class=\"quote\">\n"));
+        quote.insert_after(html_block(
+            arena,
+            &format!("<figcaption>{}</figcaption>\n</figure>\n", text),
+        ));
+        cited = true;
+    }
+    cited
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn render(markdown: &str) -> String {
+        let mut options = ComrakOptions::default();
+        options.render.unsafe_ = true;
+        let arena = Arena::new();
+        let root = comrak::parse_document(&arena, markdown, &options);
+        cite(&arena, root, &options);
+        let mut html = vec![];
+        comrak::format_html(root, &options, &mut html).unwrap();
+        String::from_utf8(html).unwrap()
+    }
+
+    #[test]
+    fn attribution_lines_become_captions() {
+        assert_eq!(
+            render("> Imagination is the discovering faculty.\n> — Ada Lovelace, 1843\n"),
+            "<figure class=\"quote\">\n<blockquote>\n\
+             <p>Imagination is the discovering faculty.</p>\n</blockquote>\n\
+             <figcaption>Ada Lovelace, 1843</figcaption>\n</figure>\n"
+        );
+    }
+
+    #[test]
+    fn multi_paragraph_quotes_keep_every_paragraph() {
+        assert_eq!(
+            render("> First.\n>\n> Second.\n>\n> -- Someone\n"),
+            "<figure class=\"quote\">\n<blockquote>\n<p>First.</p>\n<p>Second.</p>\n</blockquote>\n\
+             <figcaption>Someone</figcaption>\n</figure>\n"
+        );
+    }
+
+    #[test]
+    fn attributions_keep_their_links() {
+        assert_eq!(
+            render("> Quoted.\n> — [Ada](https://example.com/ada), *Notes*\n"),
+            "<figure class=\"quote\">\n<blockquote>\n<p>Quoted.</p>\n</blockquote>\n\
+             <figcaption><a href=\"https://example.com/ada\">Ada</a>, <em>Notes</em></figcaption>\n\
+             </figure>\n"
+        );
+    }
+
+    #[test]
+    fn other_quotes_are_left_alone() {
+        for markdown in [
+            "> Just a quote.\n",
+            "> — only an attribution\n",
+            "> Outer.\n>\n> > Inner.\n> > — Nested\n>\n> More.\n",
+            "> A range 1--2 is not one.\n",
+        ] {
+            let html = render(markdown);
+            assert!(!html.contains("figure"), "{}", html);
+        }
+    }
+}
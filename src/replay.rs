@@ -0,0 +1,102 @@
+use crate::paths;
+use serde_json::{json, Value};
+use std::fs;
+use std::path::Path;
+
+// keep in step with Cargo.toml, cargo does not tell a crate the versions of
+// its dependencies
+pub const COMRAK_VERSION: &str = "0.15";
+const YAMC_VERSION: &str = env!("CARGO_PKG_VERSION");
+
+// flags that never go into a dump: hooks are commands that may carry
+// secrets and run anything on the machine replaying them
+const REDACTED: &[&str] = &["--pre-hook", "--post-hook"];
+// flags about dumping and replaying themselves. the options of a config file
+// are recorded as they are, so the file is not needed to replay them
+const OWN: &[&str] = &["--dump-effective-config", "--replay", "--config"];
+
+// the option arguments of a run, without inputs, in a form `load` can replay.
+// `features` and `comrak_options` are only there for whoever reads the file
+pub fn dump(
+    path: &Path,
+    arguments: &[String],
+    features: &[&str],
+    comrak_options: &comrak::ComrakOptions,
+) -> Result<(), String> {
+    let mut kept = Vec::new();
+    let mut redacted = Vec::new();
+    let mut arguments = arguments.iter();
+    while let Some(argument) = arguments.next() {
+        // `--flag value` or `--flag=value`
+        let (flag, inline) = match argument.split_once('=') {
+            Some((flag, _)) => (flag, true),
+            None => (argument.as_str(), false),
+        };
+        if REDACTED.contains(&flag) || OWN.contains(&flag) {
+            if !inline {
+                arguments.next();
+            }
+            if REDACTED.contains(&flag) {
+                redacted.push(flag.to_string());
+            }
+        } else {
+            kept.push(argument.clone());
+        }
+    }
+    let dump = json!({
+        "yamc": YAMC_VERSION,
+        "comrak": COMRAK_VERSION,
+        "arguments": kept,
+        "redacted": redacted,
+        "features": features,
+        "comrak_options": format!("{:?}", comrak_options),
+    });
+    let text = serde_json::to_string_pretty(&dump).expect("Dump should serialize");
+    fs::write(path, text + "\n")
+        .map_err(|err| format!("cannot write {}: {}", paths::display(path), err))
+}
+
+// the option arguments recorded by `dump`, with a warning for every version
+// that differs from this build
+pub fn load(path: &Path) -> Result<(Vec<String>, Vec<String>), String> {
+    let text = fs::read_to_string(path)
+        .map_err(|err| format!("cannot read {}: {}", paths::display(path), err))?;
+    let dump: Value = serde_json::from_str(&text).map_err(|err| {
+        format!(
+            "{} is not a yamc config dump: {}",
+            paths::display(path),
+            err
+        )
+    })?;
+    let arguments = dump["arguments"]
+        .as_array()
+        .and_then(|arguments| {
+            arguments
+                .iter()
+                .map(|argument| argument.as_str().map(str::to_string))
+                .collect::<Option<Vec<String>>>()
+        })
+        .ok_or_else(|| format!("{} has no list of arguments", paths::display(path)))?;
+    let mut warnings = Vec::new();
+    for (name, version) in [("yamc", YAMC_VERSION), ("comrak", COMRAK_VERSION)] {
+        let recorded = dump[name].as_str().unwrap_or("unknown");
+        if recorded != version {
+            warnings.push(format!(
+                "{} was dumped with {} {}, this is {} {}; output may differ",
+                paths::display(path),
+                name,
+                recorded,
+                name,
+                version
+            ));
+        }
+    }
+    for flag in dump["redacted"].as_array().into_iter().flatten() {
+        warnings.push(format!(
+            "{} {} was left out of the dump and is not replayed",
+            paths::display(path),
+            flag.as_str().unwrap_or("?")
+        ));
+    }
+    Ok((arguments, warnings))
+}
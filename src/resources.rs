@@ -0,0 +1,228 @@
+use crate::collate;
+use comrak::nodes::{AstNode, NodeValue};
+use std::fmt;
+
+#[derive(Clone)]
+pub struct Resource {
+    pub url: String,
+    pub kind: &'static str,
+}
+
+impl Resource {
+    pub fn new(url: &str, kind: &'static str) -> Resource {
+        Resource {
+            url: url.to_string(),
+            kind,
+        }
+    }
+}
+
+// absolute urls of links and images, relative references stay on our side
+pub fn collect<'a>(root: &'a AstNode<'a>) -> Vec<Resource> {
+    root.descendants()
+        .filter_map(|node| {
+            let (url, kind) = match node.data.borrow().value {
+                NodeValue::Link(ref link) => (link.url.clone(), "link"),
+                NodeValue::Image(ref link) => (link.url.clone(), "image"),
+                _ => return None,
+            };
+            let url = String::from_utf8_lossy(&url).to_string();
+            domain(&url).map(|_| Resource { url, kind })
+        })
+        .collect()
+}
+
+// the host of an absolute url, without credentials or port
+pub fn domain(url: &str) -> Option<String> {
+    let (scheme, rest) = url.split_once("://")?;
+    if scheme.is_empty()
+        || !scheme
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "+-.".contains(c))
+    {
+        return None;
+    }
+    let authority = rest.split(['/', '?', '#']).next()?;
+    let host = authority.rsplit('@').next()?;
+    let host = match host.strip_prefix('[') {
+        Some(ipv6) => ipv6.split(']').next()?,
+        None => host.split(':').next()?,
+    };
+    if host.is_empty() {
+        None
+    } else {
+        Some(host.to_ascii_lowercase())
+    }
+}
+
+const SECRET_PARAMETERS: &[&str] = &["password", "passwd", "secret", "token", "apikey", "api_key"];
+
+fn warnings(url: &str) -> Vec<&'static str> {
+    let mut warnings = Vec::new();
+    let lower = url.to_ascii_lowercase();
+    if lower.starts_with("http://") {
+        warnings.push("not served over TLS");
+    }
+    let authority = url
+        .split_once("://")
+        .map(|(_, rest)| rest.split(['/', '?', '#']).next().unwrap_or(""))
+        .unwrap_or("");
+    let query = lower.split_once('?').map(|(_, query)| query).unwrap_or("");
+    let secret_query = query.split('&').any(|pair| {
+        let name = pair.split('=').next().unwrap_or("");
+        SECRET_PARAMETERS.contains(&name)
+    });
+    if authority.contains('@') || secret_query {
+        warnings.push("contains credentials");
+    }
+    warnings
+}
+
+// `*` matches any run of characters and `?` a single one
+pub fn glob_match(pattern: &str, text: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let text: Vec<char> = text.chars().collect();
+    let (mut p, mut t) = (0, 0);
+    let mut backtrack: Option<(usize, usize)> = None;
+    while t < text.len() {
+        if p < pattern.len() && (pattern[p] == '?' || pattern[p] == text[t]) {
+            p += 1;
+            t += 1;
+        } else if p < pattern.len() && pattern[p] == '*' {
+            backtrack = Some((p, t));
+            p += 1;
+        } else if let Some((star, matched)) = backtrack {
+            p = star + 1;
+            t = matched + 1;
+            backtrack = Some((star, matched + 1));
+        } else {
+            return false;
+        }
+    }
+    pattern[p..].iter().all(|c| *c == '*')
+}
+
+struct Entry {
+    url: String,
+    kind: &'static str,
+    count: usize,
+    warnings: Vec<&'static str>,
+    denied_by: Option<String>,
+}
+
+struct Domain {
+    name: String,
+    count: usize,
+    entries: Vec<Entry>,
+}
+
+// resources grouped by domain, sorted by domain name, urls in document order
+pub struct Report {
+    domains: Vec<Domain>,
+}
+
+impl Report {
+    pub fn new(resources: &[Resource], deny: &[String]) -> Report {
+        let mut domains: Vec<Domain> = Vec::new();
+        for resource in resources {
+            let name = match domain(&resource.url) {
+                Some(name) => name,
+                None => continue,
+            };
+            let index = match domains.iter().position(|domain| domain.name == name) {
+                Some(index) => index,
+                None => {
+                    domains.push(Domain {
+                        name: name.clone(),
+                        count: 0,
+                        entries: Vec::new(),
+                    });
+                    domains.len() - 1
+                }
+            };
+            let domain = &mut domains[index];
+            domain.count += 1;
+            match domain
+                .entries
+                .iter_mut()
+                .find(|entry| entry.url == resource.url && entry.kind == resource.kind)
+            {
+                Some(entry) => entry.count += 1,
+                None => domain.entries.push(Entry {
+                    url: resource.url.clone(),
+                    kind: resource.kind,
+                    count: 1,
+                    warnings: warnings(&resource.url),
+                    denied_by: deny
+                        .iter()
+                        .find(|pattern| glob_match(&pattern.to_ascii_lowercase(), &name))
+                        .cloned(),
+                }),
+            }
+        }
+        domains.sort_by(|a, b| collate::compare(&a.name, &b.name));
+        Report { domains }
+    }
+
+    pub fn denied(&self) -> bool {
+        self.entries().any(|entry| entry.denied_by.is_some())
+    }
+
+    fn entries(&self) -> impl Iterator<Item = &Entry> {
+        self.domains.iter().flat_map(|domain| domain.entries.iter())
+    }
+
+    pub fn to_json(&self) -> String {
+        let domains: Vec<serde_json::Value> = self
+            .domains
+            .iter()
+            .map(|domain| {
+                let resources: Vec<serde_json::Value> = domain
+                    .entries
+                    .iter()
+                    .map(|entry| {
+                        serde_json::json!({
+                            "url": entry.url,
+                            "kind": entry.kind,
+                            "count": entry.count,
+                            "warnings": entry.warnings,
+                            "denied_by": entry.denied_by,
+                        })
+                    })
+                    .collect();
+                serde_json::json!({
+                    "domain": domain.name,
+                    "count": domain.count,
+                    "resources": resources,
+                })
+            })
+            .collect();
+        serde_json::to_string_pretty(&serde_json::json!({
+            "domains": domains,
+            "denied": self.denied(),
+        }))
+        .expect("Resource report should serialize")
+    }
+}
+
+impl fmt::Display for Report {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for domain in &self.domains {
+            writeln!(f, "{} ({})", domain.name, domain.count)?;
+            for entry in &domain.entries {
+                write!(f, "  {} [{}]", entry.url, entry.kind)?;
+                if entry.count > 1 {
+                    write!(f, " x{}", entry.count)?;
+                }
+                writeln!(f)?;
+                for warning in &entry.warnings {
+                    writeln!(f, "    Warning : {}", warning)?;
+                }
+                if let Some(pattern) = &entry.denied_by {
+                    writeln!(f, "    Error : domain matches --deny-domain {}", pattern)?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,94 @@
+use ammonia::Builder;
+use std::collections::HashSet;
+
+// the attributes the kept markup can hold a url in
+const URL_ATTRIBUTES: &[&str] = &["href", "src", "cite", "longdesc"];
+
+// the scheme of `url` in lowercase, the way a browser reads it: spaces
+// around it and tabs or newlines inside it do not count
+fn scheme(url: &str) -> String {
+    let url: String = url.chars().filter(|c| !c.is_ascii_whitespace()).collect();
+    match url.split_once(':') {
+        Some((scheme, _)) if !scheme.contains('/') => scheme.to_ascii_lowercase(),
+        _ => String::new(),
+    }
+}
+
+// whether the data: url `url` holds an image
+fn is_image(url: &str) -> bool {
+    let url: String = url.chars().filter(|c| !c.is_ascii_whitespace()).collect();
+    url.to_ascii_lowercase().starts_with("data:image/")
+}
+
+// what --sanitize keeps: the markup github-markdown-css styles and yamc's own
+// passes write, ids and classes included so links to headings keep working.
+// scripts, event handlers, iframes and urls other than http(s), mailto and
+// relative ones are removed, except the data: images --embed-images and
+// --fetch-images put into img src. comments stay, yamc's later passes read
+// some
+pub fn clean(html: &str) -> String {
+    Builder::default()
+        .add_tags(["input", "section"])
+        .add_generic_attributes(["id", "class", "aria-hidden"])
+        .add_tag_attributes("input", ["type", "checked", "disabled"])
+        .add_tag_attributes("details", ["open"])
+        // the search box of an enhanced table
+        .add_tag_attributes("div", ["data-filter"])
+        // the colours of highlighted code blocks
+        .add_tag_attributes("pre", ["style"])
+        .add_tag_attributes("span", ["style"])
+        .filter_style_properties(HashSet::from([
+            "background-color",
+            "color",
+            "font-style",
+            "font-weight",
+            "text-decoration",
+        ]))
+        .url_schemes(HashSet::from(["http", "https", "mailto", "data"]))
+        // a task list's checkbox, not a text field, and data: urls only for
+        // the source of an image
+        .attribute_filter(|element, attribute, value| {
+            let data = URL_ATTRIBUTES.contains(&attribute) && scheme(value) == "data";
+            match (element, attribute) {
+                ("input", "type") if value != "checkbox" => None,
+                ("img", "src") if data && !is_image(value) => None,
+                ("img", "src") => Some(value.into()),
+                _ if data => None,
+                _ => Some(value.into()),
+            }
+        })
+        // the anchors of headings have to stay as comrak wrote them
+        .link_rel(None)
+        .strip_comments(false)
+        .clean(html)
+        .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const IMAGE: &str = "data:image/png;base64,iVBORw0KGgo=";
+
+    #[test]
+    fn images_keep_their_data_urls() {
+        let html = format!("<p><img src=\"{}\" alt=\"x\"></p>", IMAGE);
+        assert_eq!(clean(&html), html);
+    }
+
+    #[test]
+    fn other_data_urls_go() {
+        assert_eq!(
+            clean("<img src=\"data:text/html;base64,PHNjcmlwdD4=\" alt=\"x\">"),
+            "<img alt=\"x\">"
+        );
+        assert_eq!(clean(&format!("<a href=\"{}\">x</a>", IMAGE)), "<a>x</a>");
+        assert_eq!(clean("<a href=\"DA\tTA:text/html,x\">x</a>"), "<a>x</a>");
+    }
+
+    #[test]
+    fn enhanced_tables_keep_their_filter() {
+        let html = "<div class=\"yamc-table\" data-filter=\"\"><table></table></div>";
+        assert_eq!(clean(html), html);
+    }
+}
@@ -0,0 +1,248 @@
+use std::fs;
+use std::io::{self, BufRead, BufReader, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::path::{Component, Path, PathBuf};
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread;
+use std::time::Duration;
+
+// the served page listens here for word that it changed
+const EVENTS: &str = "/__yamc/events";
+
+// put at the end of the served page. the browser reconnects on its own when
+// the server comes back
+pub const RELOAD: &str = "<script>new EventSource(\"/__yamc/events\").onmessage = function () { location.reload(); };</script>\n";
+
+// a page that is waiting for news says something this often, so that a
+// closed tab frees its connection
+const HEARTBEAT: Duration = Duration::from_secs(15);
+
+// a client that sends nothing for this long is dropped
+const READ_TIMEOUT: Duration = Duration::from_secs(10);
+
+const TYPES: &[(&str, &str)] = &[
+    ("html", "text/html; charset=utf-8"),
+    ("htm", "text/html; charset=utf-8"),
+    ("css", "text/css; charset=utf-8"),
+    ("js", "text/javascript; charset=utf-8"),
+    ("json", "application/json"),
+    ("txt", "text/plain; charset=utf-8"),
+    ("md", "text/plain; charset=utf-8"),
+    ("png", "image/png"),
+    ("jpg", "image/jpeg"),
+    ("jpeg", "image/jpeg"),
+    ("gif", "image/gif"),
+    ("svg", "image/svg+xml"),
+    ("webp", "image/webp"),
+    ("avif", "image/avif"),
+    ("ico", "image/x-icon"),
+    ("pdf", "application/pdf"),
+    ("woff", "font/woff"),
+    ("woff2", "font/woff2"),
+    ("mp4", "video/mp4"),
+    ("webm", "video/webm"),
+];
+
+// the rendered page, kept in memory, and how many times it was replaced
+pub struct Page {
+    current: Mutex<(u64, String)>,
+    changed: Condvar,
+}
+
+impl Page {
+    pub fn new(html: String) -> Page {
+        Page {
+            current: Mutex::new((0, html)),
+            changed: Condvar::new(),
+        }
+    }
+
+    // put in a new rendering and tell the open pages to reload
+    pub fn replace(&self, html: String) {
+        let mut current = self
+            .current
+            .lock()
+            .expect("the page lock is never poisoned");
+        current.0 += 1;
+        current.1 = html;
+        self.changed.notify_all();
+    }
+}
+
+// listen on `port` of the loopback interface and answer from another thread:
+// `/` is the page, anything else a file below `root`. the listener goes away
+// with the process
+pub fn start(port: u16, page: Arc<Page>, root: PathBuf) -> io::Result<SocketAddr> {
+    let listener = TcpListener::bind(("127.0.0.1", port))?;
+    let address = listener.local_addr()?;
+    let root = fs::canonicalize(&root).unwrap_or(root);
+    thread::spawn(move || {
+        for stream in listener.incoming().flatten() {
+            let page = Arc::clone(&page);
+            let root = root.clone();
+            thread::spawn(move || {
+                // a client going away is nothing to report
+                let _ = answer(stream, &page, &root);
+            });
+        }
+    });
+    Ok(address)
+}
+
+fn answer(mut stream: TcpStream, page: &Page, root: &Path) -> io::Result<()> {
+    stream.set_read_timeout(Some(READ_TIMEOUT))?;
+    let mut reader = BufReader::new(stream.try_clone()?);
+    let mut request = String::new();
+    reader.read_line(&mut request)?;
+    // nothing in the headers changes the answer
+    loop {
+        let mut line = String::new();
+        if reader.read_line(&mut line)? == 0 || line.trim().is_empty() {
+            break;
+        }
+    }
+    let mut words = request.split_whitespace();
+    let method = words.next().unwrap_or_default();
+    let target = words.next().unwrap_or_default();
+    if method != "GET" && method != "HEAD" {
+        return respond(
+            &mut stream,
+            "405 Method Not Allowed",
+            "text/plain",
+            b"",
+            false,
+        );
+    }
+    let head = method == "HEAD";
+    let path = match target.split(['?', '#']).next().and_then(decode) {
+        Some(path) => path,
+        None => return respond(&mut stream, "400 Bad Request", "text/plain", b"", head),
+    };
+    if path == "/" {
+        let html = page
+            .current
+            .lock()
+            .expect("the page lock is never poisoned")
+            .1
+            .clone();
+        return respond(
+            &mut stream,
+            "200 OK",
+            "text/html; charset=utf-8",
+            html.as_bytes(),
+            head,
+        );
+    }
+    if path == EVENTS {
+        return events(stream, page);
+    }
+    match asset(root, &path).and_then(|file| Some((fs::read(&file).ok()?, file))) {
+        Some((bytes, file)) => respond(&mut stream, "200 OK", content_type(&file), &bytes, head),
+        None => respond(
+            &mut stream,
+            "404 Not Found",
+            "text/plain; charset=utf-8",
+            format!("{} not found\n", path).as_bytes(),
+            head,
+        ),
+    }
+}
+
+fn respond(
+    stream: &mut TcpStream,
+    status: &str,
+    content_type: &str,
+    body: &[u8],
+    head: bool,
+) -> io::Result<()> {
+    write!(
+        stream,
+        "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nCache-Control: no-store\r\nConnection: close\r\n\r\n",
+        status,
+        content_type,
+        body.len()
+    )?;
+    if !head {
+        stream.write_all(body)?;
+    }
+    stream.flush()
+}
+
+// server-sent events: a message each time the page is replaced
+fn events(mut stream: TcpStream, page: &Page) -> io::Result<()> {
+    stream.write_all(
+        b"HTTP/1.1 200 OK\r\nContent-Type: text/event-stream\r\nCache-Control: no-store\r\n\r\n",
+    )?;
+    stream.flush()?;
+    let mut seen = page
+        .current
+        .lock()
+        .expect("the page lock is never poisoned")
+        .0;
+    loop {
+        let current = page
+            .current
+            .lock()
+            .expect("the page lock is never poisoned");
+        let (current, _) = page
+            .changed
+            .wait_timeout_while(current, HEARTBEAT, |current| current.0 == seen)
+            .expect("the page lock is never poisoned");
+        let message = if current.0 == seen {
+            ": waiting\n\n"
+        } else {
+            seen = current.0;
+            "data: reload\n\n"
+        };
+        drop(current);
+        stream.write_all(message.as_bytes())?;
+        stream.flush()?;
+    }
+}
+
+// the path of a request with its %XX escapes undone
+fn decode(path: &str) -> Option<String> {
+    let bytes = path.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut index = 0;
+    while index < bytes.len() {
+        if bytes[index] == b'%' {
+            let hex = std::str::from_utf8(bytes.get(index + 1..index + 3)?).ok()?;
+            decoded.push(u8::from_str_radix(hex, 16).ok()?);
+            index += 3;
+        } else {
+            decoded.push(bytes[index]);
+            index += 1;
+        }
+    }
+    String::from_utf8(decoded).ok()
+}
+
+// the file below `root` a request path names. `..`, and links leading out
+// of `root`, name nothing
+fn asset(root: &Path, path: &str) -> Option<PathBuf> {
+    let relative = Path::new(path.trim_start_matches('/'));
+    if !relative
+        .components()
+        .all(|component| matches!(component, Component::Normal(_)))
+    {
+        return None;
+    }
+    let file = fs::canonicalize(root.join(relative)).ok()?;
+    if !file.starts_with(root) || !file.is_file() {
+        return None;
+    }
+    Some(file)
+}
+
+fn content_type(file: &Path) -> &'static str {
+    let extension = file
+        .extension()
+        .and_then(|extension| extension.to_str())
+        .unwrap_or_default()
+        .to_ascii_lowercase();
+    TYPES
+        .iter()
+        .find(|(known, _)| *known == extension)
+        .map_or("application/octet-stream", |(_, content_type)| content_type)
+}
@@ -0,0 +1,125 @@
+use crate::explain;
+use comrak::nodes::{AstNode, NodeHtmlBlock, NodeValue};
+use comrak::Arena;
+use regex::Regex;
+
+// put a marker comment in front of every top-level block, `annotate` turns it
+// into a data-source-line attribute on the element the block renders to
+pub fn mark<'a>(arena: &'a Arena<AstNode<'a>>, root: &'a AstNode<'a>) {
+    let blocks: Vec<_> = root
+        .children()
+        .filter(|node| {
+            !matches!(
+                node.data.borrow().value,
+                NodeValue::FrontMatter(..) | NodeValue::FootnoteDefinition(..)
+            )
+        })
+        .collect();
+    for block in blocks {
+        let line = explain::start_line(block);
+        if line == 0 {
+            continue;
+        }
+        let marker = arena.alloc(AstNode::from(NodeValue::HtmlBlock(NodeHtmlBlock {
+            block_type: 0,
+            literal: format!("<!-- yamc-source-line {} -->\n", line).into_bytes(),
+        })));
+        block.insert_before(marker);
+    }
+}
+
+pub fn annotate(html: String) -> String {
+    let tagged = Regex::new(r"<!-- yamc-source-line (\d+) -->\n<([a-zA-Z][a-zA-Z0-9]*)")
+        .expect("marker pattern should compile");
+    let html = tagged.replace_all(&html, "<$2 data-source-line=\"$1\"");
+    // blocks that rendered to something other than an element
+    let leftover =
+        Regex::new(r"<!-- yamc-source-line \d+ -->\n").expect("marker pattern should compile");
+    leftover.replace_all(&html, "").into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // front matter, so the lines are the file's and not comrak's
+    const DOCUMENT: &str = "---
+title: t
+---
+# Title
+
+- one
+  - nested
+    - deeper
+
+```rust
+fn main() {}
+```
+";
+
+    fn with_root<T>(work: impl for<'a> FnOnce(&'a Arena<AstNode<'a>>, &'a AstNode<'a>) -> T) -> T {
+        let mut options = comrak::ComrakOptions::default();
+        options.extension.front_matter_delimiter = Some("---".to_string());
+        options.render.unsafe_ = true;
+        let arena = Arena::new();
+        let root = comrak::parse_document(&arena, DOCUMENT, &options);
+        work(&arena, root)
+    }
+
+    // the blocks `explain` finds at `line`, outermost first
+    fn covering(line: u32) -> Vec<String> {
+        with_root(|_, root| {
+            explain::covering(root, line)
+                .into_iter()
+                .map(explain::describe_node)
+                .collect()
+        })
+    }
+
+    #[test]
+    fn top_level_blocks_carry_their_file_line() {
+        let html = with_root(|arena, root| {
+            mark(arena, root);
+            let mut options = comrak::ComrakOptions::default();
+            options.render.unsafe_ = true;
+            let mut html = vec![];
+            comrak::format_html(root, &options, &mut html).unwrap();
+            annotate(String::from_utf8(html).unwrap())
+        });
+        assert_eq!(
+            html,
+            "<h1 data-source-line=\"4\">Title</h1>\n\
+             <ul data-source-line=\"6\">\n<li>one\n<ul>\n<li>nested\n<ul>\n<li>deeper</li>\n\
+             </ul>\n</li>\n</ul>\n</li>\n</ul>\n\
+             <pre data-source-line=\"10\"><code class=\"language-rust\">fn main() {}\n</code></pre>\n"
+        );
+    }
+
+    #[test]
+    fn lines_in_nested_lists_map_to_every_level() {
+        assert_eq!(
+            covering(8),
+            [
+                "list Bullet tight=true [6-9]",
+                "item [6-9]",
+                "list Bullet tight=true [7-9]",
+                "item [7-9]",
+                "list Bullet tight=true [8-9]",
+                "item [8-9]",
+                "paragraph [8-9]",
+            ]
+        );
+        assert_eq!(
+            covering(6)[..2],
+            ["list Bullet tight=true [6-9]", "item [6-9]"]
+        );
+    }
+
+    #[test]
+    fn lines_in_fences_map_to_the_code_block() {
+        let fence = covering(11);
+        assert_eq!(fence.len(), 1);
+        assert!(fence[0].starts_with("code_block"), "{:?}", fence);
+        assert!(fence[0].ends_with(" [10-]"), "{:?}", fence);
+    }
+}
@@ -0,0 +1,37 @@
+use sha2::{Digest, Sha256};
+
+const PREFIX: &str = "<!-- yamc:sha256:";
+const SUFFIX: &str = " -->\n";
+
+pub fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+// the comment that ends a generated document, a hash of everything written
+// before it
+pub fn stamp<S: AsRef<str>>(parts: &[S]) -> String {
+    let mut hasher = Sha256::new();
+    for part in parts {
+        hasher.update(part.as_ref().as_bytes());
+    }
+    format!("{}{}{}", PREFIX, hex(&hasher.finalize()), SUFFIX)
+}
+
+// check that a document still matches the stamp at its end, returning the hash
+pub fn verify(document: &str) -> Result<String, String> {
+    let start = document
+        .rfind(PREFIX)
+        .ok_or_else(|| "no yamc:sha256 stamp".to_string())?;
+    let recorded = document[start + PREFIX.len()..]
+        .strip_suffix(SUFFIX)
+        .filter(|hash| hash.len() == 64 && hash.bytes().all(|c| c.is_ascii_hexdigit()))
+        .ok_or_else(|| "the yamc:sha256 stamp is not the end of the document".to_string())?;
+    let actual = hex(&Sha256::digest(&document.as_bytes()[..start]));
+    if actual != recorded {
+        return Err(format!(
+            "content does not match its stamp: stamped {}, content hashes to {}",
+            recorded, actual
+        ));
+    }
+    Ok(actual)
+}
@@ -0,0 +1,94 @@
+use crate::front_matter;
+use crate::inline;
+use crate::math;
+use comrak::nodes::{AstNode, NodeValue};
+
+pub const DEFAULT_LENGTH: usize = 200;
+
+// the text of a node without any markup
+pub fn plain_text<'a>(node: &'a AstNode<'a>) -> String {
+    let mut text = String::new();
+    for descendant in node.descendants() {
+        match descendant.data.borrow().value {
+            // subscripts are still marked when the summary is taken
+            NodeValue::Text(ref literal) => {
+                text.push_str(&String::from_utf8_lossy(literal).replace(inline::SUBSCRIPT, ""))
+            }
+            // so are formulas
+            NodeValue::Code(ref code) => {
+                text.push_str(&String::from_utf8_lossy(&code.literal).replace(math::MARK, ""))
+            }
+            NodeValue::SoftBreak | NodeValue::LineBreak => text.push(' '),
+            NodeValue::Paragraph | NodeValue::Heading(..) if !text.is_empty() => text.push(' '),
+            _ => {}
+        }
+    }
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+fn is_more_marker<'a>(node: &'a AstNode<'a>) -> bool {
+    match node.data.borrow().value {
+        NodeValue::HtmlBlock(ref block) => {
+            let literal = String::from_utf8_lossy(&block.literal);
+            literal
+                .trim()
+                .strip_prefix("<!--")
+                .and_then(|c| c.strip_suffix("-->"))
+                .is_some_and(|comment| comment.trim() == "more")
+        }
+        _ => false,
+    }
+}
+
+// cut at a word boundary so no word is split in half
+fn truncate(text: &str, length: usize) -> String {
+    if text.chars().count() <= length {
+        return text.to_string();
+    }
+    let cut: String = text.chars().take(length).collect();
+    let cut = match cut.rfind(char::is_whitespace) {
+        Some(end) => &cut[..end],
+        None => cut.as_str(),
+    };
+    format!(
+        "{}…",
+        cut.trim_end_matches(|c: char| c.is_whitespace() || ",;:".contains(c))
+    )
+}
+
+// a short plain text summary of the document: the `summary` or
+// `description` key of its (merged) front matter, everything before a `<!-- more -->` marker, or the
+// first paragraph cut down to `length` characters. has to run before the
+// marker is omitted
+pub fn extract<'a>(
+    root: &'a AstNode<'a>,
+    front_matter: Option<&str>,
+    length: usize,
+) -> Option<String> {
+    if let Some(summary) = front_matter.and_then(|raw| {
+        front_matter::value(raw, "summary").or_else(|| front_matter::value(raw, "description"))
+    }) {
+        return Some(summary);
+    }
+    if let Some(marker) = root.children().find(|node| is_more_marker(node)) {
+        let before: Vec<String> = root
+            .children()
+            .take_while(|node| !node.same_node(marker))
+            .filter(|node| !matches!(node.data.borrow().value, NodeValue::FrontMatter(..)))
+            .map(plain_text)
+            .filter(|text| !text.is_empty())
+            .collect();
+        if !before.is_empty() {
+            return Some(before.join(" "));
+        }
+    }
+    let paragraph = root
+        .children()
+        .find(|node| matches!(node.data.borrow().value, NodeValue::Paragraph))?;
+    let text = plain_text(paragraph);
+    if text.is_empty() {
+        None
+    } else {
+        Some(truncate(&text, length))
+    }
+}
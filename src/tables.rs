@@ -0,0 +1,222 @@
+use comrak::nodes::{AstNode, NodeHtmlBlock, NodeValue};
+use comrak::Arena;
+
+// tables with more body rows than this get the enhancements by default
+pub const DEFAULT_THRESHOLD: usize = 50;
+
+#[derive(Clone)]
+pub struct TableOptions {
+    pub enabled: bool,
+    pub threshold: usize,
+    pub filter: bool,
+}
+
+// what to do with a single table, in document order
+#[derive(Clone, Copy, PartialEq)]
+pub enum Plan {
+    Plain,
+    Enhanced { filter: bool },
+}
+
+// inline style and script, added once to any page containing enhanced tables
+pub const ASSETS: &str = r#"<style>
+.yamc-table { max-height: 80vh; overflow: auto; margin-bottom: 16px; }
+.yamc-table table { display: table; overflow: visible; margin-bottom: 0; }
+.yamc-table thead th { position: sticky; top: 0; background: var(--yamc-table-header-bg, #f6f8fa); cursor: pointer; }
+.yamc-table thead th[aria-sort="ascending"]::after { content: " \25B2"; }
+.yamc-table thead th[aria-sort="descending"]::after { content: " \25BC"; }
+.yamc-table-filter { display: block; margin-bottom: 8px; padding: 4px 8px; width: 100%; max-width: 320px; }
+@media print {
+  .yamc-table { max-height: none; overflow: visible; }
+  .yamc-table thead { display: table-header-group; }
+  .yamc-table-filter { display: none; }
+}
+</style>
+<script>
+(function () {
+  function cellValue(row, index) {
+    var cell = row.cells[index];
+    return cell ? cell.textContent.trim() : "";
+  }
+  function toNumber(value) {
+    return Number(value.replace(/,/g, ""));
+  }
+  // the same order as yamc's own listings: numbers by value, case and
+  // accents ignored, exact comparison only to break ties
+  function compareText(x, y) {
+    return x.localeCompare(y, undefined, { numeric: true, sensitivity: "base" }) || (x < y ? -1 : x > y ? 1 : 0);
+  }
+  function sortBy(table, index, header) {
+    var body = table.tBodies[0];
+    if (!body) return;
+    var rows = Array.prototype.slice.call(body.rows);
+    var numeric = rows.every(function (row) {
+      var value = cellValue(row, index);
+      return value === "" || !isNaN(toNumber(value));
+    });
+    var ascending = header.getAttribute("aria-sort") !== "ascending";
+    rows.sort(function (a, b) {
+      var x = cellValue(a, index), y = cellValue(b, index);
+      var result = numeric ? (toNumber(x) || 0) - (toNumber(y) || 0) : compareText(x, y);
+      return ascending ? result : -result;
+    });
+    Array.prototype.forEach.call(header.parentNode.cells, function (th) {
+      th.removeAttribute("aria-sort");
+    });
+    header.setAttribute("aria-sort", ascending ? "ascending" : "descending");
+    rows.forEach(function (row) { body.appendChild(row); });
+  }
+  function addFilter(wrapper, table) {
+    var input = document.createElement("input");
+    input.type = "search";
+    input.placeholder = "Filter rows";
+    input.className = "yamc-table-filter";
+    input.addEventListener("input", function () {
+      var needle = input.value.toLowerCase();
+      var rows = table.tBodies[0] ? table.tBodies[0].rows : [];
+      Array.prototype.forEach.call(rows, function (row) {
+        row.style.display = row.textContent.toLowerCase().indexOf(needle) === -1 ? "none" : "";
+      });
+    });
+    wrapper.parentNode.insertBefore(input, wrapper);
+  }
+  document.addEventListener("DOMContentLoaded", function () {
+    document.querySelectorAll(".yamc-table").forEach(function (wrapper) {
+      var table = wrapper.querySelector("table");
+      if (!table || !table.tHead) return;
+      Array.prototype.forEach.call(table.tHead.rows[0].cells, function (th, index) {
+        th.addEventListener("click", function () { sortBy(table, index, th); });
+      });
+      if (wrapper.hasAttribute("data-filter")) addFilter(wrapper, table);
+    });
+  });
+})();
+</script>
+"#;
+
+// a `<!-- table: enhanced -->` (optionally `<!-- table: enhanced filter -->`)
+// comment directly before a table opts it in regardless of its size
+fn marker(node: &AstNode) -> Option<bool> {
+    let previous = node.previous_sibling()?;
+    let value = &previous.data.borrow().value;
+    let literal = match value {
+        NodeValue::HtmlBlock(block) => String::from_utf8_lossy(&block.literal).to_string(),
+        _ => return None,
+    };
+    let comment = literal.trim().strip_prefix("<!--")?.strip_suffix("-->")?;
+    let words: Vec<&str> = comment
+        .trim()
+        .strip_prefix("table:")?
+        .split_whitespace()
+        .collect();
+    if words.contains(&"enhanced") {
+        Some(words.contains(&"filter"))
+    } else {
+        None
+    }
+}
+
+// has to run before `html::omit_raw` replaces the marker comments
+pub fn plan<'a>(root: &'a AstNode<'a>, options: &TableOptions) -> Vec<Plan> {
+    root.descendants()
+        .filter(|node| matches!(node.data.borrow().value, NodeValue::Table(..)))
+        .map(|table| {
+            // the first row is always the header
+            let body_rows = table.children().count().saturating_sub(1);
+            match marker(table) {
+                Some(filter) => Plan::Enhanced {
+                    filter: filter || options.filter,
+                },
+                None if options.enabled && body_rows > options.threshold => Plan::Enhanced {
+                    filter: options.filter,
+                },
+                None => Plan::Plain,
+            }
+        })
+        .collect()
+}
+
+fn html_block<'a>(arena: &'a Arena<AstNode<'a>>, html: &str) -> &'a AstNode<'a> {
+    arena.alloc(AstNode::from(NodeValue::HtmlBlock(NodeHtmlBlock {
+        block_type: 0,
+        literal: html.as_bytes().to_vec(),
+    })))
+}
+
+// wraps the tables that qualify, reporting whether any did so the caller can
+// add `ASSETS`. the wrapper goes into the document next to the table itself,
+// after `html::omit_raw`, so a raw `<table>` kept by --unsafe is never taken
+// for one of them
+pub fn enhance<'a>(arena: &'a Arena<AstNode<'a>>, root: &'a AstNode<'a>, plans: Vec<Plan>) -> bool {
+    let tables: Vec<_> = root
+        .descendants()
+        .filter(|node| matches!(node.data.borrow().value, NodeValue::Table(..)))
+        .collect();
+    let mut enhanced = false;
+    for (table, plan) in tables.into_iter().zip(plans) {
+        if let Plan::Enhanced { filter } = plan {
+            table.insert_before(html_block(
+                arena,
+                if filter {
+                    "<div class=\"yamc-table\" data-filter>\n"
+                } else {
+                    "<div class=\"yamc-table\">\n"
+                },
+            ));
+            table.insert_after(html_block(arena, "</div>\n"));
+            enhanced = true;
+        }
+    }
+    enhanced
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn render(markdown: &str) -> String {
+        let mut options = comrak::ComrakOptions::default();
+        options.extension.table = true;
+        options.render.unsafe_ = true;
+        let arena = Arena::new();
+        let root = comrak::parse_document(&arena, markdown, &options);
+        let plans = plan(
+            root,
+            &TableOptions {
+                enabled: false,
+                threshold: DEFAULT_THRESHOLD,
+                filter: false,
+            },
+        );
+        enhance(&arena, root, plans);
+        let mut html = vec![];
+        comrak::format_html(root, &options, &mut html).unwrap();
+        String::from_utf8(html).unwrap()
+    }
+
+    #[test]
+    fn raw_tables_are_not_enhanced() {
+        let html = render(
+            "<table><tr><td>raw</td></tr></table>\n\n\
+             <!-- table: enhanced filter -->\n\n\
+             | a |\n| - |\n| 1 |\n",
+        );
+        assert!(
+            html.starts_with("<table><tr><td>raw</td></tr></table>\n"),
+            "{}",
+            html
+        );
+        assert!(
+            html.contains("<div class=\"yamc-table\" data-filter>\n<table>\n<thead>"),
+            "{}",
+            html
+        );
+        assert!(html.ends_with("</table>\n</div>\n"), "{}", html);
+    }
+
+    #[test]
+    fn unmarked_tables_stay_plain() {
+        let html = render("| a |\n| - |\n| 1 |\n");
+        assert!(!html.contains("yamc-table"), "{}", html);
+    }
+}
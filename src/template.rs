@@ -0,0 +1,366 @@
+// the page around the rendered document, as a handlebars template. the
+// built-in layouts are templates too, so a page from `--template` and one
+// without go through the same rendering
+use crate::{html, paths};
+use handlebars::template::{HelperTemplate, Parameter, TemplateElement};
+use handlebars::{Handlebars, RenderError, Template, TemplateError};
+use serde_json::{json, Map, Value};
+use std::fs;
+use std::path::Path;
+
+pub const DEFAULT: &str = "<!DOCTYPE html>\n<html\
+{{#if lang}} lang=\"{{lang}}\"{{/if}}{{#if dir}} dir=\"{{dir}}\"{{/if}}><head>{{{head}}}</head>\
+<body class=\"{{css_class}}\">{{{content}}}</body></html>\n";
+
+pub const XHTML: &str = "<html xmlns=\"http://www.w3.org/1999/xhtml\"\
+{{#if lang}} lang=\"{{lang}}\" xml:lang=\"{{lang}}\"{{/if}}{{#if dir}} dir=\"{{dir}}\"{{/if}}>\
+<head>{{{head}}}</head><body class=\"{{css_class}}\">{{{content}}}</body></html>\n";
+
+// the document `template preview` lays out without --sample
+pub const SAMPLE_TITLE: &str = "Lorem ipsum";
+pub const SAMPLE_FRONT_MATTER: &str = "title: Lorem ipsum\nauthor: Lorem Ipsum\n";
+pub const SAMPLE: &str = "<h1>Lorem ipsum</h1>\n\
+<p>Lorem ipsum dolor sit amet, consectetur adipiscing elit, sed do eiusmod tempor \
+incididunt ut labore et dolore magna aliqua. Ut enim ad minim veniam, quis nostrud \
+exercitation ullamco laboris nisi ut aliquip ex ea commodo consequat.</p>\n\
+<h2>Dolor sit amet</h2>\n\
+<ul>\n<li>Duis aute irure dolor</li>\n<li>in reprehenderit in voluptate</li>\n</ul>\n\
+<blockquote>\n<p>Excepteur sint occaecat cupidatat non proident.</p>\n</blockquote>\n\
+<pre><code>sunt in culpa qui officia deserunt\n</code></pre>\n\
+<p>Sed ut perspiciatis unde omnis iste natus error sit voluptatem.</p>\n";
+
+// the variables every page gives a template, see `render`
+pub const VARIABLES: [&str; 12] = [
+    "content",
+    "title",
+    "description",
+    "summary",
+    "toc",
+    "head",
+    "css_links",
+    "inline_css",
+    "css_class",
+    "lang",
+    "dir",
+    "front_matter",
+];
+
+// the helpers handlebars comes with
+const HELPERS: [&str; 17] = [
+    "if", "unless", "each", "with", "lookup", "raw", "log", "eq", "ne", "gt", "gte", "lt", "lte",
+    "and", "or", "not", "len",
+];
+
+// stands in for the content while the template is rendered, the page is
+// then written around it piece by piece
+const CONTENT: &str = "\u{E002}";
+
+// what a template can use besides `content`
+pub struct Page<'p> {
+    pub title: &'p str,
+    pub description: Option<&'p str>,
+    pub summary: Option<&'p str>,
+    // the table of contents, empty for a document without headings
+    pub toc: &'p str,
+    // everything yamc puts into the head: the title, stylesheets and styles
+    pub head: &'p str,
+    pub css_links: Vec<&'p str>,
+    pub inline_css: String,
+    pub css_class: &'p str,
+    pub lang: Option<&'p str>,
+    pub dir: Option<&'p str>,
+    pub front_matter: Map<String, Value>,
+}
+
+fn template_error(name: &str, err: &TemplateError) -> String {
+    match err.pos() {
+        Some((line, column)) => format!("{}:{}:{}: {}", name, line, column, err.reason()),
+        None => format!("{}: {}", name, err.reason()),
+    }
+}
+
+fn render_error(name: &str, err: &RenderError) -> String {
+    match (err.line_no, err.column_no) {
+        (Some(line), Some(column)) => format!("{}:{}:{}: {}", name, line, column, err.reason()),
+        (Some(line), None) => format!("{}:{}: {}", name, line, err.reason()),
+        _ => format!("{}: {}", name, err.reason()),
+    }
+}
+
+// the text of the template at `path`
+pub fn load(path: &Path) -> Result<String, String> {
+    fs::read_to_string(path)
+        .map_err(|err| format!("Cannot read template {} : {}", paths::display(path), err))
+}
+
+// the page before and after the content, from `source` named `name` in
+// errors. a variable the page does not have is an error
+pub fn render(name: &str, source: &str, page: &Page) -> Result<(String, String), String> {
+    let mut registry = Handlebars::new();
+    registry.set_strict_mode(true);
+    registry.register_escape_fn(html::escape);
+    registry
+        .register_template_string(name, source)
+        .map_err(|err| template_error(name, &err))?;
+    let data = json!({
+        "content": CONTENT,
+        "title": page.title,
+        "description": page.description,
+        "summary": page.summary,
+        "toc": page.toc,
+        "head": page.head,
+        "css_links": page.css_links,
+        "inline_css": page.inline_css,
+        "css_class": page.css_class,
+        "lang": page.lang,
+        "dir": page.dir,
+        "front_matter": page.front_matter,
+    });
+    let page = registry
+        .render(name, &data)
+        .map_err(|err| render_error(name, &err))?;
+    let mut pieces = page.split(CONTENT);
+    match (pieces.next(), pieces.next(), pieces.next()) {
+        (Some(before), Some(after), None) => Ok((before.to_string(), after.to_string())),
+        _ => Err(format!(
+            "{}: the template has to place {{{{{{content}}}}}} exactly once",
+            name
+        )),
+    }
+}
+
+// a placeholder of a template, as written, and where it is
+pub struct Placeholder {
+    pub line: usize,
+    pub column: usize,
+    pub text: String,
+}
+
+// what `check` finds walking a template
+struct Walk<'n> {
+    name: &'n str,
+    placeholders: Vec<Placeholder>,
+    problems: Vec<String>,
+    content: usize,
+}
+
+impl Walk<'_> {
+    fn problem(&mut self, line: usize, column: usize, message: String) {
+        self.problems
+            .push(format!("{}:{}:{}: {}", self.name, line, column, message));
+    }
+
+    // a variable of the page at `path`. inside `each` and `with` the
+    // context is something else, so `scoped` ones are not checked
+    fn variable(&mut self, path: &str, scoped: bool, line: usize, column: usize) {
+        let root = path
+            .trim_start_matches("this.")
+            .split(['.', '/', '['])
+            .next()
+            .unwrap_or_default();
+        if root == "content" {
+            self.content += 1;
+        }
+        if scoped || root.is_empty() || root.starts_with('@') || root == "this" {
+            return;
+        }
+        if !VARIABLES.contains(&root) {
+            self.problem(line, column, format!("unknown variable {}", root));
+        }
+    }
+
+    fn parameter(&mut self, parameter: &Parameter, scoped: bool, line: usize, column: usize) {
+        match parameter {
+            Parameter::Path(_) => {
+                let path = parameter.as_name().unwrap_or_default();
+                self.variable(path, scoped, line, column);
+            }
+            Parameter::Subexpression(subexpression) => {
+                if let TemplateElement::Expression(helper) = subexpression.as_element() {
+                    self.expression(helper, false, scoped, line, column);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    // `{{name params}}`, a helper when it has parameters or is a block
+    fn expression(
+        &mut self,
+        helper: &HelperTemplate,
+        block: bool,
+        scoped: bool,
+        line: usize,
+        column: usize,
+    ) {
+        let name = helper.name.as_name().unwrap_or_default();
+        let is_helper = block
+            || !helper.params.is_empty()
+            || !helper.hash.is_empty()
+            || HELPERS.contains(&name);
+        if !is_helper {
+            self.variable(name, scoped, line, column);
+            return;
+        }
+        if !HELPERS.contains(&name) {
+            self.problem(line, column, format!("unknown helper {}", name));
+        }
+        for parameter in helper.params.iter().chain(helper.hash.values()) {
+            self.parameter(parameter, scoped, line, column);
+        }
+    }
+
+    fn template(&mut self, template: &Template, scoped: bool) {
+        for (element, mapping) in template.elements.iter().zip(&template.mapping) {
+            let (line, column) = (mapping.0, mapping.1);
+            let text = match element {
+                TemplateElement::Expression(helper) => {
+                    self.expression(helper, false, scoped, line, column);
+                    format!("{{{{{}}}}}", written(helper))
+                }
+                TemplateElement::HtmlExpression(helper) => {
+                    self.expression(helper, false, scoped, line, column);
+                    format!("{{{{{{{}}}}}}}", written(helper))
+                }
+                TemplateElement::HelperBlock(helper) => {
+                    self.expression(helper, true, scoped, line, column);
+                    self.placeholders.push(Placeholder {
+                        line,
+                        column,
+                        text: format!("{{{{#{}}}}}", written(helper)),
+                    });
+                    // `if` and `unless` keep the page as the context
+                    let name = helper.name.as_name().unwrap_or_default();
+                    let inner = scoped || !["if", "unless"].contains(&name);
+                    for block in [&helper.template, &helper.inverse].into_iter().flatten() {
+                        self.template(block, inner);
+                    }
+                    continue;
+                }
+                TemplateElement::PartialExpression(partial)
+                | TemplateElement::PartialBlock(partial) => {
+                    let name = partial.name.as_name().unwrap_or_default();
+                    self.problem(line, column, format!("unknown partial {}", name));
+                    continue;
+                }
+                _ => continue,
+            };
+            self.placeholders.push(Placeholder { line, column, text });
+        }
+    }
+}
+
+// a helper call the way it is written, without its braces
+fn written(helper: &HelperTemplate) -> String {
+    let mut text = helper.name.as_name().unwrap_or_default().to_string();
+    for parameter in &helper.params {
+        text.push(' ');
+        match parameter.as_name() {
+            Some(name) => text.push_str(name),
+            None => text.push('…'),
+        }
+    }
+    text
+}
+
+// the placeholders of the template `source`, named `name` in what it
+// says, or everything wrong with it: a syntax error, a variable or helper
+// yamc does not have, or `content` placed other than once
+pub fn check(name: &str, source: &str) -> Result<Vec<Placeholder>, Vec<String>> {
+    let template = Template::compile(source).map_err(|err| vec![template_error(name, &err)])?;
+    let mut walk = Walk {
+        name,
+        placeholders: Vec::new(),
+        problems: Vec::new(),
+        content: 0,
+    };
+    walk.template(&template, false);
+    if walk.content != 1 {
+        walk.problems.push(format!(
+            "{}: the template has to place {{{{{{content}}}}}} exactly once, not {} times",
+            name, walk.content
+        ));
+    }
+    if !walk.problems.is_empty() {
+        return Err(walk.problems);
+    }
+    Ok(walk.placeholders)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn page() -> Page<'static> {
+        Page {
+            title: "Title",
+            description: None,
+            summary: None,
+            toc: "",
+            head: "<title>Title</title>",
+            css_links: Vec::new(),
+            inline_css: String::new(),
+            css_class: "markdown-body",
+            lang: None,
+            dir: None,
+            front_matter: Map::new(),
+        }
+    }
+
+    #[test]
+    fn every_variable_checked_is_one_the_page_has() {
+        // content is the one that has to be placed once
+        let source: String = VARIABLES
+            .iter()
+            .filter(|variable| **variable != "content")
+            .map(|variable| format!("{{{{#if {}}}}}{{{{/if}}}}", variable))
+            .chain(["{{{content}}}".to_string()])
+            .collect();
+        assert!(check("all", &source).is_ok());
+        assert!(render("all", &source, &page()).is_ok());
+    }
+
+    #[test]
+    fn placeholders_come_with_where_they_are() {
+        let source =
+            "<title>{{title}}</title>\n{{#each css_links}}{{this}}{{/each}}\n{{{content}}}";
+        let placeholders: Vec<(usize, usize, String)> = check("page.hbs", source)
+            .unwrap_or_else(|problems| panic!("{:?}", problems))
+            .into_iter()
+            .map(|placeholder| (placeholder.line, placeholder.column, placeholder.text))
+            .collect();
+        assert_eq!(
+            placeholders,
+            [
+                (1, 8, "{{title}}".to_string()),
+                (2, 1, "{{#each css_links}}".to_string()),
+                (2, 20, "{{this}}".to_string()),
+                (3, 1, "{{{content}}}".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn unknown_names_and_a_missing_content_are_problems() {
+        let problems = check(
+            "page.hbs",
+            "{{sidebar}}\n{{#if draft}}{{upper title}}{{/if}}",
+        )
+        .err()
+        .unwrap();
+        assert_eq!(
+            problems,
+            [
+                "page.hbs:1:1: unknown variable sidebar",
+                "page.hbs:2:1: unknown variable draft",
+                "page.hbs:2:14: unknown helper upper",
+                "page.hbs: the template has to place {{{content}}} exactly once, not 0 times",
+            ]
+        );
+        let broken = check("page.hbs", "{{{content}}}\n{{#if title}}")
+            .err()
+            .unwrap();
+        assert_eq!(broken.len(), 1);
+        assert!(broken[0].starts_with("page.hbs:2:"), "{}", broken[0]);
+    }
+}
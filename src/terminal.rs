@@ -0,0 +1,387 @@
+use crate::html;
+use comrak::nodes::{AstNode, ListDelimType, ListType, NodeValue, TableAlignment};
+use std::env;
+use std::io::{self, IsTerminal};
+
+const RESET: &str = "\x1b[0m";
+const BOLD: &str = "\x1b[1m";
+const DIM: &str = "\x1b[2m";
+const TITLE: &str = "\x1b[1;4;35m";
+const HEADING: &str = "\x1b[1;4;36m";
+const CODE: &str = "\x1b[33m";
+
+// how a document is drawn in the terminal
+pub struct Terminal {
+    pub width: usize,
+    // osc 8 hyperlinks, otherwise the url is written after the link text
+    pub hyperlinks: bool,
+}
+
+impl Terminal {
+    // none when stdout is not a terminal or NO_COLOR is set, the document is
+    // shown as plain text then. pagers do not all pass osc 8 through
+    pub fn detect(paging: bool) -> Option<Terminal> {
+        if !io::stdout().is_terminal() || env::var_os("NO_COLOR").is_some() {
+            return None;
+        }
+        let width = terminal_size::terminal_size()
+            .map_or(80, |(terminal_size::Width(width), _)| width as usize);
+        Some(Terminal {
+            width: width.max(20),
+            hyperlinks: !paging && hyperlinks(),
+        })
+    }
+}
+
+// terminals known to understand osc 8, the others would print it
+fn hyperlinks() -> bool {
+    let term = env::var("TERM").unwrap_or_default();
+    if term == "dumb" || term == "linux" || term.starts_with("screen") {
+        return false;
+    }
+    env::var_os("VTE_VERSION").is_some()
+        || env::var_os("WT_SESSION").is_some()
+        || env::var_os("KITTY_WINDOW_ID").is_some()
+        || matches!(
+            env::var("TERM_PROGRAM").as_deref(),
+            Ok("iTerm.app" | "WezTerm" | "vscode" | "ghostty")
+        )
+}
+
+fn lossy(bytes: &[u8]) -> String {
+    String::from_utf8_lossy(bytes).to_string()
+}
+
+// the number of columns text takes, not counting escape sequences
+fn width(text: &str) -> usize {
+    let mut count = 0;
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        if c != '\x1b' {
+            count += 1;
+            continue;
+        }
+        match chars.next() {
+            // csi, up to the final letter
+            Some('[') => {
+                for c in chars.by_ref() {
+                    if c.is_ascii_alphabetic() {
+                        break;
+                    }
+                }
+            }
+            // osc, up to the string terminator or bell
+            Some(']') => {
+                while let Some(c) = chars.next() {
+                    if c == '\x07' {
+                        break;
+                    }
+                    if c == '\x1b' {
+                        chars.next();
+                        break;
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+    count
+}
+
+// break text into lines of at most `columns` at spaces, words longer than
+// that stay whole
+fn wrap(text: &str, columns: usize) -> Vec<String> {
+    let mut lines = Vec::new();
+    for paragraph in text.split('\n') {
+        let mut line = String::new();
+        for word in paragraph.split(' ') {
+            if !line.is_empty() {
+                if width(&line) + 1 + width(word) > columns {
+                    lines.push(std::mem::take(&mut line));
+                } else {
+                    line.push(' ');
+                }
+            }
+            line.push_str(word);
+        }
+        lines.push(line);
+    }
+    lines
+}
+
+// like `wrap`, but also splits long words, for table cells of plain text
+fn cut(text: &str, columns: usize) -> Vec<String> {
+    let mut lines = Vec::new();
+    for line in wrap(text, columns) {
+        let chars: Vec<char> = line.chars().collect();
+        if chars.is_empty() {
+            lines.push(line);
+        }
+        for chunk in chars.chunks(columns.max(1)) {
+            lines.push(chunk.iter().collect());
+        }
+    }
+    lines
+}
+
+fn indent(lines: Vec<String>, first: &str, rest: &str) -> Vec<String> {
+    lines
+        .into_iter()
+        .enumerate()
+        .map(|(i, line)| format!("{}{}", if i == 0 { first } else { rest }, line))
+        .collect()
+}
+
+// the text of a node without any styling
+fn plain<'a>(node: &'a AstNode<'a>) -> String {
+    let mut text = String::new();
+    for descendant in node.descendants() {
+        match descendant.data.borrow().value {
+            NodeValue::Text(ref literal) => text.push_str(&lossy(literal)),
+            NodeValue::Code(ref code) => text.push_str(&lossy(&code.literal)),
+            NodeValue::SoftBreak | NodeValue::LineBreak => text.push(' '),
+            _ => {}
+        }
+    }
+    text
+}
+
+fn styled(output: &mut String, on: &str, text: &str, off: &str) {
+    output.push_str(on);
+    output.push_str(text);
+    output.push_str(off);
+}
+
+fn inlines<'a>(node: &'a AstNode<'a>, terminal: &Terminal) -> String {
+    let mut output = String::new();
+    for child in node.children() {
+        match child.data.borrow().value {
+            NodeValue::Text(ref literal) => output.push_str(&lossy(literal)),
+            NodeValue::Code(ref code) => {
+                styled(&mut output, CODE, &lossy(&code.literal), "\x1b[39m")
+            }
+            NodeValue::SoftBreak => output.push(' '),
+            NodeValue::LineBreak => output.push('\n'),
+            NodeValue::Emph => styled(
+                &mut output,
+                "\x1b[3m",
+                &inlines(child, terminal),
+                "\x1b[23m",
+            ),
+            NodeValue::Strong => styled(&mut output, BOLD, &inlines(child, terminal), "\x1b[22m"),
+            NodeValue::Strikethrough => styled(
+                &mut output,
+                "\x1b[9m",
+                &inlines(child, terminal),
+                "\x1b[29m",
+            ),
+            NodeValue::Superscript => {
+                output.push('^');
+                output.push_str(&inlines(child, terminal));
+            }
+            NodeValue::Link(ref link) => {
+                let url = lossy(&link.url);
+                let text = inlines(child, terminal);
+                if terminal.hyperlinks {
+                    output.push_str(&format!("\x1b]8;;{}\x1b\\", url));
+                    styled(&mut output, "\x1b[4m", &text, "\x1b[24m");
+                    output.push_str("\x1b]8;;\x1b\\");
+                } else {
+                    styled(&mut output, "\x1b[4m", &text, "\x1b[24m");
+                    if plain(child) != url {
+                        styled(&mut output, DIM, &format!(" ({})", url), "\x1b[22m");
+                    }
+                }
+            }
+            NodeValue::Image(ref link) => {
+                let text = format!("[image: {}]", plain(child));
+                styled(&mut output, DIM, &text, "\x1b[22m");
+                output.push_str(&format!(" {}", lossy(&link.url)));
+            }
+            NodeValue::FootnoteReference(ref label) => styled(
+                &mut output,
+                DIM,
+                &format!("[^{}]", lossy(label)),
+                "\x1b[22m",
+            ),
+            NodeValue::TaskItem(checked) => output.push_str(if checked { "☑ " } else { "☐ " }),
+            // tags of their own, the text around them is what matters
+            NodeValue::HtmlInline(..) => {}
+            _ => output.push_str(&inlines(child, terminal)),
+        }
+    }
+    output
+}
+
+// the child blocks of a node, with blank lines between them unless tight
+fn blocks<'a>(
+    node: &'a AstNode<'a>,
+    terminal: &Terminal,
+    columns: usize,
+    tight: bool,
+) -> Vec<String> {
+    let mut lines = Vec::new();
+    for child in node.children() {
+        let rendered = block(child, terminal, columns);
+        if rendered.is_empty() {
+            continue;
+        }
+        if !lines.is_empty() && !tight {
+            lines.push(String::new());
+        }
+        lines.extend(rendered);
+    }
+    lines
+}
+
+fn block<'a>(node: &'a AstNode<'a>, terminal: &Terminal, columns: usize) -> Vec<String> {
+    match node.data.borrow().value {
+        NodeValue::FrontMatter(..) => Vec::new(),
+        NodeValue::Paragraph => wrap(&inlines(node, terminal), columns),
+        NodeValue::Heading(ref heading) => {
+            let style = if heading.level == 1 { TITLE } else { HEADING };
+            wrap(&inlines(node, terminal), columns)
+                .into_iter()
+                .map(|line| format!("{}{}{}", style, line, RESET))
+                .collect()
+        }
+        NodeValue::CodeBlock(ref code) => lossy(&code.literal)
+            .lines()
+            .map(|line| format!("    {}{}{}", CODE, line, RESET))
+            .collect(),
+        NodeValue::BlockQuote => {
+            let bar = format!("{}│{} ", DIM, RESET);
+            indent(
+                blocks(node, terminal, columns.saturating_sub(2), false),
+                &bar,
+                &bar,
+            )
+        }
+        NodeValue::List(ref list) => {
+            let mut lines = Vec::new();
+            for (number, item) in (list.start..).zip(node.children()) {
+                let marker = match (list.list_type, list.delimiter) {
+                    (ListType::Bullet, _) => "• ".to_string(),
+                    (ListType::Ordered, ListDelimType::Paren) => format!("{}) ", number),
+                    (ListType::Ordered, ListDelimType::Period) => format!("{}. ", number),
+                };
+                let rest = " ".repeat(marker.chars().count());
+                let content = blocks(
+                    item,
+                    terminal,
+                    columns.saturating_sub(rest.len()),
+                    list.tight,
+                );
+                if !lines.is_empty() && !list.tight {
+                    lines.push(String::new());
+                }
+                lines.extend(indent(content, &marker, &rest));
+            }
+            lines
+        }
+        NodeValue::ThematicBreak => vec![format!("{}{}{}", DIM, "─".repeat(columns), RESET)],
+        NodeValue::Table(ref alignments) => table(node, alignments, columns),
+        NodeValue::HtmlBlock(ref block) => html::to_text(&lossy(&block.literal))
+            .lines()
+            .map(str::to_string)
+            .collect(),
+        NodeValue::FootnoteDefinition(ref label) => {
+            let marker = format!("[^{}]: ", lossy(label));
+            let rest = " ".repeat(marker.chars().count());
+            let content = blocks(node, terminal, columns.saturating_sub(rest.len()), false);
+            indent(content, &format!("{}{}{}", DIM, marker, RESET), &rest)
+        }
+        NodeValue::DescriptionTerm => blocks(node, terminal, columns, true)
+            .into_iter()
+            .map(|line| format!("{}{}{}", BOLD, line, RESET))
+            .collect(),
+        NodeValue::DescriptionDetails => indent(
+            blocks(node, terminal, columns.saturating_sub(4), false),
+            "    ",
+            "    ",
+        ),
+        NodeValue::DescriptionItem(..) => blocks(node, terminal, columns, true),
+        _ => blocks(node, terminal, columns, false),
+    }
+}
+
+fn align(text: &str, columns: usize, alignment: TableAlignment) -> String {
+    let padding = columns.saturating_sub(text.chars().count());
+    match alignment {
+        TableAlignment::Right => format!("{}{}", " ".repeat(padding), text),
+        TableAlignment::Center => format!(
+            "{}{}{}",
+            " ".repeat(padding / 2),
+            text,
+            " ".repeat(padding - padding / 2)
+        ),
+        TableAlignment::Left | TableAlignment::None => format!("{}{}", text, " ".repeat(padding)),
+    }
+}
+
+// a table drawn with box characters, columns are narrowed (widest first) and
+// their cells wrapped until it fits
+fn table<'a>(node: &'a AstNode<'a>, alignments: &[TableAlignment], columns: usize) -> Vec<String> {
+    let rows: Vec<(bool, Vec<String>)> = node
+        .children()
+        .map(|row| {
+            let header = matches!(row.data.borrow().value, NodeValue::TableRow(true));
+            (header, row.children().map(plain).collect())
+        })
+        .collect();
+    let count = alignments.len();
+    let mut widths = vec![1; count];
+    for (_, cells) in &rows {
+        for (width, cell) in widths.iter_mut().zip(cells) {
+            *width = (*width).max(cell.chars().count());
+        }
+    }
+    // every cell takes a border and a space on either side
+    let available = columns.saturating_sub(3 * count + 1);
+    while widths.iter().sum::<usize>() > available {
+        let widest = (0..count).max_by_key(|&i| widths[i]).unwrap_or(0);
+        if widths[widest] <= 3 {
+            break;
+        }
+        widths[widest] -= 1;
+    }
+    let border = |left: &str, middle: &str, right: &str| {
+        let segments: Vec<String> = widths.iter().map(|width| "─".repeat(width + 2)).collect();
+        format!("{}{}{}", left, segments.join(middle), right)
+    };
+    let mut lines = vec![border("┌", "┬", "┐")];
+    for (header, cells) in &rows {
+        let wrapped: Vec<Vec<String>> = (0..count)
+            .map(|i| cut(cells.get(i).map_or("", String::as_str), widths[i]))
+            .collect();
+        let height = wrapped.iter().map(Vec::len).max().unwrap_or(1);
+        for index in 0..height {
+            let mut line = String::from("│");
+            for i in 0..count {
+                let text = wrapped[i].get(index).map_or("", String::as_str);
+                let text = align(text, widths[i], alignments[i]);
+                if *header {
+                    line.push_str(&format!(" {}{}{} │", BOLD, text, RESET));
+                } else {
+                    line.push_str(&format!(" {} │", text));
+                }
+            }
+            lines.push(line);
+        }
+        if *header {
+            lines.push(border("├", "┼", "┤"));
+        }
+    }
+    lines.push(border("└", "┴", "┘"));
+    lines
+}
+
+// the document as ansi formatted text
+pub fn render<'a>(root: &'a AstNode<'a>, terminal: &Terminal) -> String {
+    let mut output = String::new();
+    for line in block(root, terminal, terminal.width) {
+        output.push_str(&line);
+        output.push('\n');
+    }
+    output
+}
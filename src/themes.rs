@@ -0,0 +1,45 @@
+// stylesheets compiled into yamc, for --theme. `{class}` in them is the
+// class of the page body
+pub struct Theme {
+    pub name: &'static str,
+    pub description: &'static str,
+    css: &'static str,
+}
+
+pub const THEMES: &[Theme] = &[
+    Theme {
+        name: "github",
+        description: "GitHub's light look, the page --offline gets",
+        css: include_str!("themes/github.css"),
+    },
+    Theme {
+        name: "github-dark",
+        description: "GitHub's dark look, light again when printed",
+        css: include_str!("themes/github-dark.css"),
+    },
+    Theme {
+        name: "minimal",
+        description: "Plain text in a narrow column, little else",
+        css: include_str!("themes/minimal.css"),
+    },
+    Theme {
+        name: "latex",
+        description: "Serif, justified text in the manner of a LaTeX article",
+        css: include_str!("themes/latex.css"),
+    },
+];
+
+pub fn find(name: &str) -> Option<&'static Theme> {
+    THEMES.iter().find(|theme| theme.name == name)
+}
+
+pub fn names() -> impl Iterator<Item = &'static str> {
+    THEMES.iter().map(|theme| theme.name)
+}
+
+impl Theme {
+    // the stylesheet for pages whose body has `class`
+    pub fn css(&self, class: &str) -> String {
+        self.css.trim_end().replace("{class}", class)
+    }
+}
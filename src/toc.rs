@@ -0,0 +1,164 @@
+use crate::headings::{self, Attributes};
+use crate::html;
+use crate::summary;
+use comrak::nodes::{AstNode, NodeHtmlBlock, NodeValue};
+
+pub const DEFAULT_DEPTH: u8 = 3;
+
+// where the table of contents goes, left in the rendered html in place of a
+// `[TOC]` paragraph or a `<!-- toc -->` comment
+const PLACE: &str = "<!-- yamc:toc -->";
+
+const STYLE: &str = "<style>
+nav.toc ul { list-style: none; padding-left: 1.2em; }
+nav.toc > ul { padding-left: 0; }
+@media print {
+  nav.toc { break-after: page; }
+}
+</style>
+";
+
+// a heading as the table of contents lists it
+pub struct Entry {
+    pub level: usize,
+    pub id: String,
+    pub text: String,
+}
+
+// what the attribute blocks of the headings, in document order, say about
+// the table of contents: left out with `.no-toc`, or listed as `toc-title`
+pub struct Hint {
+    skip: bool,
+    title: Option<String>,
+}
+
+pub fn hints(attributes: &[Attributes]) -> Vec<Hint> {
+    attributes
+        .iter()
+        .map(|attributes| Hint {
+            skip: attributes.classes.iter().any(|class| class == "no-toc"),
+            title: attributes
+                .pairs
+                .iter()
+                .find(|(key, _)| key == "toc-title")
+                .map(|(_, value)| value.clone()),
+        })
+        .collect()
+}
+
+fn is_marker<'a>(node: &'a AstNode<'a>) -> bool {
+    match node.data.borrow().value {
+        NodeValue::Paragraph => summary::plain_text(node) == "[TOC]",
+        NodeValue::HtmlBlock(ref block) => {
+            let literal = String::from_utf8_lossy(&block.literal);
+            literal
+                .trim()
+                .strip_prefix("<!--")
+                .and_then(|comment| comment.strip_suffix("-->"))
+                .is_some_and(|comment| comment.trim().eq_ignore_ascii_case("toc"))
+        }
+        _ => false,
+    }
+}
+
+// turn the first `[TOC]` paragraph or `<!-- toc -->` comment at the top level
+// into the place the table of contents goes
+pub fn mark_place<'a>(root: &'a AstNode<'a>) {
+    if let Some(marker) = root.children().find(|node| is_marker(node)) {
+        while let Some(child) = marker.first_child() {
+            child.detach();
+        }
+        marker.data.borrow_mut().value = NodeValue::HtmlBlock(NodeHtmlBlock {
+            block_type: 0,
+            literal: format!("{}\n", PLACE).into_bytes(),
+        });
+    }
+}
+
+// the headings of rendered html, with the ids they ended up with
+pub fn entries(html: &str, hints: &[Hint]) -> Vec<Entry> {
+    let mut entries = Vec::new();
+    for (index, anchor) in headings::anchors(html).into_iter().enumerate() {
+        let hint = hints.get(index);
+        if hint.is_some_and(|hint| hint.skip) {
+            continue;
+        }
+        let content = &html[anchor.end..];
+        let content = &content[..content
+            .find(&format!("</h{}>", anchor.level))
+            .unwrap_or(content.len())];
+        let text = match hint.and_then(|hint| hint.title.clone()) {
+            Some(title) => title,
+            None => html::to_text(content).trim().to_string(),
+        };
+        entries.push(Entry {
+            level: anchor.level,
+            id: anchor.id,
+            text,
+        });
+    }
+    entries
+}
+
+// the nested list of `entries` down to `depth` levels below the highest one
+fn nav(entries: &[Entry], depth: u8) -> String {
+    let top = entries.iter().map(|entry| entry.level).min().unwrap_or(1);
+    let mut html = String::from("<nav class=\"toc\">\n");
+    // the levels of the lists still open, innermost last
+    let mut open: Vec<usize> = Vec::new();
+    for entry in entries {
+        let level = entry.level - top;
+        if level >= usize::from(depth) {
+            continue;
+        }
+        match open.last() {
+            Some(&last) if level > last => {
+                html.push_str("\n<ul>\n");
+                open.push(level);
+            }
+            Some(_) => {
+                while open.len() > 1 && open.last().is_some_and(|&last| level < last) {
+                    open.pop();
+                    html.push_str("</li>\n</ul>\n");
+                }
+                html.push_str("</li>\n");
+            }
+            None => {
+                html.push_str("<ul>\n");
+                open.push(level);
+            }
+        }
+        html.push_str(&format!(
+            "<li><a href=\"#{}\">{}</a>",
+            entry.id,
+            html::escape(&entry.text)
+        ));
+    }
+    for _ in &open {
+        html.push_str("</li>\n</ul>\n");
+    }
+    html.push_str("</nav>\n");
+    html
+}
+
+// the table of contents of `entries`, with its style. a document without
+// headings gets none
+pub fn contents(entries: &[Entry], depth: u8) -> String {
+    if entries.is_empty() {
+        String::new()
+    } else {
+        format!("{}{}", STYLE, nav(entries, depth))
+    }
+}
+
+// put `contents` where `mark_place` left its place, or at the top
+pub fn insert(html: String, contents: &str) -> String {
+    match html.find(PLACE) {
+        Some(start) => {
+            let end =
+                start + PLACE.len() + usize::from(html[start + PLACE.len()..].starts_with('\n'));
+            format!("{}{}{}", &html[..start], contents, &html[end..])
+        }
+        None => format!("{}{}", contents, html),
+    }
+}
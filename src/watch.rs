@@ -0,0 +1,74 @@
+use crate::paths;
+use notify::{Event, EventKind, RecursiveMode, Watcher};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc;
+use std::time::Duration;
+
+// editors save by writing a temporary file and renaming it over the old one,
+// changes this close together make one rebuild
+const SETTLE: Duration = Duration::from_millis(200);
+
+enum Message {
+    Changed(Vec<PathBuf>),
+    Stop,
+}
+
+// whether an event can mean different content: reading a file, which every
+// rebuild does, or touching its times does not
+fn changes_content(event: &Event) -> bool {
+    match event.kind {
+        EventKind::Create(_) | EventKind::Remove(_) => true,
+        EventKind::Modify(notify::event::ModifyKind::Metadata(_)) => false,
+        EventKind::Modify(_) => true,
+        _ => false,
+    }
+}
+
+// watch `directories` (with whether to watch below them) and call `rebuild`
+// once changes to files that are `relevant` have settled, until ctrl-c
+pub fn run(
+    directories: &[(PathBuf, bool)],
+    relevant: impl Fn(&Path) -> bool,
+    mut rebuild: impl FnMut(),
+) -> Result<(), String> {
+    let (sender, receiver) = mpsc::channel();
+    let stop = sender.clone();
+    ctrlc::set_handler(move || {
+        let _ = stop.send(Message::Stop);
+    })
+    .map_err(|err| format!("cannot handle ctrl-c: {}", err))?;
+    let mut watcher = notify::recommended_watcher(move |event: notify::Result<Event>| {
+        if let Ok(event) = event {
+            if changes_content(&event) {
+                let _ = sender.send(Message::Changed(event.paths));
+            }
+        }
+    })
+    .map_err(|err| format!("cannot watch for changes: {}", err))?;
+    for (directory, recursive) in directories {
+        let mode = if *recursive {
+            RecursiveMode::Recursive
+        } else {
+            RecursiveMode::NonRecursive
+        };
+        watcher
+            .watch(directory, mode)
+            .map_err(|err| format!("cannot watch {}: {}", paths::display(directory), err))?;
+    }
+    loop {
+        match receiver.recv() {
+            Ok(Message::Changed(paths)) if paths.iter().any(|path| relevant(path)) => {}
+            Ok(Message::Changed(_)) => continue,
+            Ok(Message::Stop) | Err(_) => return Ok(()),
+        }
+        // wait for the burst of events of a save to end
+        loop {
+            match receiver.recv_timeout(SETTLE) {
+                Ok(Message::Changed(_)) => continue,
+                Ok(Message::Stop) => return Ok(()),
+                Err(_) => break,
+            }
+        }
+        rebuild();
+    }
+}
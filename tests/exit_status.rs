@@ -0,0 +1,203 @@
+// the exit statuses scripts rely on, from the built binary
+use std::env;
+use std::ffi::OsStr;
+use std::fs;
+use std::io::Write;
+use std::path::PathBuf;
+use std::process::{Command, Stdio};
+
+fn yamc<A: AsRef<OsStr>>(arguments: &[A]) -> i32 {
+    Command::new(env!("CARGO_BIN_EXE_yamc"))
+        .args(arguments)
+        .output()
+        .expect("yamc runs")
+        .status
+        .code()
+        .expect("yamc exits on its own")
+}
+
+// a directory of its own for `test`, empty
+fn scratch(test: &str) -> PathBuf {
+    let directory = env::temp_dir().join(format!("yamc-exit-{}-{}", test, std::process::id()));
+    let _ = fs::remove_dir_all(&directory);
+    fs::create_dir_all(&directory).expect("the temporary directory is writable");
+    directory
+}
+
+#[test]
+fn help_and_version_succeed() {
+    assert_eq!(yamc(&["--help"]), 0);
+    assert_eq!(yamc(&["--version"]), 0);
+}
+
+#[test]
+fn a_missing_input_is_an_io_failure() {
+    let directory = scratch("missing");
+    let missing = directory.join("missing.md");
+    let output = directory.join("missing.html");
+    let arguments = [
+        "convert",
+        missing.to_str().unwrap(),
+        "--output",
+        output.to_str().unwrap(),
+    ];
+    assert_eq!(yamc(&arguments), 3);
+    // a page already there does not make it a different failure
+    fs::write(&output, "<p>old</p>\n").unwrap();
+    assert_eq!(yamc(&arguments), 3);
+}
+
+#[test]
+fn the_post_hook_hears_about_a_failed_document() {
+    let directory = scratch("post-hook");
+    let missing = directory.join("missing.md");
+    let statuses = directory.join("statuses");
+    let hook = format!("echo $YAMC_STATUS >> {}", statuses.display());
+    let arguments = [
+        "convert",
+        missing.to_str().unwrap(),
+        "--pre-hook",
+        &hook,
+        "--post-hook",
+        &hook,
+    ];
+    // the failure is the conversion's, not the hook's
+    assert_eq!(yamc(&arguments), 3);
+    assert_eq!(fs::read_to_string(&statuses).unwrap(), "pending\nfailed\n");
+}
+
+#[test]
+fn inputs_going_to_the_same_page_fail_before_any_is_converted() {
+    let directory = scratch("collision");
+    for name in ["a", "b"] {
+        fs::create_dir_all(directory.join(name)).unwrap();
+        fs::write(directory.join(name).join("x.md"), "# X\n").unwrap();
+    }
+    fs::write(directory.join("other.md"), "# Other\n").unwrap();
+    let build = directory.join("build");
+    let arguments = [
+        "convert".into(),
+        "--output-dir".into(),
+        build.clone().into_os_string(),
+        directory.join("other.md").into_os_string(),
+        directory.join("a").join("x.md").into_os_string(),
+        directory.join("b").join("x.md").into_os_string(),
+    ];
+    assert_eq!(yamc(&arguments), 2);
+    assert!(!build.join("other.html").exists());
+}
+
+#[test]
+fn the_other_documents_are_converted_before_failing() {
+    let directory = scratch("batch");
+    let page = directory.join("page.md");
+    fs::write(&page, "# Page\n").unwrap();
+    let missing = directory.join("missing.md");
+    fs::write(directory.join("missing.html"), "<p>old</p>\n").unwrap();
+    let arguments = ["convert", page.to_str().unwrap(), missing.to_str().unwrap()];
+    assert_eq!(yamc(&arguments), 3);
+    assert!(directory.join("page.html").is_file());
+}
+
+#[cfg(unix)]
+#[test]
+fn a_name_that_is_not_unicode_converts() {
+    use std::os::unix::ffi::OsStrExt;
+    let directory = scratch("not-unicode");
+    let page = directory.join(OsStr::from_bytes(b"caf\xE9.md"));
+    fs::write(&page, "# Menu\n").unwrap();
+    let output = directory.join(OsStr::from_bytes(b"caf\xE9.html"));
+    let arguments = [
+        OsStr::new("convert"),
+        page.as_os_str(),
+        OsStr::new("--output"),
+        output.as_os_str(),
+    ];
+    assert_eq!(yamc(&arguments), 0);
+    assert!(output.is_file());
+}
+
+#[test]
+fn options_that_make_no_sense_are_usage_errors() {
+    assert_eq!(yamc(&["convert", "--no-such-option"]), 2);
+    assert_eq!(yamc(&["convert", "page.md", "--output", "page.pdf"]), 2);
+    assert_eq!(yamc(&["explain", "page.md"]), 2);
+}
+
+#[test]
+fn a_failed_check_is_a_failure() {
+    let directory = scratch("check");
+    let page = directory.join("page.html");
+    fs::write(&page, "<p>no stamp</p>\n").unwrap();
+    assert_eq!(yamc(&["verify-hash", page.to_str().unwrap()]), 1);
+}
+
+#[test]
+fn a_broken_template_fails_before_any_page_is_written() {
+    let directory = scratch("template");
+    let template = directory.join("layout.hbs");
+    fs::write(&template, "<main>{{{content}}}</main>{{sidebar}}\n").unwrap();
+    let page = directory.join("page.md");
+    fs::write(&page, "# Page\n").unwrap();
+    let template = template.to_str().unwrap();
+    assert_eq!(yamc(&["template", "check", template]), 1);
+    let arguments = ["convert", "--template", template, page.to_str().unwrap()];
+    assert_eq!(yamc(&arguments), 2);
+    assert!(!directory.join("page.html").exists());
+}
+
+#[test]
+fn a_denied_resource_fails_and_leaves_the_printed_page_alone() {
+    let directory = scratch("resources");
+    let page = directory.join("page.md");
+    fs::write(&page, "[tracker](https://ads.example.com/pixel)\n").unwrap();
+    let output = Command::new(env!("CARGO_BIN_EXE_yamc"))
+        .args([
+            "convert",
+            "--emit-resources",
+            "--deny-domain",
+            "*.example.com",
+        ])
+        .arg(&page)
+        .args(["--output", "-"])
+        .output()
+        .expect("yamc runs");
+    assert_eq!(output.status.code(), Some(1));
+    let printed = String::from_utf8(output.stdout).unwrap();
+    // the page and its stamp, nothing after them
+    assert!(printed.trim_end().ends_with("-->"), "{}", printed);
+    assert!(!printed.contains("ads.example.com ("), "{}", printed);
+    let said = String::from_utf8(output.stderr).unwrap();
+    assert!(said.contains("ads.example.com"), "{}", said);
+}
+
+#[test]
+fn the_node_tree_stays_off_the_printed_page() {
+    let directory = scratch("events");
+    let page = directory.join("page.md");
+    fs::write(&page, "# Title\n").unwrap();
+    let output = Command::new(env!("CARGO_BIN_EXE_yamc"))
+        .args(["convert", "--show-events"])
+        .arg(&page)
+        .args(["--output", "-"])
+        .output()
+        .expect("yamc runs");
+    assert_eq!(output.status.code(), Some(0));
+    let printed = String::from_utf8(output.stdout).unwrap();
+    assert!(printed.starts_with("<!DOCTYPE html>"), "{}", printed);
+    assert!(!String::from_utf8(output.stderr).unwrap().is_empty());
+}
+
+#[test]
+fn unreadable_standard_input_is_an_io_failure() {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_yamc"))
+        .args(["convert", "-", "--output", "-"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .expect("yamc runs");
+    // not utf-8
+    child.stdin.take().unwrap().write_all(b"\xff\xfe").unwrap();
+    assert_eq!(child.wait().unwrap().code(), Some(3));
+}